@@ -0,0 +1,175 @@
+use std::{
+    collections::{BTreeMap, HashSet, VecDeque},
+    io,
+    path::{Path, PathBuf},
+};
+
+use cargo_metadata::{DependencyKind, Metadata, Package};
+
+pub const MARKDOWN_FILE_NAME: &str = "Acknowledgements.md";
+pub const PLIST_FILE_NAME: &str = "Acknowledgements.plist";
+
+static LICENSE_FILE_PREFIXES: &[&str] = &["LICENSE", "LICENCE", "COPYING", "NOTICE"];
+
+pub struct Acknowledgement {
+    pub name: String,
+    pub version: String,
+    pub license: String,
+    pub texts: Vec<String>,
+}
+
+/// Walks the normal (non-dev, non-build) dependency graph of `package` and
+/// gathers the license of every crate that ends up linked into it.
+pub fn collect(metadata: &Metadata, package: &Package) -> Vec<Acknowledgement> {
+    let resolve = match &metadata.resolve {
+        Some(v) => v,
+        None => return vec![],
+    };
+    let nodes = resolve
+        .nodes
+        .iter()
+        .map(|node| (&node.id, node))
+        .collect::<BTreeMap<_, _>>();
+
+    let mut seen = HashSet::new();
+    let mut queue = VecDeque::from(vec![&package.id]);
+    while let Some(id) = queue.pop_front() {
+        let node = match nodes.get(id) {
+            Some(v) => v,
+            None => continue,
+        };
+        for dep in &node.deps {
+            let is_normal = dep.dep_kinds.is_empty()
+                || dep
+                    .dep_kinds
+                    .iter()
+                    .any(|x| x.kind == DependencyKind::Normal);
+            if is_normal && seen.insert(&dep.pkg) {
+                queue.push_back(&dep.pkg);
+            }
+        }
+    }
+
+    let mut acknowledgements = metadata
+        .packages
+        .iter()
+        .filter(|p| seen.contains(&p.id) && p.id != package.id)
+        .map(|p| Acknowledgement {
+            name: p.name.clone(),
+            version: p.version.to_string(),
+            license: p.license.clone().unwrap_or_else(|| "UNKNOWN".into()),
+            texts: license_texts(p),
+        })
+        .collect::<Vec<_>>();
+    acknowledgements.sort_by(|a, b| (&a.name, &a.version).cmp(&(&b.name, &b.version)));
+    acknowledgements
+}
+
+fn license_texts(package: &Package) -> Vec<String> {
+    let package_dir = package.manifest_path.parent().unwrap();
+
+    let mut paths = std::fs::read_dir(package_dir)
+        .map(|entries| {
+            entries
+                .filter_map(Result::ok)
+                .map(|x| x.path())
+                .filter(|x| x.is_file())
+                .filter(|x| {
+                    let name = x.file_name().unwrap().to_string_lossy().to_uppercase();
+                    LICENSE_FILE_PREFIXES.iter().any(|p| name.starts_with(p))
+                })
+                .collect::<Vec<PathBuf>>()
+        })
+        .unwrap_or_default();
+    if let Some(path) = package.license_file() {
+        if !paths.contains(&path) {
+            paths.push(path);
+        }
+    }
+    paths.sort();
+
+    paths
+        .iter()
+        .filter_map(|x| std::fs::read_to_string(x).ok())
+        .collect()
+}
+
+pub fn to_markdown(acknowledgements: &[Acknowledgement]) -> String {
+    let mut out = String::from("# Acknowledgements\n\nThis software uses the following crates.\n");
+    for ack in acknowledgements {
+        out.push_str(&format!(
+            "\n## {} {}\n\nLicense: {}\n",
+            ack.name, ack.version, ack.license
+        ));
+        for text in &ack.texts {
+            out.push_str("\n```\n");
+            out.push_str(text.trim_end());
+            out.push_str("\n```\n");
+        }
+    }
+    out
+}
+
+fn escape_xml(input: &str) -> String {
+    input
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// Renders the acknowledgements in the Settings bundle plist format used by
+/// CocoaPods' own acknowledgements.
+pub fn to_plist(acknowledgements: &[Acknowledgement]) -> String {
+    let mut out = String::from(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+	<key>PreferenceSpecifiers</key>
+	<array>
+"#,
+    );
+    for ack in acknowledgements {
+        let text = if ack.texts.is_empty() {
+            format!("License: {}", ack.license)
+        } else {
+            ack.texts.join("\n\n")
+        };
+        out.push_str(&format!(
+            "\t\t<dict>
+\t\t\t<key>FooterText</key>
+\t\t\t<string>{}</string>
+\t\t\t<key>License</key>
+\t\t\t<string>{}</string>
+\t\t\t<key>Title</key>
+\t\t\t<string>{} {}</string>
+\t\t\t<key>Type</key>
+\t\t\t<string>PSGroupSpecifier</string>
+\t\t</dict>
+",
+            escape_xml(&text),
+            escape_xml(&ack.license),
+            escape_xml(&ack.name),
+            escape_xml(&ack.version)
+        ));
+    }
+    out.push_str(
+        "\t</array>
+\t<key>StringsTable</key>
+\t<string>Acknowledgements</string>
+\t<key>Title</key>
+\t<string>Acknowledgements</string>
+</dict>
+</plist>
+",
+    );
+    out
+}
+
+/// Writes both the markdown and plist acknowledgements into `dir`.
+pub fn write(dir: &Path, acknowledgements: &[Acknowledgement]) -> io::Result<()> {
+    std::fs::create_dir_all(dir)?;
+    std::fs::write(dir.join(MARKDOWN_FILE_NAME), to_markdown(acknowledgements))?;
+    std::fs::write(dir.join(PLIST_FILE_NAME), to_plist(acknowledgements))?;
+    Ok(())
+}
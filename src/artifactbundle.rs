@@ -0,0 +1,83 @@
+use std::{io, path::Path};
+
+/// The extension `bundle` gives zipped artifact bundles.
+pub const ZIP_SUFFIX: &str = ".artifactbundle.zip";
+
+const INCLUDE_DIR: &str = "include";
+
+/// The triple SwiftPM matches a static library variant by.
+fn swift_triple(triple: &str) -> Option<&'static str> {
+    Some(match triple {
+        "aarch64-apple-darwin" => "arm64-apple-macosx",
+        "x86_64-apple-darwin" => "x86_64-apple-macosx",
+        "aarch64-apple-ios" => "arm64-apple-ios",
+        "aarch64-apple-ios-sim" => "arm64-apple-ios-simulator",
+        "x86_64-apple-ios" => "x86_64-apple-ios-simulator",
+        _ => return None,
+    })
+}
+
+/// Writes `<name>.artifactbundle` into `out_dir`, holding the `lib_name`
+/// static library of each triple built in `dist_dir` and the headers in
+/// `headers_dir`, with a module map exposing them as the `name` module.
+pub fn write(
+    out_dir: &Path,
+    name: &str,
+    version: &str,
+    lib_name: &str,
+    dist_dir: &Path,
+    headers_dir: &Path,
+) -> io::Result<()> {
+    let bundle = out_dir.join(format!("{}.artifactbundle", name));
+    let include = bundle.join(INCLUDE_DIR);
+    std::fs::create_dir_all(&include)?;
+    dircpy::copy_dir(headers_dir, &include)?;
+    let umbrella = format!("{}.h", lib_name);
+    std::fs::write(
+        include.join("module.modulemap"),
+        format!(
+            "module {} {{\n    header \"{}\"\n    export *\n}}\n",
+            name, umbrella
+        ),
+    )?;
+
+    let mut variants = vec![];
+    for triple in crate::IOS_TRIPLES.iter().chain(crate::MACOS_TRIPLES) {
+        let lib = dist_dir.join(triple).join(format!("lib{}.a", lib_name));
+        let swift_triple = match swift_triple(triple) {
+            Some(v) if lib.exists() => v,
+            _ => continue,
+        };
+        std::fs::create_dir_all(bundle.join(triple))?;
+        std::fs::copy(&lib, bundle.join(triple).join(lib.file_name().unwrap()))?;
+        variants.push(serde_json::json!({
+            "path": format!("{}/lib{}.a", triple, lib_name),
+            "supportedTriples": [swift_triple],
+            "staticLibraryMetadata": {
+                "headerPaths": [INCLUDE_DIR],
+                "moduleMapPath": format!("{}/module.modulemap", INCLUDE_DIR),
+            },
+        }));
+    }
+    if variants.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("No lib{}.a built in {}", lib_name, dist_dir.display()),
+        ));
+    }
+
+    let info = serde_json::json!({
+        "schemaVersion": "1.0",
+        "artifacts": {
+            name: {
+                "type": "staticLibrary",
+                "version": version,
+                "variants": variants,
+            },
+        },
+    });
+    std::fs::write(
+        bundle.join("info.json"),
+        serde_json::to_string_pretty(&info).unwrap(),
+    )
+}
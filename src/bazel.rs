@@ -0,0 +1,38 @@
+pub const FILE_NAME: &str = "BUILD.bazel";
+
+/// A BUILD file importing the pod's xcframeworks with rules_apple, for apps
+/// built with Bazel. It sits next to the xcframeworks in `dist`.
+pub struct BuildBazel {
+    /// The Swift and FFI module names of each crate target.
+    pub targets: Vec<(String, String)>,
+}
+
+impl std::fmt::Display for BuildBazel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(
+            "load(\"@build_bazel_rules_apple//apple:apple.bzl\", \"apple_static_xcframework_import\")\n",
+        )?;
+        for (mod_name, ffi_mod_name) in &self.targets {
+            f.write_str("\napple_static_xcframework_import(\n")?;
+            f.write_fmt(format_args!("    name = \"{}\",\n", ffi_mod_name))?;
+            f.write_fmt(format_args!(
+                "    xcframework_imports = glob([\"{}.xcframework/**\"]),\n",
+                ffi_mod_name
+            ))?;
+            f.write_str("    visibility = [\"//visibility:public\"],\n")?;
+            f.write_str(")\n")?;
+
+            f.write_str("\napple_static_xcframework_import(\n")?;
+            f.write_fmt(format_args!("    name = \"{}\",\n", mod_name))?;
+            f.write_fmt(format_args!(
+                "    xcframework_imports = glob([\"{}.xcframework/**\"]),\n",
+                mod_name
+            ))?;
+            f.write_str("    has_swift = True,\n")?;
+            f.write_fmt(format_args!("    deps = [\":{}\"],\n", ffi_mod_name))?;
+            f.write_str("    visibility = [\"//visibility:public\"],\n")?;
+            f.write_str(")\n")?;
+        }
+        Ok(())
+    }
+}
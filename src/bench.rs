@@ -0,0 +1,89 @@
+use std::{
+    io,
+    path::{Path, PathBuf},
+    process::{Command, ExitStatus},
+};
+
+use crate::cmd::{current_sdk, current_triple, MinVersions};
+
+/// Where `cargo pod bench` looks for the harness, next to the podspec.
+pub const DIR_NAME: &str = "bench";
+
+const MIN_MACOS_VERSION: &str = "11.0";
+
+/// Compiled in with the harness, which calls `bench` from its `main.swift`.
+const PRELUDE: &str = r#"import Foundation
+
+/// Times `iterations` calls of `body` after a warm-up, printing the time per
+/// call and calls per second.
+func bench(_ name: String, iterations: Int = 1_000_000, _ body: () -> Void) {
+    for _ in 0..<min(iterations, 1_000) {
+        body()
+    }
+    let start = DispatchTime.now().uptimeNanoseconds
+    for _ in 0..<iterations {
+        body()
+    }
+    let elapsed = Double(DispatchTime.now().uptimeNanoseconds - start)
+    let perCall = elapsed / Double(iterations)
+    print(String(
+        format: "%@: %.1f ns/call, %.0f calls/s (%d iterations)",
+        name, perCall, 1e9 / perCall, iterations
+    ))
+}
+"#;
+
+/// Compiles the harness `sources` for `triple` into `out_dir`, linked against
+/// the `modules` frameworks in `frameworks_dir`.
+pub fn build(
+    triple: &str,
+    frameworks_dir: &Path,
+    modules: &[String],
+    sources: &[PathBuf],
+    out_dir: &Path,
+) -> io::Result<PathBuf> {
+    std::fs::create_dir_all(out_dir)?;
+    let prelude = out_dir.join("CargoPodBench.swift");
+    std::fs::write(&prelude, PRELUDE)?;
+    let executable = out_dir.join("bench");
+
+    let min_versions = MinVersions {
+        macos: MIN_MACOS_VERSION.into(),
+        ..Default::default()
+    };
+    let mut cmd = Command::new("swiftc");
+    cmd.args(["-O", "-module-name", "Bench"])
+        .arg("-sdk")
+        .arg(current_sdk(triple)?)
+        .arg("-target")
+        .arg(current_triple(triple, &min_versions)?)
+        .arg("-F")
+        .arg(frameworks_dir);
+    for module in modules {
+        cmd.args(["-framework", module]);
+    }
+    cmd.arg(&prelude).args(sources).arg("-o").arg(&executable);
+
+    if crate::cmd::skip(&cmd) {
+        return Ok(executable);
+    }
+    log::trace!("Calling: {:?}", &cmd);
+    let status = crate::cmd::status(&mut cmd)?;
+    if !status.success() {
+        return Err(io::Error::new(
+            io::ErrorKind::Other,
+            format!("Failed to compile the benchmarks for {}", triple),
+        ));
+    }
+    Ok(executable)
+}
+
+/// Runs the harness, under Rosetta where it is not built for the host.
+pub fn run(executable: &Path) -> io::Result<ExitStatus> {
+    let mut cmd = Command::new(executable);
+    if crate::cmd::skip(&cmd) {
+        return Ok(ExitStatus::default());
+    }
+    log::trace!("Calling: {:?}", &cmd);
+    crate::cmd::status(&mut cmd)
+}
@@ -0,0 +1,761 @@
+//! Building the crate's static libraries and assembling the xcframeworks.
+
+use std::path::{Path, PathBuf};
+
+use cargo_metadata::{Metadata, Package, Target};
+use heck::CamelCase;
+use jwalk::WalkDir;
+
+use crate::{
+    cmd::{lipo, Ar, MinVersions, Swiftc, Xcodebuild},
+    error::Error,
+    pod::{
+        derive_manifest_with, framework_names, pod_manifest, pod_name, resolve_dist_dir,
+        resolve_pod_root, run_hook, ManifestOptions,
+    },
+    podspec::sync_podspec_version,
+    timings::Format as TimingsFormat,
+    IOS_TRIPLES, MACOS_TRIPLES,
+};
+
+/// What [`build`] builds, as the options of `cargo pod build`.
+#[derive(Debug, Clone, Default)]
+pub struct BuildOptions {
+    /// The crate's manifest; found from the current directory if not given.
+    pub manifest_path: Option<PathBuf>,
+    pub macos_only: bool,
+    pub ios_only: bool,
+    /// Fail if the podspec version does not match the crate.
+    pub strict: bool,
+    /// Keep debug info in the static libraries, so that the app's dSYM
+    /// covers the crate.
+    pub debug_info: bool,
+    /// Add the staticlib crate-type to Cargo.toml if it is missing.
+    pub fix: bool,
+    /// Also write a `BUILD.bazel` importing the xcframeworks.
+    pub bazel: bool,
+    /// Also write a Tuist project description helper.
+    pub tuist: bool,
+    /// Also write a report of how long each step took to `dist`.
+    pub timings: Option<TimingsFormat>,
+    /// Passed on to `cargo build`.
+    pub cargo_args: Vec<String>,
+}
+
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn build_static_libs(
+    mut cargo_args: Vec<String>,
+    metadata: &Metadata,
+    package: &Package,
+    targets: &[Target],
+    dist_dir: &Path,
+    build_target: BuildTarget,
+    min_versions: &MinVersions,
+    debug_info: bool,
+) -> Result<(), Error> {
+    let package_dir = package.manifest_path.parent().unwrap();
+
+    if cargo_args.contains(&"--target".into()) {
+        return Err(Error::Usage(
+            "Do not pass --target to the cargo args, we handle that!".into(),
+        ));
+    }
+
+    if !cargo_args.contains(&"--release".into()) {
+        cargo_args.push("--release".into())
+    }
+
+    if !cargo_args.contains(&"--lib".into()) {
+        cargo_args.push("--lib".into())
+    }
+
+    let mut lib_paths = vec![];
+    let phase = crate::progress::Phase::new("Building", build_target.triples().count() as u64);
+
+    if build_target.is_ios() {
+        for triple in IOS_TRIPLES {
+            crate::fs::create_dir_all(dist_dir.join(triple))?;
+
+            phase.step_with_output(&format!("cargo build --target {}", triple), || {
+                crate::cargo::build(
+                    package_dir,
+                    triple,
+                    &cargo_args,
+                    min_versions,
+                    false,
+                    None,
+                    debug_info,
+                )
+            })?;
+
+            for target in targets {
+                lib_paths.push((
+                    triple,
+                    metadata
+                        .target_directory
+                        .join(triple)
+                        .join("release")
+                        .join(format!("lib{}.a", target.name.replace('-', "_"))),
+                ));
+            }
+        }
+    }
+
+    if build_target.is_macos() {
+        for triple in MACOS_TRIPLES {
+            crate::fs::create_dir_all(dist_dir.join(triple))?;
+
+            phase.step_with_output(&format!("cargo build --target {}", triple), || {
+                crate::cargo::build(
+                    package_dir,
+                    triple,
+                    &cargo_args,
+                    min_versions,
+                    false,
+                    None,
+                    debug_info,
+                )
+            })?;
+
+            for target in targets {
+                lib_paths.push((
+                    triple,
+                    metadata
+                        .target_directory
+                        .join(triple)
+                        .join("release")
+                        .join(format!("lib{}.a", target.name.replace('-', "_"))),
+                ));
+            }
+        }
+    }
+
+    phase.time("copy static libraries", || -> Result<(), Error> {
+        for (triple, path) in lib_paths {
+            let dest = dist_dir.join(triple).join(path.file_name().unwrap());
+            crate::fs::copy(&path, &dest)?;
+        }
+        Ok(())
+    })?;
+    phase.finish();
+    Ok(())
+}
+
+#[allow(non_camel_case_types)]
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum BuildTarget {
+    _iOS,
+    MacOS,
+    Both,
+}
+
+impl BuildTarget {
+    /// The platforms given by `--ios` and `--macos`, or by the `platforms`
+    /// setting when neither is.
+    pub(crate) fn new(is_ios: bool, is_macos: bool, config: &crate::meta::Config) -> BuildTarget {
+        let (is_ios, is_macos) = match (is_ios, is_macos) {
+            (false, false) if !config.platforms.is_empty() => (
+                config.platforms.contains(&crate::meta::Platform::Ios),
+                config.platforms.contains(&crate::meta::Platform::Macos),
+            ),
+            v => v,
+        };
+        match (is_ios, is_macos) {
+            (true, true) | (false, false) => BuildTarget::Both,
+            (true, false) => BuildTarget::_iOS,
+            (false, true) => BuildTarget::MacOS,
+        }
+    }
+
+    pub(crate) fn is_ios(&self) -> bool {
+        matches!(self, BuildTarget::_iOS | BuildTarget::Both)
+    }
+
+    pub(crate) fn is_macos(&self) -> bool {
+        matches!(self, BuildTarget::MacOS | BuildTarget::Both)
+    }
+
+    pub(crate) fn triples(&self) -> impl Iterator<Item = &'_ str> {
+        const MAC: &[&str] = &["aarch64-apple-darwin", "x86_64-apple-darwin"];
+        const IOS: &[&str] = &[
+            "aarch64-apple-ios",
+            "aarch64-apple-ios-sim",
+            "x86_64-apple-ios",
+        ];
+        IOS.iter()
+            .filter(|_| self.is_ios())
+            .chain(MAC.iter().filter(|_| self.is_macos()))
+            .copied()
+    }
+
+    pub(crate) fn framework_targets(&self) -> impl Iterator<Item = &'_ str> {
+        const MAC: &[&str] = &["macos-universal"];
+        const IOS: &[&str] = &["aarch64-apple-ios", "ios-simulator"];
+        IOS.iter()
+            .filter(|_| self.is_ios())
+            .chain(MAC.iter().filter(|_| self.is_macos()))
+            .copied()
+    }
+}
+
+pub(crate) fn build_safe_frameworks(
+    package: &Package,
+    targets: &[Target],
+    dist_dir: &Path,
+    build_target: BuildTarget,
+    min_versions: &MinVersions,
+) -> Result<(), Error> {
+    let package_dir = package.manifest_path.parent().unwrap();
+    let bindings_path = package_dir.join("bindings");
+
+    let swift_files = WalkDir::new(&bindings_path)
+        .into_iter()
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_type().is_file())
+        .map(|entry| entry.path())
+        .collect::<Vec<_>>();
+
+    // Per target: swiftc for each triple, lipo for each fat slice and two
+    // `xcodebuild -create-xcframework` runs.
+    let triples = build_target.triples().count() as u64;
+    let platforms = build_target.is_ios() as u64 + build_target.is_macos() as u64;
+    let phase = crate::progress::Phase::new(
+        "Swift frameworks",
+        targets.len() as u64 * (triples + platforms + 2),
+    );
+
+    for target in targets {
+        let sys_name = target.name.replace('-', "_");
+        let ffi_mod_name = format!("{sys_name}_ffi").to_camel_case();
+        let ffi_fw_name = format!("{ffi_mod_name}.framework");
+
+        let mod_name = target.name.replace('-', "_").to_string().to_camel_case();
+        let fw_name = format!("{mod_name}.framework");
+
+        for triple in build_target.triples() {
+            let triple_dir = dist_dir.join(triple);
+            let ffi_fw_dir = triple_dir.join(&ffi_fw_name);
+            let fw_dir = triple_dir.join(&fw_name);
+
+            phase.time(
+                &format!("assemble {} for {}", mod_name, triple),
+                || -> Result<(), Error> {
+                    crate::fs::create_dir_all(&fw_dir)?;
+                    crate::fs::copy_dir(&ffi_fw_dir, &fw_dir)?;
+                    crate::fs::write(
+                        fw_dir.join("Info.plist"),
+                        INFO_PLIST.replace("%BUNDLE_NAME%", &mod_name),
+                    )?;
+                    crate::fs::rename(fw_dir.join("Headers"), fw_dir.join("PrivateHeaders"))?;
+                    crate::fs::rename(fw_dir.join(&ffi_mod_name), fw_dir.join(&mod_name))?;
+                    crate::fs::write(
+                        fw_dir.join("Modules").join("module.modulemap"),
+                        format!(
+                            "framework module {mod_name} {{
+}}"
+                        ),
+                    )?;
+
+                    crate::fs::write(
+                        fw_dir.join("Modules").join("module.private.modulemap"),
+                        format!(
+                            "framework module {mod_name}_Private {{
+    header \"{sys_name}.h\"
+    link \"{mod_name}\"
+}}"
+                        ),
+                    )?;
+                    Ok(())
+                },
+            )?;
+
+            // Build the bindings
+            let obj_path = phase.step(&format!("swiftc {} for {}", mod_name, triple), || {
+                Swiftc::build(triple, min_versions, &mod_name, &triple_dir, &swift_files)
+            })?;
+            phase.time(
+                &format!("add {} swiftmodule for {}", mod_name, triple),
+                || -> Result<(), Error> {
+                    Ar::insert(&fw_dir.join(&mod_name), &obj_path)?;
+                    let swift_mod_path = fw_dir
+                        .join("Modules")
+                        .join(format!("{mod_name}.swiftmodule"));
+                    crate::fs::create_dir_all(&swift_mod_path)?;
+                    let arch = current_arch(triple);
+                    for ext in [
+                        "swiftdoc",
+                        "swiftmodule",
+                        "swiftsourceinfo",
+                        "abi.json",
+                        "swiftinterface",
+                    ] {
+                        crate::fs::rename(
+                            format!("{mod_name}.{ext}"),
+                            swift_mod_path.join(format!("{arch}.{ext}")),
+                        )?;
+                    }
+                    log::debug!("Deleting {}", &obj_path);
+                    crate::fs::remove_file(obj_path)?;
+                    crate::fs::remove_file(format!("{mod_name}.private.swiftinterface"))?;
+                    Ok(())
+                },
+            )?;
+        }
+
+        if build_target.is_ios() {
+            let output_path = dist_dir.join("ios-simulator").join(&fw_name);
+            crate::fs::create_dir_all(&output_path)?;
+            crate::fs::write(
+                output_path.join("Info.plist"),
+                INFO_PLIST.replace("%BUNDLE_NAME%", &mod_name),
+            )?;
+            let lipo_1 = dist_dir
+                .join("aarch64-apple-ios-sim")
+                .join(&fw_name)
+                .join(&mod_name);
+            let lipo_2 = dist_dir
+                .join("x86_64-apple-ios")
+                .join(&fw_name)
+                .join(&mod_name);
+
+            phase.step(&format!("lipo {} for ios-simulator", mod_name), || {
+                lipo([lipo_1, lipo_2].iter(), &output_path.join(&mod_name))
+            })?;
+
+            phase.time(
+                &format!("copy {} for ios-simulator", mod_name),
+                || -> Result<(), Error> {
+                    crate::fs::copy_dir(
+                        dist_dir
+                            .join("aarch64-apple-ios-sim")
+                            .join(&fw_name)
+                            .join("PrivateHeaders"),
+                        output_path.join("PrivateHeaders"),
+                    )?;
+                    crate::fs::copy_dir(
+                        dist_dir
+                            .join("x86_64-apple-ios")
+                            .join(&fw_name)
+                            .join("Modules"),
+                        output_path.join("Modules"),
+                    )?;
+                    crate::fs::copy_dir(
+                        dist_dir
+                            .join("aarch64-apple-ios-sim")
+                            .join(&fw_name)
+                            .join("Modules"),
+                        output_path.join("Modules"),
+                    )?;
+                    Ok(())
+                },
+            )?;
+        }
+
+        if build_target.is_macos() {
+            let output_path = dist_dir.join("macos-universal").join(&fw_name);
+            crate::fs::create_dir_all(&output_path)?;
+            crate::fs::write(
+                output_path.join("Info.plist"),
+                INFO_PLIST.replace("%BUNDLE_NAME%", &mod_name),
+            )?;
+            phase.step(&format!("lipo {} for macos-universal", mod_name), || {
+                lipo(
+                    [
+                        dist_dir
+                            .join("aarch64-apple-darwin")
+                            .join(&fw_name)
+                            .join(&mod_name),
+                        dist_dir
+                            .join("x86_64-apple-darwin")
+                            .join(&fw_name)
+                            .join(&mod_name),
+                    ]
+                    .iter(),
+                    &output_path.join(&mod_name),
+                )
+            })?;
+
+            phase.time(
+                &format!("copy {} for macos-universal", mod_name),
+                || -> Result<(), Error> {
+                    crate::fs::copy_dir(
+                        dist_dir
+                            .join("aarch64-apple-darwin")
+                            .join(&fw_name)
+                            .join("PrivateHeaders"),
+                        output_path.join("PrivateHeaders"),
+                    )?;
+                    crate::fs::copy_dir(
+                        dist_dir
+                            .join("x86_64-apple-darwin")
+                            .join(&fw_name)
+                            .join("Modules"),
+                        output_path.join("Modules"),
+                    )?;
+                    crate::fs::copy_dir(
+                        dist_dir
+                            .join("aarch64-apple-darwin")
+                            .join(&fw_name)
+                            .join("Modules"),
+                        output_path.join("Modules"),
+                    )?;
+                    Ok(())
+                },
+            )?;
+        }
+
+        for name in [&mod_name, &ffi_mod_name] {
+            phase.step(&format!("xcodebuild -create-xcframework {}", name), || {
+                Xcodebuild::create_xcframework_frameworks(
+                    name,
+                    build_target
+                        .framework_targets()
+                        .map(|x| dist_dir.join(x).join(format!("{name}.framework"))),
+                    dist_dir,
+                )
+            })?;
+        }
+    }
+    phase.finish();
+    Ok(())
+}
+
+pub(crate) fn current_arch(triple: &str) -> &str {
+    if triple.starts_with("aarch64-") {
+        return "arm64";
+    }
+
+    if triple.starts_with("x86_64-") {
+        return "x86_64";
+    }
+
+    panic!("unsupported triple: {}", triple);
+}
+
+pub(crate) const INFO_PLIST: &str = r#"
+<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+	<key>CFBundleExecutable</key>
+	<string>%BUNDLE_NAME%</string>
+	<key>CFBundleIdentifier</key>
+	<string>internal.cargo-cocoapods.%BUNDLE_NAME%</string>
+	<key>CFBundleInfoDictionaryVersion</key>
+	<string>6.0</string>
+	<key>CFBundleName</key>
+	<string>%BUNDLE_NAME%</string>
+	<key>CFBundlePackageType</key>
+	<string>FMWK</string>
+</dict>
+</plist>
+"#;
+
+pub(crate) fn build_ffi_frameworks(
+    package: &Package,
+    targets: &[Target],
+    dist_dir: &Path,
+    build_target: BuildTarget,
+) -> Result<(), Error> {
+    let package_dir = package.manifest_path.parent().unwrap();
+    let headers_path = package_dir.join("headers");
+    let platforms = build_target.is_ios() as u64 + build_target.is_macos() as u64;
+    let phase = crate::progress::Phase::new("FFI frameworks", targets.len() as u64 * platforms);
+
+    for target in targets {
+        let sys_name = target.name.replace('-', "_");
+        let mod_name = format!("{sys_name}_ffi").to_camel_case();
+        let fw_name = format!("{mod_name}.framework");
+
+        for triple in build_target.triples() {
+            let triple_dir = dist_dir.join(triple);
+            let fw_dir = triple_dir.join(&fw_name);
+
+            phase.time(
+                &format!("assemble {} for {}", mod_name, triple),
+                || -> Result<(), Error> {
+                    let headers_dir = fw_dir.join("Headers");
+                    crate::fs::create_dir_all(&fw_dir)?;
+                    crate::fs::create_dir_all(&headers_dir)?;
+                    crate::fs::create_dir_all(fw_dir.join("Modules"))?;
+                    crate::fs::write(
+                        fw_dir.join("Info.plist"),
+                        INFO_PLIST.replace("%BUNDLE_NAME%", &mod_name),
+                    )?;
+
+                    crate::fs::copy_dir(&headers_path, &headers_dir)?;
+
+                    crate::fs::copy(
+                        triple_dir.join(format!("lib{sys_name}.a")),
+                        fw_dir.join(&mod_name),
+                    )?;
+
+                    crate::fs::write(
+                        fw_dir.join("Modules").join("module.modulemap"),
+                        format!(
+                            "framework module {mod_name} {{
+    header \"{sys_name}.h\"
+    link \"{mod_name}\"
+}}"
+                        ),
+                    )?;
+                    Ok(())
+                },
+            )?;
+        }
+
+        if build_target.is_ios() {
+            let output_path = dist_dir.join("ios-simulator").join(&fw_name);
+            crate::fs::create_dir_all(&output_path)?;
+            phase.step(&format!("lipo {} for ios-simulator", mod_name), || {
+                lipo(
+                    [
+                        dist_dir
+                            .join("aarch64-apple-ios-sim")
+                            .join(&fw_name)
+                            .join(&mod_name),
+                        dist_dir
+                            .join("x86_64-apple-ios")
+                            .join(&fw_name)
+                            .join(&mod_name),
+                    ]
+                    .iter(),
+                    &output_path.join(&mod_name),
+                )
+            })?;
+
+            phase.time(
+                &format!("copy {} for ios-simulator", mod_name),
+                || -> Result<(), Error> {
+                    crate::fs::copy_dir(
+                        dist_dir
+                            .join("aarch64-apple-ios-sim")
+                            .join(&fw_name)
+                            .join("Headers"),
+                        output_path.join("Headers"),
+                    )?;
+                    crate::fs::copy_dir(
+                        dist_dir
+                            .join("aarch64-apple-ios-sim")
+                            .join(&fw_name)
+                            .join("Modules"),
+                        output_path.join("Modules"),
+                    )?;
+                    crate::fs::write(
+                        output_path.join("Info.plist"),
+                        INFO_PLIST.replace("%BUNDLE_NAME%", &mod_name),
+                    )?;
+                    Ok(())
+                },
+            )?;
+        }
+
+        if build_target.is_macos() {
+            let output_path = dist_dir.join("macos-universal").join(&fw_name);
+            crate::fs::create_dir_all(&output_path)?;
+            phase.step(&format!("lipo {} for macos-universal", mod_name), || {
+                lipo(
+                    [
+                        dist_dir
+                            .join("aarch64-apple-darwin")
+                            .join(&fw_name)
+                            .join(&mod_name),
+                        dist_dir
+                            .join("x86_64-apple-darwin")
+                            .join(&fw_name)
+                            .join(&mod_name),
+                    ]
+                    .iter(),
+                    &output_path.join(&mod_name),
+                )
+            })?;
+
+            phase.time(
+                &format!("copy {} for macos-universal", mod_name),
+                || -> Result<(), Error> {
+                    crate::fs::copy_dir(
+                        dist_dir
+                            .join("aarch64-apple-darwin")
+                            .join(&fw_name)
+                            .join("Headers"),
+                        output_path.join("Headers"),
+                    )?;
+                    crate::fs::copy_dir(
+                        dist_dir
+                            .join("aarch64-apple-darwin")
+                            .join(&fw_name)
+                            .join("Modules"),
+                        output_path.join("Modules"),
+                    )?;
+                    crate::fs::write(
+                        output_path.join("Info.plist"),
+                        INFO_PLIST.replace("%BUNDLE_NAME%", &mod_name),
+                    )?;
+                    Ok(())
+                },
+            )?;
+        }
+    }
+    phase.finish();
+    Ok(())
+}
+
+/// Builds the static libraries and xcframeworks into `dist`, like
+/// `cargo pod build`.
+pub fn build(options: BuildOptions) -> Result<(), Error> {
+    let started = std::time::Instant::now();
+    let has_subtree = std::fs::read_dir("./crate").is_ok();
+    let (metadata, package, targets) = derive_manifest_with(
+        pod_manifest(options.manifest_path.as_deref(), has_subtree),
+        ManifestOptions {
+            fix: options.fix,
+            ..Default::default()
+        },
+    )?;
+
+    let dist_dir = resolve_dist_dir(&metadata, &package, has_subtree);
+    crate::fs::create_dir_all(&dist_dir)?;
+    let pod_root = resolve_pod_root(&dist_dir);
+
+    let config = crate::meta::config(&package);
+    let build_target = BuildTarget::new(options.ios_only, options.macos_only, &config);
+
+    sync_podspec_version(&pod_root, &package, options.strict)?;
+
+    let min_versions = config.min_versions();
+    let mut cargo_args = options.cargo_args;
+    if !config.features.is_empty() && !cargo_args.contains(&"--features".into()) {
+        cargo_args.push("--features".into());
+        cargo_args.push(config.features.join(","));
+    }
+
+    run_hook("pre_build", config.hooks.pre_build.as_deref(), &package)?;
+
+    let marker = dist_dir.join(INCOMPLETE_MARKER);
+    if marker.exists() {
+        log::warn!(
+            "The last build in {} did not finish; removing what it left behind",
+            dist_dir.display()
+        );
+        remove_incomplete_build(&dist_dir, &targets);
+    }
+    crate::fs::write(&marker, "")?;
+    let result = build_static_libs(
+        cargo_args,
+        &metadata,
+        &package,
+        &targets,
+        &dist_dir,
+        build_target,
+        &min_versions,
+        options.debug_info,
+    )
+    .and_then(|_| build_ffi_frameworks(&package, &targets, &dist_dir, build_target))
+    .and_then(|_| {
+        build_safe_frameworks(&package, &targets, &dist_dir, build_target, &min_versions)
+    });
+    if result.is_err() {
+        remove_incomplete_build(&dist_dir, &targets);
+    }
+    result?;
+    crate::fs::remove_file(&marker)?;
+    for target in &targets {
+        let (mod_name, ffi_mod_name) = framework_names(target);
+        for name in [ffi_mod_name, mod_name] {
+            crate::message::artifact(
+                "xcframework",
+                &dist_dir.join(format!("{}.xcframework", name)),
+            );
+        }
+    }
+
+    if options.bazel {
+        let path = dist_dir.join(crate::bazel::FILE_NAME);
+        let build_bazel = crate::bazel::BuildBazel {
+            targets: targets.iter().map(framework_names).collect(),
+        };
+        log::info!("Writing {}", path.display());
+        crate::fs::write(&path, build_bazel.to_string())?;
+        crate::message::artifact("bazel", &path);
+    }
+
+    if options.tuist {
+        let helper = crate::tuist::Helper {
+            pod_name: pod_name(&package),
+            targets: targets.iter().map(framework_names).collect(),
+        };
+        let path = dist_dir.join(helper.file_name());
+        log::info!("Writing {}", path.display());
+        crate::fs::write(&path, helper.to_string())?;
+        crate::message::artifact("tuist", &path);
+        log::info!("Copy it into Tuist/ProjectDescriptionHelpers to depend on the xcframeworks.");
+    }
+
+    crate::timings::log_summary(started);
+    if let Some(format) = options.timings {
+        let path = dist_dir.join(format.file_name());
+        log::info!("Writing {}", path.display());
+        crate::fs::write(&path, crate::timings::report(format, started))?;
+        crate::message::artifact("timings", &path);
+    }
+    run_hook("post_build", config.hooks.post_build.as_deref(), &package)
+}
+
+/// Left in dist while it is being built. If it is still there,
+/// a build was interrupted or failed before it could clean up, and dist must
+/// not be bundled.
+pub(crate) const INCOMPLETE_MARKER: &str = ".cargo-pod-incomplete";
+
+/// Removes what a failed or interrupted build of `targets` left behind: its
+/// static libraries, xcframeworks, the frameworks they are assembled from and
+/// swiftc's output,
+/// so that nothing half-written is bundled or trips up the next build. The
+/// marker is removed once everything is gone.
+pub(crate) fn remove_incomplete_build(dist_dir: &Path, targets: &[Target]) {
+    let framework_dirs = IOS_TRIPLES
+        .iter()
+        .chain(MACOS_TRIPLES.iter())
+        .chain(["ios-simulator", "macos-universal"].iter());
+    let mut paths = vec![];
+    for target in targets {
+        let (mod_name, ffi_mod_name) = framework_names(target);
+        let lib_name = format!("lib{}.a", target.name.replace('-', "_"));
+        for triple in IOS_TRIPLES.iter().chain(MACOS_TRIPLES.iter()) {
+            paths.push(dist_dir.join(triple).join(&lib_name));
+        }
+        for name in [&mod_name, &ffi_mod_name] {
+            paths.push(dist_dir.join(format!("{}.xcframework", name)));
+            for dir in framework_dirs.clone() {
+                paths.push(dist_dir.join(dir).join(format!("{}.framework", name)));
+            }
+        }
+        for ext in [
+            "o",
+            "swiftdoc",
+            "swiftmodule",
+            "swiftsourceinfo",
+            "abi.json",
+            "swiftinterface",
+            "private.swiftinterface",
+        ] {
+            paths.push(PathBuf::from(format!("{}.{}", mod_name, ext)));
+        }
+    }
+
+    let mut is_clean = true;
+    for path in paths.iter().filter(|x| x.exists()) {
+        log::warn!("Removing {}", path.display());
+        let result = if path.is_dir() {
+            crate::fs::remove_dir_all(path)
+        } else {
+            crate::fs::remove_file(path)
+        };
+        if let Err(e) = result {
+            log::warn!("{}", e);
+            is_clean = false;
+        }
+    }
+    if is_clean {
+        let _ = crate::fs::remove_file(dist_dir.join(INCOMPLETE_MARKER));
+    }
+}
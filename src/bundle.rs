@@ -0,0 +1,530 @@
+use std::{
+    fs::File,
+    io::{self, Read, Write},
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use jwalk::WalkDir;
+use serde::Deserialize;
+
+use crate::{error::Error, meta::BundleConfig};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ArchiveFormat {
+    #[serde(alias = "tgz")]
+    Tar,
+    Zip,
+}
+
+impl ArchiveFormat {
+    pub(crate) fn default_output(&self, compression: Compression) -> String {
+        match self {
+            ArchiveFormat::Tar => format!("cargo-pod.{}", compression.tar_extension()),
+            ArchiveFormat::Zip => "cargo-pod.zip".into(),
+        }
+    }
+}
+
+impl Default for ArchiveFormat {
+    fn default() -> Self {
+        ArchiveFormat::Tar
+    }
+}
+
+impl FromStr for ArchiveFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "tar" | "tgz" => Ok(ArchiveFormat::Tar),
+            "zip" => Ok(ArchiveFormat::Zip),
+            other => Err(format!("unsupported archive format: {}", other)),
+        }
+    }
+}
+
+/// How the bundle archive is written.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct ArchiveOptions {
+    pub format: ArchiveFormat,
+    pub compression: Compression,
+    pub level: Option<u32>,
+}
+
+/// Compression applied to tar archives.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Compression {
+    Gzip,
+    Zstd,
+    Xz,
+}
+
+impl Compression {
+    pub(crate) fn tar_extension(&self) -> &'static str {
+        match self {
+            Compression::Gzip => "tgz",
+            Compression::Zstd => "tar.zst",
+            Compression::Xz => "tar.xz",
+        }
+    }
+
+    /// The highest `level` the encoder accepts.
+    pub(crate) fn max_level(&self) -> u32 {
+        match self {
+            Compression::Gzip | Compression::Xz => 9,
+            Compression::Zstd => 22,
+        }
+    }
+
+    pub(crate) fn encoder<W: Write>(
+        &self,
+        output: W,
+        level: Option<u32>,
+    ) -> io::Result<Encoder<W>> {
+        Ok(match self {
+            Compression::Gzip => Encoder::Gzip(flate2::write::GzEncoder::new(
+                output,
+                flate2::Compression::new(level.unwrap_or(6)),
+            )),
+            Compression::Zstd => Encoder::Zstd(zstd::Encoder::new(
+                output,
+                level.map(|x| x as i32).unwrap_or(0),
+            )?),
+            Compression::Xz => Encoder::Xz(xz2::write::XzEncoder::new(output, level.unwrap_or(6))),
+        })
+    }
+}
+
+impl Compression {
+    pub(crate) fn decoder<'a, R: Read + 'a>(&self, input: R) -> io::Result<Box<dyn Read + 'a>> {
+        Ok(match self {
+            Compression::Gzip => Box::new(flate2::read::GzDecoder::new(input)),
+            Compression::Zstd => Box::new(zstd::Decoder::new(input)?),
+            Compression::Xz => Box::new(xz2::read::XzDecoder::new(input)),
+        })
+    }
+}
+
+pub(crate) enum Encoder<W: Write> {
+    Gzip(flate2::write::GzEncoder<W>),
+    Zstd(zstd::Encoder<'static, W>),
+    Xz(xz2::write::XzEncoder<W>),
+}
+
+impl<W: Write> Encoder<W> {
+    pub(crate) fn finish(self) -> io::Result<W> {
+        match self {
+            Encoder::Gzip(x) => x.finish(),
+            Encoder::Zstd(x) => x.finish(),
+            Encoder::Xz(x) => x.finish(),
+        }
+    }
+}
+
+impl<W: Write> Write for Encoder<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Encoder::Gzip(x) => x.write(buf),
+            Encoder::Zstd(x) => x.write(buf),
+            Encoder::Xz(x) => x.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Encoder::Gzip(x) => x.flush(),
+            Encoder::Zstd(x) => x.flush(),
+            Encoder::Xz(x) => x.flush(),
+        }
+    }
+}
+
+impl Default for Compression {
+    fn default() -> Self {
+        Compression::Gzip
+    }
+}
+
+impl FromStr for Compression {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "gzip" | "gz" => Ok(Compression::Gzip),
+            "zstd" | "zst" => Ok(Compression::Zstd),
+            "xz" => Ok(Compression::Xz),
+            other => Err(format!("unsupported compression: {}", other)),
+        }
+    }
+}
+
+/// The content type to upload an archive with, based on its extension.
+pub(crate) fn content_type(path: &Path) -> &'static str {
+    let name = path
+        .file_name()
+        .map(|x| x.to_string_lossy().to_string())
+        .unwrap_or_default();
+    if name == crate::checksum::SUMS_FILE_NAME || name.ends_with(".asc") {
+        "text/plain"
+    } else if name.ends_with(".zip") {
+        "application/zip"
+    } else if name.ends_with(".zst") {
+        "application/zstd"
+    } else if name.ends_with(".xz") {
+        "application/x-xz"
+    } else {
+        "application/x-gtar"
+    }
+}
+
+trait IoResultExt<T> {
+    fn at(self, path: &Path) -> Result<T, Error>;
+}
+
+impl<T> IoResultExt<T> for io::Result<T> {
+    fn at(self, path: &Path) -> Result<T, Error> {
+        self.map_err(Error::io(path))
+    }
+}
+
+// The podspec is left out: bundling sets its `:sha256` to the archive's hash,
+// so archiving it would change the archive every time.
+static DEFAULT_ROOT_FILES: &[&str] = &["LICENSE", "LICENSE*", "README", "README*"];
+
+fn glob_set(patterns: &[String]) -> Result<GlobSet, Error> {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        builder.add(Glob::new(pattern).map_err(invalid_pattern)?);
+    }
+    builder.build().map_err(invalid_pattern)
+}
+
+fn invalid_pattern(e: globset::Error) -> Error {
+    Error::Usage(format!("invalid pattern: {}", e))
+}
+
+fn non_utf8_path(path: &Path) -> Error {
+    Error::Usage(format!("{}: path is not valid UTF-8", path.display()))
+}
+
+fn walk_files(root: &Path, path: &Path, files: &mut Vec<PathBuf>) -> Result<(), Error> {
+    let full_path = root.join(path);
+    if full_path.is_file() {
+        files.push(path.to_path_buf());
+        return Ok(());
+    }
+
+    for entry in WalkDir::new(&full_path) {
+        let entry = entry.map_err(io::Error::from).at(&full_path)?;
+        if entry.file_type().is_file() {
+            files.push(entry.path().strip_prefix(root).unwrap().to_path_buf());
+        }
+    }
+    Ok(())
+}
+
+pub(crate) const PODIGNORE_FILE_NAME: &str = ".podignore";
+
+/// Loads `.podignore` from `root`, which uses gitignore syntax.
+fn podignore(root: &Path) -> Result<Gitignore, Error> {
+    let path = root.join(PODIGNORE_FILE_NAME);
+    let mut builder = GitignoreBuilder::new(root);
+    if path.is_file() {
+        if let Some(e) = builder.add(&path) {
+            return Err(invalid_podignore(e));
+        }
+    }
+    builder.build().map_err(invalid_podignore)
+}
+
+fn invalid_podignore(e: ignore::Error) -> Error {
+    Error::Usage(format!("invalid {}: {}", PODIGNORE_FILE_NAME, e))
+}
+
+/// Collects the files to put in the bundle, relative to `root`.
+///
+/// This is the default set of top-level files (license, readme) plus
+/// everything under `dirs`, extended by the `include` globs and then filtered
+/// by the `exclude` globs from the bundle config.
+pub(crate) fn collect_files(
+    root: &Path,
+    dirs: &[PathBuf],
+    config: &BundleConfig,
+) -> Result<Vec<PathBuf>, Error> {
+    let root_files = glob_set(
+        &DEFAULT_ROOT_FILES
+            .iter()
+            .map(|x| x.to_string())
+            .collect::<Vec<_>>(),
+    )?;
+    let exclude = glob_set(&config.exclude)?;
+
+    let mut files = vec![];
+    for entry in std::fs::read_dir(root).at(root)? {
+        let entry = entry.at(root)?;
+        let name = PathBuf::from(entry.file_name());
+        if entry.path().is_file() && root_files.is_match(&name) {
+            files.push(name);
+        }
+    }
+
+    for dir in dirs {
+        if root.join(dir).exists() {
+            walk_files(root, dir, &mut files)?;
+        } else {
+            log::warn!("{} does not exist, skipping.", dir.display());
+        }
+    }
+
+    for pattern in &config.include {
+        let full_pattern = root.join(pattern);
+        let matches = match glob::glob(&full_pattern.to_string_lossy()) {
+            Ok(v) => v,
+            Err(e) => {
+                log::warn!("Invalid include pattern '{}': {}", pattern, e);
+                continue;
+            }
+        };
+        for path in matches {
+            let path = path.map_err(|e| Error::Io {
+                path: e.path().to_path_buf(),
+                source: e.into(),
+            })?;
+            walk_files(root, path.strip_prefix(root).unwrap(), &mut files)?;
+        }
+    }
+
+    let podignore = podignore(root)?;
+    files.retain(|x| {
+        !exclude.is_match(x) && !podignore.matched_path_or_any_parents(x, false).is_ignore()
+    });
+    files.sort();
+    files.dedup();
+    Ok(files)
+}
+
+/// Extensions of the archives bundle writes, longest first.
+const ARCHIVE_EXTENSIONS: &[&str] = &[".tar.zst", ".tar.xz", ".tar.gz", ".tgz", ".zip"];
+
+/// Derives the path of a companion archive, e.g. `cargo-pod-ios.tgz` from
+/// `cargo-pod.tgz`.
+pub(crate) fn sibling_output(output_path: &Path, suffix: &str) -> PathBuf {
+    let file_name = output_path
+        .file_name()
+        .unwrap()
+        .to_string_lossy()
+        .to_string();
+    let ext = ARCHIVE_EXTENSIONS
+        .iter()
+        .find(|x| file_name.len() > x.len() && file_name.ends_with(*x))
+        .copied()
+        .unwrap_or("");
+    let base = &file_name[..file_name.len() - ext.len()];
+    output_path.with_file_name(format!("{}-{}{}", base, suffix, ext))
+}
+
+/// The `{platform}` of the main bundle, which contains every platform.
+pub(crate) const UNIVERSAL_PLATFORM: &str = "universal";
+
+/// Fills in the `{name}`, `{version}` and `{platform}` placeholders of an
+/// output template.
+pub(crate) fn render_output(template: &str, name: &str, version: &str, platform: &str) -> String {
+    template
+        .replace("{name}", name)
+        .replace("{version}", version)
+        .replace("{platform}", platform)
+}
+
+/// Renders the output for a single platform's bundle. Templates without a
+/// `{platform}` placeholder get the platform appended as a suffix instead.
+pub(crate) fn render_platform_output(
+    template: &str,
+    name: &str,
+    version: &str,
+    platform: &str,
+) -> PathBuf {
+    if template.contains("{platform}") {
+        PathBuf::from(render_output(template, name, version, platform))
+    } else {
+        sibling_output(
+            Path::new(&render_output(template, name, version, UNIVERSAL_PLATFORM)),
+            platform,
+        )
+    }
+}
+
+/// Normalised permissions so that archives don't depend on the local umask.
+fn normalized_mode(path: &Path) -> Result<u32, Error> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        if path.metadata().at(path)?.permissions().mode() & 0o111 != 0 {
+            return Ok(0o755);
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = path;
+    Ok(0o644)
+}
+
+/// The timestamp recorded for every entry, honouring `SOURCE_DATE_EPOCH`.
+fn archive_mtime() -> u64 {
+    std::env::var("SOURCE_DATE_EPOCH")
+        .ok()
+        .and_then(|x| x.parse().ok())
+        .unwrap_or(0)
+}
+
+/// Writes a compressed tar archive of `files` (relative to `root`).
+///
+/// Entries are written in the given order with normalised owners, modes and
+/// mtimes, so identical inputs give byte-identical archives.
+pub(crate) fn write_tar(
+    root: &Path,
+    files: &[PathBuf],
+    output_path: &Path,
+    compression: Compression,
+    level: Option<u32>,
+) -> Result<(), Error> {
+    let mtime = archive_mtime();
+    let output = File::create(output_path).at(output_path)?;
+    let encoder = compression.encoder(output, level).at(output_path)?;
+    let mut builder = tar::Builder::new(encoder);
+
+    for file in files {
+        log::debug!("Adding {}", file.display());
+        let full_path = root.join(file);
+        if file.to_str().is_none() {
+            return Err(non_utf8_path(file));
+        }
+
+        let mut header = tar::Header::new_gnu();
+        header.set_size(full_path.metadata().at(&full_path)?.len());
+        header.set_mode(normalized_mode(&full_path)?);
+        header.set_mtime(mtime);
+        header.set_uid(0);
+        header.set_gid(0);
+        header.set_username("").at(file)?;
+        header.set_groupname("").at(file)?;
+        header.set_entry_type(tar::EntryType::Regular);
+        let data = File::open(&full_path).at(&full_path)?;
+        builder.append_data(&mut header, file, data).at(file)?;
+    }
+
+    builder
+        .into_inner()
+        .and_then(|x| x.finish())
+        .at(output_path)?;
+    Ok(())
+}
+
+pub(crate) fn write_zip(root: &Path, files: &[PathBuf], output_path: &Path) -> Result<(), Error> {
+    let output = File::create(output_path).at(output_path)?;
+    let mut zip = zip::ZipWriter::new(output);
+    // Without the `time` feature, entries get a fixed timestamp.
+    let options =
+        zip::write::FileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+
+    for file in files {
+        log::debug!("Adding {}", file.display());
+        let full_path = root.join(file);
+        let name = file.to_str().ok_or_else(|| non_utf8_path(file))?;
+        let options = options.unix_permissions(normalized_mode(&full_path)?);
+
+        zip.start_file(name, options)
+            .map_err(io::Error::from)
+            .at(file)?;
+        let mut data = File::open(&full_path).at(&full_path)?;
+        io::copy(&mut data, &mut zip).at(&full_path)?;
+    }
+
+    zip.finish().map_err(io::Error::from).at(output_path)?;
+    Ok(())
+}
+
+/// Extracts an archive written by `write_tar` or `write_zip` into `dest`.
+pub(crate) fn extract(
+    archive_path: &Path,
+    options: &ArchiveOptions,
+    dest: &Path,
+) -> Result<(), Error> {
+    let input = File::open(archive_path).at(archive_path)?;
+    match options.format {
+        ArchiveFormat::Tar => {
+            let decoder = options.compression.decoder(input).at(archive_path)?;
+            tar::Archive::new(decoder).unpack(dest).at(archive_path)
+        }
+        ArchiveFormat::Zip => zip::ZipArchive::new(input)
+            .and_then(|mut x| x.extract(dest))
+            .map_err(io::Error::from)
+            .at(archive_path),
+    }
+}
+
+/// Parses a human readable size such as `50MB` or `1.5GiB` into bytes.
+pub(crate) fn parse_size(input: &str) -> Result<u64, String> {
+    let input = input.trim();
+    let split = input
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(input.len());
+    let (number, unit) = input.split_at(split);
+    let number = number
+        .parse::<f64>()
+        .map_err(|_| format!("invalid size: {}", input))?;
+    let multiplier = match unit.trim().to_ascii_lowercase().as_str() {
+        "" | "b" => 1u64,
+        "k" | "kb" => 1000,
+        "m" | "mb" => 1000 * 1000,
+        "g" | "gb" => 1000 * 1000 * 1000,
+        "kib" => 1 << 10,
+        "mib" => 1 << 20,
+        "gib" => 1 << 30,
+        other => return Err(format!("unknown size unit: {}", other)),
+    };
+    Ok((number * multiplier as f64) as u64)
+}
+
+pub(crate) fn format_size(bytes: u64) -> String {
+    const UNITS: &[&str] = &["B", "KB", "MB", "GB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1000.0 && unit < UNITS.len() - 1 {
+        size /= 1000.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sibling_output_keeps_versions() {
+        assert_eq!(
+            sibling_output(Path::new("out/MyPod-1.2.3.tgz"), "ios"),
+            Path::new("out/MyPod-1.2.3-ios.tgz")
+        );
+        assert_eq!(
+            sibling_output(Path::new("MyPod-1.2.3.tar.zst"), "macos"),
+            Path::new("MyPod-1.2.3-macos.tar.zst")
+        );
+        assert_eq!(
+            sibling_output(Path::new("cargo-pod.zip"), "ios"),
+            Path::new("cargo-pod-ios.zip")
+        );
+        assert_eq!(
+            sibling_output(Path::new("MyPod"), "ios"),
+            Path::new("MyPod-ios")
+        );
+    }
+}
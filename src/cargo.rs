@@ -1,12 +1,22 @@
 use std::path::Path;
 use std::process::Command;
 
+use crate::cmd::{MinVersions, Sanitizer};
+use crate::error::Error;
+use crate::message::Verbosity;
+
+/// Runs `cargo build` for `triple`. Sanitizers need the nightly toolchain.
+/// With `debug_info`, release builds keep DWARF in their object files, which
+/// the app's dSYM picks up when it links the static library.
 pub(crate) fn build(
     dir: &Path,
     triple: &str,
     cargo_args: &Vec<String>,
+    min_versions: &MinVersions,
     is_nightly: bool,
-) -> std::process::ExitStatus {
+    sanitizer: Option<Sanitizer>,
+    debug_info: bool,
+) -> Result<(), Error> {
     let cargo_bin = "cargo";
 
     let mut cargo_cmd = Command::new(cargo_bin);
@@ -20,15 +30,167 @@ pub(crate) fn build(
 
     cargo_cmd.arg("build");
 
+    match crate::message::verbosity() {
+        Verbosity::Quiet => {
+            cargo_cmd.arg("--quiet");
+        }
+        Verbosity::VeryVerbose => {
+            cargo_cmd.arg("--verbose");
+        }
+        _ => {}
+    }
+
     if is_nightly {
         cargo_cmd.args(["-Z", "build-std"]);
     }
 
+    if let Some(sanitizer) = sanitizer {
+        let mut rustflags = std::env::var("RUSTFLAGS").unwrap_or_default();
+        if !rustflags.is_empty() {
+            rustflags.push(' ');
+        }
+        rustflags.push_str(&format!("-Zsanitizer={}", sanitizer.name()));
+        cargo_cmd.env("RUSTFLAGS", rustflags);
+    }
+
+    if debug_info {
+        cargo_cmd
+            .env("CARGO_PROFILE_RELEASE_DEBUG", "true")
+            .env("CARGO_PROFILE_RELEASE_SPLIT_DEBUGINFO", "off");
+    }
+
     cargo_cmd
         .args(cargo_args)
         .arg("--target")
         .arg(triple)
-        .current_dir(dir)
-        .status()
-        .expect("cargo crashed")
+        .env("IPHONEOS_DEPLOYMENT_TARGET", &min_versions.ios)
+        .env("MACOSX_DEPLOYMENT_TARGET", &min_versions.macos)
+        .current_dir(dir);
+    crate::cmd::run("cargo build", &mut cargo_cmd)
+}
+
+/// The line range of the `header` table's body in a manifest, if present.
+fn table_range(lines: &[String], header: &str) -> Option<(usize, usize)> {
+    let start = lines
+        .iter()
+        .position(|x| x.split('#').next().unwrap().trim() == header)?;
+    let end = lines[start + 1..]
+        .iter()
+        .position(|x| x.trim_start().starts_with('['))
+        .map(|x| start + 1 + x)
+        .unwrap_or(lines.len());
+    Some((start, end))
+}
+
+/// Appends a new table to a manifest, separated by a blank line.
+fn push_table(lines: &mut Vec<String>, header: &str, body: String) {
+    if lines.last().map(|x| !x.trim().is_empty()).unwrap_or(false) {
+        lines.push(String::new());
+    }
+    lines.push(header.into());
+    lines.push(body);
+}
+
+fn read_lines(manifest_path: &Path) -> std::io::Result<Vec<String>> {
+    Ok(std::fs::read_to_string(manifest_path)?
+        .lines()
+        .map(str::to_string)
+        .collect())
+}
+
+fn write_lines(manifest_path: &Path, lines: &[String]) -> std::io::Result<()> {
+    let mut manifest = lines.join("\n");
+    manifest.push('\n');
+    std::fs::write(manifest_path, manifest)
+}
+
+/// Adds `staticlib` to the `[lib]` crate types in `manifest_path`, creating
+/// the section if needed. Other crate types are kept, and a crate with none
+/// keeps building an rlib for its Rust users.
+pub(crate) fn add_staticlib_crate_type(manifest_path: &Path) -> std::io::Result<()> {
+    let mut lines = read_lines(manifest_path)?;
+    let default = r#"crate-type = ["staticlib", "lib"]"#.to_string();
+
+    match table_range(&lines, "[lib]") {
+        Some((start, end)) => {
+            let crate_type = (start + 1..end).find(|&i| {
+                let line = lines[i].trim_start();
+                line.starts_with("crate-type") || line.starts_with("crate_type")
+            });
+            match crate_type {
+                Some(i) => {
+                    let open = match lines[i].find('[') {
+                        Some(v) => v,
+                        None => {
+                            return Err(std::io::Error::new(
+                                std::io::ErrorKind::InvalidData,
+                                format!("could not parse `{}`", lines[i].trim()),
+                            ))
+                        }
+                    };
+                    lines[i] = format!(
+                        "{}\"staticlib\", {}",
+                        &lines[i][..=open],
+                        &lines[i][open + 1..]
+                    );
+                }
+                None => lines.insert(start + 1, default),
+            }
+        }
+        None => push_table(&mut lines, "[lib]", default),
+    }
+
+    write_lines(manifest_path, &lines)
+}
+
+/// The package recorded by `cargo pod init` for a workspace with several
+/// staticlib packages, from `[workspace.metadata.pod]`.
+pub(crate) fn recorded_package(workspace_root: &Path) -> Option<String> {
+    let manifest = std::fs::read_to_string(workspace_root.join("Cargo.toml")).ok()?;
+    let value = manifest.parse::<toml::Value>().ok()?;
+    value
+        .get("workspace")?
+        .get("metadata")?
+        .get("pod")?
+        .get("package")?
+        .as_str()
+        .map(str::to_string)
+}
+
+/// Records `package` as the workspace's pod package.
+pub(crate) fn record_package(workspace_root: &Path, package: &str) -> std::io::Result<()> {
+    set_table_value(
+        &workspace_root.join("Cargo.toml"),
+        "[workspace.metadata.pod]",
+        "package",
+        package,
+    )
+}
+
+/// Sets a string `key` in the `header` table of a manifest, adding the table
+/// if needed.
+pub(crate) fn set_table_value(
+    manifest_path: &Path,
+    header: &str,
+    key: &str,
+    value: &str,
+) -> std::io::Result<()> {
+    let mut lines = read_lines(manifest_path)?;
+    let entry = format!(
+        "{} = \"{}\"",
+        key,
+        value.replace('\\', "\\\\").replace('"', "\\\"")
+    );
+
+    match table_range(&lines, header) {
+        Some((start, end)) => {
+            match (start + 1..end).find(|&i| lines[i].split('=').next().unwrap().trim() == key) {
+                Some(i) => lines[i] = entry,
+                None => lines.insert(start + 1, entry),
+            }
+        }
+        None => push_table(&mut lines, header, entry),
+    }
+
+    write_lines(manifest_path, &lines)
 }
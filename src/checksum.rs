@@ -0,0 +1,51 @@
+use std::{
+    fs::File,
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+use futures_util::{Stream, TryStreamExt};
+use sha2::{Digest, Sha256};
+
+pub fn sha256_file(path: &Path) -> io::Result<String> {
+    let mut file = File::open(path)?;
+    let mut hasher = Sha256::new();
+    io::copy(&mut file, &mut hasher)?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// Hashes a stream of chunks, such as a downloaded response body.
+pub async fn sha256_stream<S, B, E>(stream: S) -> Result<String, E>
+where
+    S: Stream<Item = Result<B, E>>,
+    B: AsRef<[u8]>,
+{
+    let mut hasher = Sha256::new();
+    futures_util::pin_mut!(stream);
+    while let Some(chunk) = stream.try_next().await? {
+        hasher.update(chunk);
+    }
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+pub const SUMS_FILE_NAME: &str = "SHA256SUMS";
+
+/// Writes a `sha256sum`-compatible `SHA256SUMS` file into `dir` covering
+/// `files`, which are listed by file name.
+pub fn write_sums(dir: &Path, files: &[PathBuf]) -> io::Result<PathBuf> {
+    let mut entries = files
+        .iter()
+        .map(|path| {
+            let name = path.file_name().unwrap().to_string_lossy().to_string();
+            Ok((name, sha256_file(path)?))
+        })
+        .collect::<io::Result<Vec<_>>>()?;
+    entries.sort();
+
+    let sums_path = dir.join(SUMS_FILE_NAME);
+    let mut out = File::create(&sums_path)?;
+    for (name, sha256) in entries {
+        writeln!(out, "{}  {}", sha256, name)?;
+    }
+    Ok(sums_path)
+}
@@ -1,24 +1,38 @@
-use std::{
-    path::{Path, PathBuf},
-    process::{exit, Stdio},
-};
+use std::path::{Path, PathBuf};
 
-use cargo_metadata::{Metadata, MetadataCommand, Package, Target};
+use cargo_metadata::{Package, Target};
 use glob::glob;
 use gumdrop::{Options, ParsingStyle};
-use heck::CamelCase;
-use jwalk::WalkDir;
 
-use serde::{Deserialize, Serialize};
-use std::{
-    fs::File,
-    io::{Read, Write},
-};
+use std::io::Write;
 
 use crate::{
-    cmd::{lipo, Ar, Swiftc, Xcodebuild},
-    podspec::Podspec,
-    IOS_TRIPLES, MACOS_TRIPLES,
+    build::{BuildOptions, BuildTarget, INCOMPLETE_MARKER},
+    bundle::{ArchiveFormat, ArchiveOptions, Compression},
+    cmd::{lipo, MinVersions, Sanitizer, Xcodebuild},
+    completions::Shell,
+    error::Error,
+    message::{MessageFormat, Verbosity},
+    meta::PublishConfig,
+    pod::{
+        bundle_output_path, derive_manifest, derive_manifest_with, find_configured_pod_root,
+        find_pod_root, find_podspec, framework_names, git_commit_paths, has_subtree_at,
+        pod_manifest, pod_name, pod_version, relative_path, resolve_dist_dir, resolve_pod_root,
+        ManifestOptions, SPLIT_PLATFORMS,
+    },
+    podspec::{
+        generate_podspec, set_podspec_version, show_podspec_diff, sync_podspec_version,
+        update_podspec_sha256, update_podspec_sha256_at, Podspec, PodspecBuilder,
+    },
+    publish::{
+        create_tag, default_tag, forge::Repo, update_package_swift, Provider, Publisher,
+        DEFAULT_PUBLISH_KEY,
+    },
+    subtree::{CrateRemote, Pin, PREFIX},
+    template::Template,
+    workflow::Workflow,
+    xctest::Platform,
+    MACOS_TRIPLES,
 };
 
 #[derive(Debug, Options)]
@@ -32,6 +46,37 @@ struct BuildArgs {
     #[options(long = "ios", help = "iOS builds only")]
     is_ios: bool,
 
+    #[options(help = "fail if the podspec version does not match the crate")]
+    strict: bool,
+
+    #[options(
+        no_short,
+        help = "keep debug info in the static libraries, for the app's dSYM"
+    )]
+    debug_info: bool,
+
+    #[options(
+        no_short,
+        meta = "FMT",
+        help = "write a report of how long each step took to dist/, as html or json"
+    )]
+    timings: Option<crate::timings::Format>,
+
+    #[options(
+        no_short,
+        help = "add the staticlib crate-type to Cargo.toml if it is missing"
+    )]
+    fix: bool,
+
+    #[options(no_short, help = "also write a BUILD.bazel importing the xcframeworks")]
+    bazel: bool,
+
+    #[options(
+        no_short,
+        help = "also write a Tuist project description helper depending on the xcframeworks"
+    )]
+    tuist: bool,
+
     #[options(free, help = "args to be passed to `cargo build` step")]
     cargo_args: Vec<String>,
 
@@ -46,6 +91,9 @@ struct InitArgs {
     #[options(help = "override the name of the pod")]
     name: Option<String>,
 
+    #[options(help = "the workspace package to build the pod from")]
+    package: Option<String>,
+
     #[options(help = "override the repository url")]
     repo: Option<String>,
 
@@ -55,6 +103,51 @@ struct InitArgs {
     #[options(short = "b", help = "branch for the subtree repo")]
     subtree_branch: Option<String>,
 
+    #[options(no_short, help = "pin the subtree to a tag")]
+    subtree_tag: Option<String>,
+
+    #[options(no_short, help = "pin the subtree to a commit")]
+    subtree_rev: Option<String>,
+
+    #[options(
+        no_short,
+        help = "scaffold headers and Swift bindings: plain, cbindgen or uniffi"
+    )]
+    template: Option<Template>,
+
+    #[options(
+        no_short,
+        help = "generate an example iOS app whose Podfile uses the local podspec"
+    )]
+    example_app: bool,
+
+    #[options(no_short, help = "write release automation: github or fastlane")]
+    workflow: Option<Workflow>,
+
+    #[options(
+        no_short,
+        help = "regenerate the podspec and Package.swift instead of merging"
+    )]
+    force: bool,
+
+    #[options(
+        no_short,
+        help = "directory for the podspec, src and dist, if not the workspace root"
+    )]
+    pod_root: Option<PathBuf>,
+
+    #[options(
+        no_short,
+        help = "also write a Package.swift vending the xcframeworks to SwiftPM"
+    )]
+    package_swift: bool,
+
+    #[options(
+        no_short,
+        help = "add the staticlib crate-type to Cargo.toml if it is missing"
+    )]
+    fix: bool,
+
     manifest_path: Option<PathBuf>,
 }
 
@@ -63,7 +156,9 @@ struct PublishArgs {
     #[options(help = "show help information")]
     help: bool,
 
-    #[options(help = "GitHub Personal Access Token")]
+    #[options(
+        help = "access token; defaults to GITHUB_TOKEN, GH_TOKEN or `gh auth token` (GITEA_TOKEN for Gitea, CARGO_POD_PUBLISH_TOKEN for http, ARTIFACTORY_API_KEY for artifactory)"
+    )]
     token: Option<String>,
 
     #[options(help = "URL to repository; will use git remote origin if not given")]
@@ -71,12 +166,80 @@ struct PublishArgs {
 
     #[options(
         no_short,
-        help = "Override tag; uses data in .podspec file if not given"
+        help = "github, gitea (also forgejo), s3, http (also webdav), artifactory, ssh or local; inferred from the repository host if not given"
+    )]
+    provider: Option<Provider>,
+
+    #[options(
+        no_short,
+        help = "API URL; derived from the repository host if not given"
+    )]
+    api_url: Option<String>,
+
+    #[options(
+        no_short,
+        help = "Override tag; uses v{version} from the .podspec file (or Cargo.toml) if not given"
     )]
     tag: Option<String>,
 
     #[options(help = "Overwrite tag if present")]
     force: bool,
+
+    #[options(no_short, help = "create the release as a draft for manual review")]
+    draft: bool,
+
+    #[options(
+        no_short,
+        help = "create and push a signed tag if it does not exist yet; implies --verify-tag"
+    )]
+    sign_tag: bool,
+
+    #[options(no_short, help = "require a valid signature on the release tag")]
+    verify_tag: bool,
+
+    #[options(
+        no_short,
+        help = "mark the release as a prerelease; implied by tags like v1.0.0-beta.1"
+    )]
+    prerelease: bool,
+
+    #[options(
+        no_short,
+        help = "release channel (e.g. beta); the tag must carry it as a prerelease suffix"
+    )]
+    channel: Option<String>,
+
+    #[options(no_short, help = "path of the archive to upload")]
+    file: Option<PathBuf>,
+
+    #[options(
+        no_short,
+        help = "re-download each asset after upload and compare its sha256"
+    )]
+    verify_hash: bool,
+
+    #[options(no_short, help = "directory to publish into with --provider local")]
+    path: Option<PathBuf>,
+
+    #[options(
+        no_short,
+        help = "destination for --provider ssh, like user@host:/var/www/pods/"
+    )]
+    dest: Option<String>,
+
+    #[options(
+        no_short,
+        help = "print what would be published without uploading or changing anything"
+    )]
+    dry_run: bool,
+
+    #[options(
+        no_short,
+        help = "commit the podspec once it points at the published archive"
+    )]
+    commit: bool,
+
+    manifest_path: Option<PathBuf>,
 }
 
 #[derive(Debug, Options)]
@@ -84,971 +247,2258 @@ struct UpdateArgs {
     #[options(help = "show help information")]
     help: bool,
 
+    #[options(short = "b", help = "track a branch of the crate repo")]
+    branch: Option<String>,
+
+    #[options(no_short, help = "pin the crate to a tag")]
+    tag: Option<String>,
+
+    #[options(no_short, help = "pin the crate to a commit")]
+    rev: Option<String>,
+
+    #[options(no_short, help = "the vendored crate to update (default: crate)")]
+    prefix: Option<String>,
+
+    #[options(no_short, help = "update every vendored crate")]
+    all: bool,
+
+    #[options(
+        no_short,
+        help = "stash uncommitted changes and restore them afterwards"
+    )]
+    autostash: bool,
+
     manifest_path: Option<PathBuf>,
 }
 
 #[derive(Debug, Options)]
-struct BundleArgs {
+struct AddCrateArgs {
     #[options(help = "show help information")]
     help: bool,
 
+    #[options(no_short, help = "where to vendor the crate (default: crates/<name>)")]
+    prefix: Option<String>,
+
+    #[options(short = "b", help = "track a branch of the crate repo")]
+    branch: Option<String>,
+
+    #[options(no_short, help = "pin the crate to a tag")]
+    tag: Option<String>,
+
+    #[options(no_short, help = "pin the crate to a commit")]
+    rev: Option<String>,
+
+    #[options(free, help = "url of the crate repo")]
+    url: Option<String>,
+
     manifest_path: Option<PathBuf>,
 }
 
 #[derive(Debug, Options)]
-struct ExampleArgs {
+struct PushSubtreeArgs {
     #[options(help = "show help information")]
     help: bool,
 
-    #[options(free)]
-    example_args: Vec<String>,
-}
+    #[options(short = "b", help = "push to this branch instead of the tracked one")]
+    branch: Option<String>,
 
-#[derive(Debug, Options)]
-enum Command {
-    Init(InitArgs),
-    Build(BuildArgs),
-    Bundle(BundleArgs),
-    Publish(PublishArgs),
-    Update(UpdateArgs),
-    #[options(help = "Run example swift (if present)")]
-    Example(ExampleArgs),
+    #[options(no_short, help = "the vendored crate to push (default: crate)")]
+    prefix: Option<String>,
+
+    manifest_path: Option<PathBuf>,
 }
 
 #[derive(Debug, Options)]
-pub struct Args {
+struct ExtractCrateArgs {
     #[options(help = "show help information")]
     help: bool,
 
-    #[options(command)]
-    command: Option<Command>,
-}
+    #[options(short = "b", help = "branch of the new repository (default: main)")]
+    branch: Option<String>,
 
-fn derive_manifest(manifest_path: Option<&Path>) -> (Metadata, Package, Vec<Target>) {
-    let mut cmd = MetadataCommand::new();
+    #[options(no_short, help = "the vendored crate to extract (default: crate)")]
+    prefix: Option<String>,
 
-    if let Some(path) = manifest_path {
-        cmd.manifest_path(path);
-    }
+    #[options(free, help = "directory to create the repository in")]
+    path: Option<PathBuf>,
 
-    let metadata = match cmd.exec() {
-        Ok(v) => v,
-        Err(e) => {
-            log::error!("Failed to load Cargo.toml.");
-            log::error!("{}", e);
-            exit(1);
-        }
-    };
-    let packages = metadata
-        .packages
-        .iter()
-        .filter(|p| metadata.workspace_members.contains(&p.id))
-        .cloned()
-        .collect::<Vec<_>>();
+    manifest_path: Option<PathBuf>,
+}
 
-    log::trace!("Got these packages:");
-    log::trace!("{:#?}", packages);
+#[derive(Debug, Options)]
+struct BundleArgs {
+    #[options(help = "show help information")]
+    help: bool,
 
-    let lib_targets = packages
-        .iter()
-        .filter_map(|x| {
-            let targets = x
-                .targets
-                .iter()
-                .filter(|x| x.kind.contains(&"staticlib".into()))
-                .collect::<Vec<_>>();
+    #[options(help = "path of the archive; supports {name}, {version} and {platform}")]
+    output: Option<PathBuf>,
 
-            if targets.is_empty() {
-                return None;
-            }
+    #[options(help = "archive format (tar or zip)")]
+    format: Option<ArchiveFormat>,
 
-            Some((x, targets))
-        })
-        .collect::<Vec<_>>();
+    #[options(help = "tar compression (gzip, zstd or xz)")]
+    compression: Option<Compression>,
 
-    if lib_targets.is_empty() {
-        log::error!("No lib crates found!");
-        exit(1);
-    }
+    #[options(no_short, help = "compression level")]
+    compression_level: Option<u32>,
 
-    log::trace!("Got these libs:");
-    log::trace!("{:#?}", &lib_targets);
+    #[options(no_short, help = "also write separate iOS-only and macOS-only bundles")]
+    split_platforms: bool,
 
-    let (package, targets) = lib_targets.first().unwrap();
-    (
-        metadata,
-        (**package).clone(),
-        targets.iter().map(|x| (*x).clone()).collect::<Vec<_>>(),
-    )
-}
+    #[options(no_short, help = "also write a zip of each xcframework")]
+    zip_xcframeworks: bool,
 
-fn init_subtree(args: &InitArgs) {
-    let subtree_url = args.subtree_url.as_ref().unwrap();
-    let branch = args.subtree_branch.as_deref().unwrap_or("main");
+    #[options(
+        no_short,
+        help = "also write a zipped SwiftPM .artifactbundle of each static library"
+    )]
+    artifactbundle: bool,
 
-    let has_commits = std::process::Command::new("git")
-        .args(["rev-parse", "HEAD"])
-        .stdout(Stdio::null())
-        .stderr(Stdio::null())
-        .status()
-        .unwrap()
-        .code()
-        .unwrap()
-        == 0;
+    #[options(no_short, help = "sign the SHA256SUMS file with gpg")]
+    sign: bool,
 
-    if !has_commits {
-        let exists = Path::new(".gitignore").exists();
+    #[options(help = "fail if the podspec version does not match the crate")]
+    strict: bool,
 
-        let mut f = std::fs::OpenOptions::new()
-            .create(true)
-            .append(true)
-            .open(".gitignore")
-            .unwrap();
+    #[options(
+        no_short,
+        help = "check the archive contains every path the podspec references"
+    )]
+    verify: bool,
 
-        if !exists {
-            writeln!(f, "dist/").unwrap();
-        }
+    #[options(no_short, help = "only warn when the bundle exceeds max_bundle_size")]
+    allow_oversize: bool,
 
-        drop(f);
+    manifest_path: Option<PathBuf>,
+}
 
-        std::process::Command::new("git")
-            .arg("init")
-            .stdout(Stdio::null())
-            .stderr(Stdio::null())
-            .status()
-            .unwrap();
+#[derive(Debug, Options)]
+struct SpmArgs {
+    #[options(help = "show help information")]
+    help: bool,
 
-        std::process::Command::new("git")
-            .args(["reset"])
-            .stdout(Stdio::null())
-            .stderr(Stdio::null())
-            .status()
-            .unwrap();
+    #[options(help = "directory to write the xcframework zips to (default: next to the podspec)")]
+    output_dir: Option<PathBuf>,
 
-        std::process::Command::new("git")
-            .args(["add", ".gitignore"])
-            .stdout(Stdio::null())
-            .stderr(Stdio::null())
-            .status()
-            .unwrap();
+    #[options(
+        help = "URL the zips are published under; defaults to next to the podspec's :http source"
+    )]
+    url: Option<String>,
 
-        std::process::Command::new("git")
-            .args(["commit", "-m", "Initial commit"])
-            .stdout(Stdio::null())
-            .stderr(Stdio::null())
-            .status()
-            .unwrap();
-    }
+    manifest_path: Option<PathBuf>,
+}
 
-    std::process::Command::new("git")
-        .args(["remote", "add", "-f", "crate", subtree_url])
-        .status()
-        .unwrap();
+#[derive(Debug, Options)]
+struct PodspecArgs {
+    #[options(help = "show help information")]
+    help: bool,
 
-    std::process::Command::new("git")
-        .args([
-            "subtree", "add", "--prefix", "crate", "crate", branch, "--squash",
-        ])
-        .status()
-        .unwrap();
+    #[options(help = "emit a spec for local development with `:path`")]
+    local: bool,
 
-    std::fs::write(".crate-remote", subtree_url).unwrap();
+    #[options(
+        no_short,
+        help = "emit a spec that builds the crate during `pod install`"
+    )]
+    source_build: bool,
 
-    std::process::Command::new("git")
-        .args(["add", ".crate-remote"])
-        .stdout(Stdio::null())
-        .stderr(Stdio::null())
-        .status()
-        .unwrap();
+    #[options(
+        no_short,
+        help = "fail with a diff if the podspec on disk differs from the generated one"
+    )]
+    check: bool,
 
-    std::process::Command::new("git")
-        .args(["commit", "-m", "Add .crate-remote"])
-        .stdout(Stdio::null())
-        .stderr(Stdio::null())
-        .status()
-        .unwrap();
+    manifest_path: Option<PathBuf>,
 }
 
-fn init(args: InitArgs) {
-    if args.subtree_url.is_some() {
-        init_subtree(&args);
-    }
-
-    std::fs::create_dir_all("./src").unwrap();
+#[derive(Debug, Options)]
+struct VersionArgs {
+    #[options(help = "show help information")]
+    help: bool,
 
-    let manifest_path = args
-        .subtree_url
-        .as_ref()
-        .map(|_| Path::new("crate/Cargo.toml"))
-        .or(args.manifest_path.as_deref());
+    #[options(help = "commit the version change")]
+    commit: bool,
 
-    let (_metadata, package, targets) = derive_manifest(manifest_path);
-    let mut config = crate::meta::config(&package);
+    #[options(help = "commit and tag the version change")]
+    tag: bool,
 
-    if let Some(name) = args.name {
-        config.name = Some(name);
-    }
+    #[options(
+        no_short,
+        help = "commit and create a signed tag (gpg or ssh, per git config)"
+    )]
+    sign: bool,
 
-    let mut podspec = Podspec::from(package.clone());
-    podspec.disable_bitcode();
-    for target in &targets {
-        podspec.add_target(target);
-    }
+    #[options(free, help = "major, minor, patch or an explicit version")]
+    bump: Vec<String>,
 
-    let name = config.name.unwrap_or_else(|| package.name.to_camel_case());
-    podspec.name = name.clone();
+    manifest_path: Option<PathBuf>,
+}
 
-    log::info!(
-        "Writing {}.podspec to {}",
-        &name,
-        std::env::current_dir().unwrap().display()
-    );
+#[derive(Debug, Options)]
+struct LintArgs {
+    #[options(help = "show help information")]
+    help: bool,
 
-    std::fs::write(
-        std::env::current_dir()
-            .unwrap()
-            .join(&name)
-            .with_extension("podspec"),
-        podspec.to_string(),
-    )
-    .unwrap();
+    #[options(no_short, help = "lint the published spec with `pod spec lint`")]
+    remote: bool,
 
-    std::process::Command::new("git")
-        .arg("add")
-        .arg(format!("{}.podspec", name))
-        .status()
-        .unwrap();
-}
+    #[options(long = "macos", help = "lint for macOS only")]
+    is_macos: bool,
 
-fn update(_args: UpdateArgs) {
-    let has_subtree = std::fs::read_dir("./crate").is_ok();
+    #[options(long = "ios", help = "lint for iOS only")]
+    is_ios: bool,
 
-    if !has_subtree {
-        println!("No crate found.");
-        std::process::exit(1);
-    }
+    #[options(no_short, help = "do not fail on warnings")]
+    allow_warnings: bool,
 
-    let crate_remote = std::fs::read_to_string(".crate-remote").unwrap();
+    #[options(free, help = "args to be passed to `pod`")]
+    pod_args: Vec<String>,
 
-    std::process::Command::new("git")
-        .args([
-            "subtree",
-            "pull",
-            "--prefix",
-            "crate",
-            crate_remote.trim(),
-            "main",
-            "--squash",
-        ])
-        .status()
-        .unwrap();
+    manifest_path: Option<PathBuf>,
 }
 
-fn build_static_libs(
-    mut cargo_args: Vec<String>,
-    metadata: &Metadata,
-    package: &Package,
-    targets: &[Target],
-    dist_dir: &Path,
-    build_target: BuildTarget,
-) {
-    let package_dir = package.manifest_path.parent().unwrap();
+#[derive(Debug, Options)]
+struct VerifyArgs {
+    #[options(help = "show help information")]
+    help: bool,
 
-    if cargo_args.contains(&"--target".into()) {
-        log::error!("Do not pass --target to the cargo args, we handle that!");
-        exit(1);
-    }
+    #[options(no_short, help = "path of the local archive to compare against")]
+    file: Option<PathBuf>,
 
-    if !cargo_args.contains(&"--release".into()) {
-        cargo_args.push("--release".into())
-    }
+    #[options(
+        no_short,
+        help = "also run a trial `pod install` of the spec in a temporary project"
+    )]
+    pod_install: bool,
 
-    if !cargo_args.contains(&"--lib".into()) {
-        cargo_args.push("--lib".into())
-    }
+    manifest_path: Option<PathBuf>,
+}
 
-    let mut lib_paths = vec![];
+#[derive(Debug, Options)]
+struct IntegrationTestArgs {
+    #[options(help = "show help information")]
+    help: bool,
 
-    if build_target.is_ios() {
-        for triple in IOS_TRIPLES {
-            log::info!("Building for target '{}'...", triple);
-            std::fs::create_dir_all(format!("./dist/{}", triple)).unwrap();
+    manifest_path: Option<PathBuf>,
+}
 
-            if !crate::cargo::build(package_dir, triple, &cargo_args, false).success() {
-                std::process::exit(1);
-            }
+#[derive(Debug, Options)]
+struct TestArgs {
+    #[options(help = "show help information")]
+    help: bool,
 
-            for target in targets {
-                lib_paths.push((
-                    triple,
-                    metadata
-                        .target_directory
-                        .join(triple)
-                        .join("release")
-                        .join(format!("lib{}.a", target.name.replace('-', "_"))),
-                ));
-            }
-        }
-    }
+    #[options(long = "macos", help = "macOS tests only")]
+    is_macos: bool,
 
-    if build_target.is_macos() {
-        for triple in MACOS_TRIPLES {
-            log::info!("Building for target '{}'...", triple);
-            std::fs::create_dir_all(format!("./dist/{}", triple)).unwrap();
+    #[options(long = "ios", help = "iOS simulator tests only")]
+    is_ios: bool,
 
-            if !crate::cargo::build(package_dir, triple, &cargo_args, false).success() {
-                std::process::exit(1);
-            }
+    #[options(no_short, help = "simulator to test on (default: booted)")]
+    simulator: Option<String>,
 
-            for target in targets {
-                lib_paths.push((
-                    triple,
-                    metadata
-                        .target_directory
-                        .join(triple)
-                        .join("release")
-                        .join(format!("lib{}.a", target.name.replace('-', "_"))),
-                ));
+    manifest_path: Option<PathBuf>,
+}
+
+#[derive(Debug, Options)]
+struct BenchArgs {
+    #[options(help = "show help information")]
+    help: bool,
+
+    #[options(no_short, help = "benchmark only this macOS triple")]
+    triple: Option<String>,
+
+    manifest_path: Option<PathBuf>,
+}
+
+#[derive(Debug, Options)]
+struct ReplArgs {
+    #[options(help = "show help information")]
+    help: bool,
+
+    manifest_path: Option<PathBuf>,
+}
+
+#[derive(Debug, Options)]
+struct PlaygroundArgs {
+    #[options(help = "show help information")]
+    help: bool,
+
+    #[options(no_short, help = "overwrite an existing playground")]
+    force: bool,
+
+    manifest_path: Option<PathBuf>,
+}
+
+#[derive(Debug, Options)]
+struct DemoArgs {
+    #[options(help = "show help information")]
+    help: bool,
+
+    #[options(no_short, help = "overwrite an existing demo app")]
+    force: bool,
+
+    manifest_path: Option<PathBuf>,
+}
+
+#[derive(Debug, Options)]
+struct ExampleArgs {
+    #[options(help = "show help information")]
+    help: bool,
+
+    #[options(no_short, help = "directory of the example sources (default: example)")]
+    dir: Option<PathBuf>,
+
+    #[options(
+        no_short,
+        help = "run on the iOS device with this identifier (see `xcrun devicectl list devices`)"
+    )]
+    device: Option<String>,
+
+    #[options(no_short, help = "development provisioning profile for --device")]
+    profile: Option<PathBuf>,
+
+    #[options(no_short, help = "team to sign with (default: the profile's team)")]
+    team: Option<String>,
+
+    #[options(no_short, help = "signing identity (default: Apple Development)")]
+    identity: Option<String>,
+
+    #[options(no_short, help = "bundle identifier of the example app")]
+    bundle_id: Option<String>,
+
+    #[options(
+        no_short,
+        help = "link against the built xcframeworks instead of the raw libraries"
+    )]
+    framework: bool,
+
+    #[options(
+        no_short,
+        help = "macOS slice to build and run, such as x86_64-apple-darwin"
+    )]
+    triple: Option<String>,
+
+    #[options(no_short, help = "link against the universal macOS libraries")]
+    universal: bool,
+
+    #[options(no_short, help = "build the Rust library and example unoptimised")]
+    debug: bool,
+
+    #[options(
+        no_short,
+        help = "build as with --debug and run the example under lldb"
+    )]
+    debug_lldb: bool,
+
+    #[options(
+        no_short,
+        meta = "KEY=VAL",
+        help = "set an environment variable for the example"
+    )]
+    env: Vec<String>,
+
+    #[options(no_short, help = "directory to run the example in")]
+    cwd: Option<PathBuf>,
+
+    #[options(
+        no_short,
+        help = "build with a sanitizer: address or thread (needs nightly Rust)"
+    )]
+    sanitize: Option<Sanitizer>,
+
+    #[options(free)]
+    example_args: Vec<String>,
+
+    manifest_path: Option<PathBuf>,
+}
+
+#[derive(Debug, Options)]
+struct DoctorArgs {
+    #[options(help = "show help information")]
+    help: bool,
+
+    manifest_path: Option<PathBuf>,
+}
+
+#[derive(Debug, Options)]
+struct InfoArgs {
+    #[options(help = "show help information")]
+    help: bool,
+
+    #[options(long = "macos", help = "as for a macOS build only")]
+    is_macos: bool,
+
+    #[options(long = "ios", help = "as for an iOS build only")]
+    is_ios: bool,
+
+    #[options(no_short, help = "as for publishing with this provider")]
+    provider: Option<Provider>,
+
+    manifest_path: Option<PathBuf>,
+}
+
+#[derive(Debug, Options)]
+struct CompletionsArgs {
+    #[options(help = "show help information")]
+    help: bool,
+
+    #[options(free, help = "bash, zsh or fish")]
+    shell: Option<Shell>,
+}
+
+#[derive(Debug, Options)]
+enum Command {
+    Init(InitArgs),
+    Build(BuildArgs),
+    Bundle(BundleArgs),
+    Publish(PublishArgs),
+    Update(UpdateArgs),
+    #[options(help = "Vendor another crate repo as a subtree")]
+    AddCrate(AddCrateArgs),
+    #[options(help = "Push changes made under crate/ back to the crate repo")]
+    PushSubtree(PushSubtreeArgs),
+    #[options(help = "Split crate/ and its history into a new repository")]
+    ExtractCrate(ExtractCrateArgs),
+    #[options(help = "Generate a podspec")]
+    Podspec(PodspecArgs),
+    #[options(help = "Zip the xcframeworks and write their checksums to Package.swift")]
+    Spm(SpmArgs),
+    #[options(help = "Bump the crate and podspec version")]
+    Version(VersionArgs),
+    #[options(help = "Validate the podspec with CocoaPods")]
+    Lint(LintArgs),
+    #[options(help = "Check the published archive against the podspec and local bundle")]
+    Verify(VerifyArgs),
+    #[options(help = "Install the built pod into a test app and compile it")]
+    IntegrationTest(IntegrationTestArgs),
+    #[options(help = "Run the XCTest suites in Tests/ against the built frameworks")]
+    Test(TestArgs),
+    #[options(help = "Run example swift (if present)")]
+    Example(ExampleArgs),
+    #[options(help = "Generate a SwiftUI demo app for the example command")]
+    Demo(DemoArgs),
+    #[options(help = "Generate a Swift Playgrounds app package importing the built pod")]
+    Playground(PlaygroundArgs),
+    #[options(help = "Start a Swift REPL with the built pod importable")]
+    Repl(ReplArgs),
+    #[options(help = "Run the Swift benchmark harness for each macOS architecture")]
+    Bench(BenchArgs),
+    #[options(help = "Check the tools, SDKs, Rust targets and project layout a build needs")]
+    Doctor(DoctorArgs),
+    #[options(
+        help = "Print the package, targets, paths and publish destination a build would use"
+    )]
+    Info(InfoArgs),
+    #[options(help = "Print a completion script for bash, zsh or fish")]
+    Completions(CompletionsArgs),
+}
+
+impl Command {
+    /// The command's `--manifest-path`, which every command but
+    /// `completions` takes.
+    fn manifest_path_mut(&mut self) -> Option<&mut Option<PathBuf>> {
+        Some(match self {
+            Command::Init(args) => &mut args.manifest_path,
+            Command::Build(args) => &mut args.manifest_path,
+            Command::Bundle(args) => &mut args.manifest_path,
+            Command::Publish(args) => &mut args.manifest_path,
+            Command::Update(args) => &mut args.manifest_path,
+            Command::AddCrate(args) => &mut args.manifest_path,
+            Command::PushSubtree(args) => &mut args.manifest_path,
+            Command::ExtractCrate(args) => &mut args.manifest_path,
+            Command::Podspec(args) => &mut args.manifest_path,
+            Command::Spm(args) => &mut args.manifest_path,
+            Command::Version(args) => &mut args.manifest_path,
+            Command::Lint(args) => &mut args.manifest_path,
+            Command::Verify(args) => &mut args.manifest_path,
+            Command::IntegrationTest(args) => &mut args.manifest_path,
+            Command::Test(args) => &mut args.manifest_path,
+            Command::Example(args) => &mut args.manifest_path,
+            Command::Demo(args) => &mut args.manifest_path,
+            Command::Playground(args) => &mut args.manifest_path,
+            Command::Repl(args) => &mut args.manifest_path,
+            Command::Bench(args) => &mut args.manifest_path,
+            Command::Doctor(args) => &mut args.manifest_path,
+            Command::Info(args) => &mut args.manifest_path,
+            Command::Completions(_) => return None,
+        })
+    }
+}
+
+#[derive(Debug, Options)]
+pub struct Args {
+    #[options(help = "show help information")]
+    help: bool,
+
+    #[options(
+        count,
+        help = "print debug logs; -vv also prints trace logs and cargo's verbose output"
+    )]
+    verbose: u32,
+
+    #[options(help = "print only warnings and errors, and tool output only if it fails")]
+    quiet: bool,
+
+    #[options(
+        no_short,
+        meta = "FMT",
+        help = "human, or json to print progress, artifacts and errors as JSON lines on stdout"
+    )]
+    message_format: Option<MessageFormat>,
+
+    #[options(
+        no_short,
+        help = "print the commands, writes and git changes that would run without running them"
+    )]
+    dry_run: bool,
+
+    #[options(
+        no_short,
+        meta = "PATH",
+        help = "also write a full timestamped log, with the output of each tool, to PATH"
+    )]
+    log_file: Option<PathBuf>,
+
+    #[options(command)]
+    command: Option<Command>,
+}
+
+/// Moves into the pod root of the crate at `manifest_path`, or of the
+/// current directory, since paths such as `./dist` and `./crate` are
+/// relative to the pod root.
+fn enter_pod_root(manifest_path: Option<&Path>) {
+    let cwd = match std::env::current_dir() {
+        Ok(v) => v,
+        Err(e) => {
+            log::warn!("Could not read the current directory: {}", e);
+            return;
+        }
+    };
+    let start = manifest_path
+        .and_then(Path::parent)
+        .map(Path::to_path_buf)
+        .unwrap_or_else(|| cwd.clone());
+    let pod_root = match find_pod_root(&start) {
+        Some(v) if v != cwd => v,
+        _ => return,
+    };
+    log::debug!("Using pod root {}", pod_root.display());
+    if let Err(e) = std::env::set_current_dir(&pod_root) {
+        log::warn!("Could not change to {}: {}", pod_root.display(), e);
+    }
+}
+
+/// Moves into the `pod_root` configured in the crate's metadata, if any. The
+/// crate may then be outside the pod root, so `--manifest-path` is set to its
+/// manifest when not given.
+fn enter_configured_pod_root(command: &mut Command) {
+    let manifest_path = match command.manifest_path_mut() {
+        Some(v) => v,
+        None => return,
+    };
+    let has_subtree = std::fs::read_dir("./crate").is_ok();
+    let (pod_root, manifest) =
+        match find_configured_pod_root(pod_manifest(manifest_path.as_deref(), has_subtree)) {
+            Some(v) => v,
+            None => return,
+        };
+    if manifest_path.is_none() {
+        *manifest_path = Some(manifest);
+    }
+    log::debug!("Using pod root {}", pod_root.display());
+    if let Err(e) = std::env::set_current_dir(&pod_root) {
+        log::warn!("Could not change to {}: {}", pod_root.display(), e);
+    }
+}
+
+/// Writes license files from the crate's license expression if the pod root
+/// has none, so that the podspec and bundle have one to reference.
+fn write_license_files(package: &Package) -> Result<(), Error> {
+    let has_license = glob("LICENSE*")
+        .unwrap()
+        .filter_map(Result::ok)
+        .any(|x| x.is_file());
+    let expression = match &package.license {
+        Some(v) if !has_license => v,
+        _ => return Ok(()),
+    };
+
+    let authors = package
+        .authors
+        .iter()
+        .map(|x| x.split('<').next().unwrap().trim())
+        .filter(|x| !x.is_empty())
+        .collect::<Vec<_>>();
+    let holder = if authors.is_empty() {
+        format!("The {} Authors", package.name)
+    } else {
+        authors.join(", ")
+    };
+
+    match crate::license::write(Path::new("."), expression, &holder) {
+        Ok((written, unknown)) => {
+            for path in &written {
+                if crate::cmd::is_dry_run() {
+                    log::info!("Would write {}", path.display());
+                } else {
+                    log::info!("Writing {}", path.display());
+                }
+            }
+            if !written.is_empty() {
+                git_add(&written);
+            }
+            for id in unknown {
+                log::warn!(
+                    "No bundled text for the {} license; add its LICENSE file by hand.",
+                    id
+                );
+            }
+        }
+        Err(e) => {
+            return Err(Error::Io {
+                path: ".".into(),
+                source: e,
+            })
+        }
+    }
+    Ok(())
+}
+
+/// Stages `paths`, if the pod is in a git repository.
+fn git_add<P: AsRef<Path>>(paths: &[P]) {
+    let repo = match crate::git::open() {
+        Ok(v) => v,
+        Err(_) => return,
+    };
+    if let Err(e) = crate::git::add(&repo, paths) {
+        log::warn!("Could not stage changes: {}", e);
+    }
+}
+
+/// Build outputs that should never be committed to the pod's repository.
+const GITIGNORE_ENTRIES: &[&str] = &[
+    "dist/",
+    "*.swiftmodule",
+    "*.swiftdoc",
+    "*.swiftsourceinfo",
+    "*.swiftinterface",
+    "*.abi.json",
+    "cargo-pod.*",
+    "cargo-pod-*",
+    "*.xcframework.zip",
+    "SHA256SUMS",
+    "SHA256SUMS.asc",
+];
+
+/// Adds any of `GITIGNORE_ENTRIES` missing from the `.gitignore` at `path`,
+/// keeping what is already there.
+fn update_gitignore(path: &Path) -> Result<(), Error> {
+    let existing = std::fs::read_to_string(path).unwrap_or_default();
+    let normalize = |x: &str| x.trim().trim_matches('/').to_string();
+    let present = existing.lines().map(normalize).collect::<Vec<_>>();
+    let missing = GITIGNORE_ENTRIES
+        .iter()
+        .filter(|x| !present.contains(&normalize(x)))
+        .collect::<Vec<_>>();
+    if missing.is_empty() {
+        return Ok(());
+    }
+
+    if crate::cmd::is_dry_run() {
+        log::info!("Would add build outputs to {}", path.display());
+        return Ok(());
+    }
+    log::info!("Adding build outputs to {}", path.display());
+    let mut contents = String::new();
+    if !existing.is_empty() && !existing.ends_with('\n') {
+        contents.push('\n');
+    }
+    if !existing.is_empty() {
+        contents.push_str("\n# cargo-pod build outputs\n");
+    }
+    for entry in missing {
+        contents.push_str(entry);
+        contents.push('\n');
+    }
+    std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .and_then(|mut f| f.write_all(contents.as_bytes()))
+        .map_err(Error::io(path))
+}
+
+fn init_subtree(args: &InitArgs) -> Result<(), Error> {
+    let subtree_url = args.subtree_url.as_ref().unwrap();
+    let pin = Pin::from_options(
+        args.subtree_branch.as_deref(),
+        args.subtree_tag.as_deref(),
+        args.subtree_rev.as_deref(),
+    )?
+    .unwrap_or_else(|| crate::subtree::default_branch(subtree_url));
+    let mut remote = CrateRemote::new(subtree_url, pin, PREFIX);
+
+    let repo = crate::git::open_or_init().map_err(|e| {
+        Error::Git(format!(
+            "Could not open or create a git repository: {}",
+            e.message()
+        ))
+    })?;
+
+    if !repo.as_ref().map(crate::git::has_commits).unwrap_or(false) {
+        update_gitignore(Path::new(".gitignore"))?;
+        git_commit_paths("Initial commit", &[".gitignore"])?;
+    }
+
+    match &repo {
+        Some(repo) => crate::git::add_remote(repo, "crate", subtree_url)
+            .map_err(|e| Error::Git(format!("Failed to add the crate remote: {}", e.message())))?,
+        None => log::info!("Would git remote add crate {}", subtree_url),
+    }
+
+    remote.synced = Some(crate::subtree::add(&remote)?);
+    remote.write()?;
+    git_commit_paths("Add .crate-remote", &[crate::subtree::REMOTE_FILE])
+}
+
+fn init(args: InitArgs) -> Result<(), Error> {
+    if args.subtree_url.is_some() {
+        if Path::new("crate").exists() {
+            log::info!("crate/ already exists; use `cargo pod update` to pull changes.");
+        } else {
+            init_subtree(&args)?;
+        }
+    }
+
+    let start_dir = crate::fs::canonicalize(".")?;
+    let manifest_path = args
+        .subtree_url
+        .as_ref()
+        .map(|_| Path::new("crate/Cargo.toml"))
+        .or(args.manifest_path.as_deref())
+        .map(|x| std::fs::canonicalize(x).unwrap_or_else(|_| x.to_path_buf()));
+    let manifest_path = manifest_path.as_deref();
+
+    if let Some(pod_root) = &args.pod_root {
+        let (_, package, _) = derive_manifest(manifest_path)?;
+        crate::fs::create_dir_all(pod_root)?;
+        let pod_root = crate::fs::canonicalize(pod_root)?;
+        let package_dir = crate::fs::canonicalize(package.manifest_path.parent().unwrap())?;
+        let relative = relative_path(&package_dir, &pod_root);
+        log::info!(
+            "Setting the pod root in {} to {}",
+            package.manifest_path.display(),
+            relative.display()
+        );
+        crate::cargo::set_table_value(
+            &package.manifest_path,
+            "[package.metadata.pod]",
+            "pod_root",
+            &relative.to_string_lossy().replace('\\', "/"),
+        )
+        .map_err(Error::io(&package.manifest_path))?;
+    }
+
+    let (metadata, package, targets) = derive_manifest_with(
+        manifest_path,
+        ManifestOptions {
+            fix: args.fix,
+            package: args.package.as_deref(),
+            record_package: true,
+        },
+    )?;
+
+    crate::fs::create_dir_all("./src")?;
+
+    update_gitignore(Path::new(".gitignore"))?;
+    git_add(&[".gitignore"]);
+
+    write_license_files(&package)?;
+
+    let package_dir = package.manifest_path.parent().unwrap().to_path_buf();
+    let (metadata, package, targets) = match args.template {
+        Some(template) => {
+            log::info!("Scaffolding the {:?} template", template);
+            crate::template::scaffold(template, &package, &targets)
+                .map_err(Error::io(&package_dir))?;
+            for step in crate::template::next_steps(template, &targets) {
+                log::info!("Next: {}", step);
+            }
+            // Pick up any metadata the template added.
+            derive_manifest(manifest_path)?
+        }
+        None if !crate::template::has_swift_sources(&package) => {
+            log::info!("No Swift bindings found; generating a starter wrapper.");
+            crate::template::scaffold(Template::Plain, &package, &targets)
+                .map_err(Error::io(&package_dir))?;
+            for step in crate::template::next_steps(Template::Plain, &targets) {
+                log::info!("Next: {}", step);
+            }
+            (metadata, package, targets)
+        }
+        None => (metadata, package, targets),
+    };
+    let podspec = generate_podspec(&metadata, &package, &targets, args.name.clone(), false)?;
+    let name = podspec.name.clone();
+    let podspec_path = std::env::current_dir()
+        .map_err(Error::io("."))?
+        .join(&name)
+        .with_extension("podspec");
+
+    match std::fs::read_to_string(&podspec_path) {
+        Ok(existing) if !args.force => {
+            match crate::podspec::merge(&existing, &podspec.to_string()) {
+                Some((merged, added)) if added.is_empty() => {
+                    if show_podspec_diff(&podspec_path, &existing, &podspec.to_string()) {
+                        log::info!(
+                            "{} differs from the generated podspec as above; keeping it. Use --force to regenerate it.",
+                            podspec_path.display()
+                        );
+                    } else {
+                        log::info!(
+                            "{} is up to date; keeping it. Use --force to regenerate it.",
+                            podspec_path.display()
+                        );
+                    }
+                    if merged != existing {
+                        crate::fs::write(&podspec_path, merged)?;
+                    }
+                }
+                Some((merged, added)) => {
+                    log::info!(
+                        "Adding {} to the existing {}",
+                        added.join(", "),
+                        podspec_path.display()
+                    );
+                    crate::fs::write(&podspec_path, merged)?;
+                }
+                None => log::warn!(
+                    "Could not read the existing {}; keeping it. Use --force to regenerate it.",
+                    podspec_path.display()
+                ),
+            }
+        }
+        _ => {
+            log::info!(
+                "Writing {}.podspec to {}",
+                &name,
+                std::env::current_dir().map_err(Error::io("."))?.display()
+            );
+            crate::fs::write(&podspec_path, podspec.to_string())?;
+        }
+    }
+
+    git_add(&[format!("{}.podspec", name)]);
+
+    if args.package_swift {
+        write_package_swift(
+            Path::new(crate::package_swift::FILE_NAME),
+            &podspec,
+            &targets,
+            args.force,
+        )?;
+        log::info!("Run `cargo pod spm`, or `cargo pod bundle` and `cargo pod publish`, to fill in the binary target checksums.");
+    }
+
+    if let Some(workflow) = args.workflow {
+        // The workflow lives where init was run, which is the repository
+        // root; commands run from the crate unless it is a subtree.
+        let package_dir = crate::fs::canonicalize(package.manifest_path.parent().unwrap())?;
+        let crate_dir = relative_path(&start_dir, &package_dir);
+        let working_dir = if args.subtree_url.is_some() || has_subtree_at(&start_dir) {
+            PathBuf::from(".")
+        } else {
+            crate_dir.clone()
+        };
+        workflow
+            .write(
+                &start_dir,
+                &crate_dir.to_string_lossy(),
+                &working_dir.to_string_lossy(),
+            )
+            .map_err(Error::io(&start_dir))?;
+    }
+
+    if args.example_app {
+        let dir = Path::new(crate::example_app::DIR_NAME);
+        if dir.exists() {
+            log::warn!(
+                "{} already exists; not generating an example app.",
+                dir.display()
+            );
+            return Ok(());
+        }
+        let modules = targets
+            .iter()
+            .map(|x| framework_names(x).0)
+            .collect::<Vec<_>>();
+        let config = crate::meta::config(&package);
+        if crate::cmd::is_dry_run() {
+            log::info!("Would write example app to {}", dir.display());
+            return Ok(());
+        }
+        log::info!("Writing example app to {}", dir.display());
+        crate::example_app::write(
+            dir,
+            &format!("{}Example", name.replace('-', "")),
+            &name,
+            &modules,
+            &config.min_versions().ios,
+        )
+        .map_err(Error::io(dir))?;
+        log::info!(
+            "Run `cargo pod build`, then `pod install` in {} and open the workspace.",
+            dir.display()
+        );
+    }
+    Ok(())
+}
+
+/// Writes a `Package.swift` next to the podspec, with binary targets at the
+/// URLs `publish` will upload the xcframework zips to.
+fn spm(args: SpmArgs) -> Result<(), Error> {
+    let has_subtree = std::fs::read_dir("./crate").is_ok();
+    let (metadata, package, targets) =
+        derive_manifest(pod_manifest(args.manifest_path.as_deref(), has_subtree))?;
+    let dist_dir = resolve_dist_dir(&metadata, &package, has_subtree);
+    let pod_root = resolve_pod_root(&dist_dir);
+
+    // The binary targets are published next to the pod archive, so the
+    // podspec's source tells us where.
+    let url = match args.url {
+        // A trailing slash makes the zips siblings of an empty file name.
+        Some(v) => format!("{}/", v.trim_end_matches('/')),
+        None => {
+            let spec = find_podspec(&pod_root)
+                .map(crate::fs::read_to_string)
+                .transpose()?
+                .unwrap_or_default();
+            match crate::podspec::source_http(&spec) {
+                Some(v) => v.replace(
+                    "#{spec.version}",
+                    crate::podspec::spec_version(&spec).unwrap_or_default(),
+                ),
+                None => {
+                    return Err(Error::Usage(
+                        "No :http source found in a podspec; pass --url.".into(),
+                    ));
+                }
+            }
+        }
+    };
+
+    let output_dir = args.output_dir.unwrap_or_else(|| pod_root.clone());
+    crate::fs::create_dir_all(&output_dir)?;
+    let zips = zip_xcframeworks(&dist_dir, &output_dir)?;
+    if zips.is_empty() {
+        return Err(Error::Usage(format!(
+            "No xcframeworks found in {}; run `cargo pod build` first.",
+            dist_dir.display()
+        )));
+    }
+
+    let path = pod_root.join(crate::package_swift::FILE_NAME);
+    if !path.exists() {
+        let podspec = generate_podspec(&metadata, &package, &targets, None, false)?;
+        write_package_swift(&path, &podspec, &targets, false)?;
+    }
+    update_package_swift(&pod_root, &output_dir, Some(&url))?;
+    for zip in &zips {
+        crate::message::artifact("xcframework-zip", zip);
+    }
+    crate::message::artifact("package-swift", &path);
+
+    log::info!(
+        "Upload {} to {}",
+        zips.iter()
+            .map(|x| x.file_name().unwrap().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join(", "),
+        crate::package_swift::sibling_url(&url, "")
+    );
+    Ok(())
+}
+
+fn write_package_swift(
+    path: &Path,
+    podspec: &Podspec,
+    targets: &[Target],
+    force: bool,
+) -> Result<(), Error> {
+    if path.exists() && !force {
+        log::info!(
+            "Keeping existing {}. Use --force to regenerate it.",
+            path.display()
+        );
+        return Ok(());
+    }
+
+    // Until the first publish, assume the zips sit next to the podspec source.
+    let source_url = podspec
+        .source
+        .as_ref()
+        .map(|x| x.http.replace("#{spec.version}", &podspec.version))
+        .unwrap_or_else(|| "UNKNOWN".into());
+    let package = crate::package_swift::PackageSwift {
+        name: podspec.name.clone(),
+        ios: podspec.ios.deployment_target.clone(),
+        macos: podspec.macos.deployment_target.clone(),
+        targets: targets
+            .iter()
+            .flat_map(|target| {
+                let (mod_name, ffi_mod_name) = framework_names(target);
+                [mod_name, ffi_mod_name]
+            })
+            .map(|name| crate::package_swift::BinaryTarget {
+                url: crate::package_swift::sibling_url(
+                    &source_url,
+                    &format!("{}{}", name, crate::package_swift::ZIP_SUFFIX),
+                ),
+                checksum: crate::package_swift::PLACEHOLDER_CHECKSUM.into(),
+                name,
+            })
+            .collect(),
+    };
+
+    log::info!("Writing {}", path.display());
+    crate::fs::write(path, package.to_string())?;
+    git_add(&[path]);
+    Ok(())
+}
+
+fn update(args: UpdateArgs) -> Result<(), Error> {
+    let new_pin = Pin::from_options(
+        args.branch.as_deref(),
+        args.tag.as_deref(),
+        args.rev.as_deref(),
+    )?;
+
+    let remotes = if args.all {
+        if new_pin.is_some() {
+            return Err(Error::Usage(
+                "--all cannot be combined with --branch, --tag or --rev.".into(),
+            ));
+        }
+        CrateRemote::read_all()?
+    } else {
+        vec![CrateRemote::read(args.prefix.as_deref().unwrap_or(PREFIX))?]
+    };
+
+    let mut repo = match crate::git::open() {
+        Ok(v) => v,
+        Err(e) => {
+            return Err(Error::Git(format!(
+                "Could not open the git repository: {}",
+                e
+            )));
+        }
+    };
+    let has_local_changes = match crate::git::has_local_changes(&repo) {
+        Ok(v) => v,
+        Err(e) => {
+            return Err(Error::Git(format!(
+                "Could not read the status of the working tree: {}",
+                e
+            )));
+        }
+    };
+    let stashed = has_local_changes && args.autostash;
+    if has_local_changes && !stashed {
+        return Err(Error::Git(
+            "The working tree has uncommitted changes. Commit or stash them, or pass --autostash."
+                .into(),
+        ));
+    }
+    if stashed {
+        log::info!("Stashing uncommitted changes");
+        if let Err(e) = crate::git::stash(&mut repo, "cargo pod update --autostash") {
+            return Err(Error::Git(format!("Failed to stash changes: {}", e)));
+        }
+    }
+
+    for (remote, pinned) in remotes {
+        if let Err(e) = update_crate(remote, pinned, new_pin.clone()) {
+            if stashed {
+                log::warn!("Your changes are still stashed; run `git stash pop` once resolved.");
             }
+            return Err(e);
+        }
+    }
+
+    if stashed {
+        log::info!("Restoring uncommitted changes");
+        // git subtree has changed the repository since it was opened.
+        let result = crate::git::open().and_then(|mut repo| crate::git::unstash(&mut repo));
+        if let Err(e) = result {
+            return Err(Error::Git(format!(
+                "Failed to restore stashed changes: {}",
+                e
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Pulls one vendored crate, recording a new pin or synced revision in
+/// `.crate-remote`.
+fn update_crate(mut remote: CrateRemote, pinned: bool, new_pin: Option<Pin>) -> Result<(), Error> {
+    let repinned = match new_pin {
+        Some(pin) if pin != remote.pin => {
+            remote.pin = pin;
+            true
+        }
+        _ => !pinned,
+    };
+
+    log::info!(
+        "Updating {}/ to {} of {}",
+        remote.prefix,
+        remote.pin,
+        remote.url
+    );
+    let synced = Some(crate::subtree::pull(&remote)?);
+
+    if repinned || synced != remote.synced {
+        let message = if repinned {
+            format!("Pin {} to {}", remote.prefix, remote.pin)
+        } else {
+            let sha = synced.as_deref().unwrap();
+            format!(
+                "Record {} sync at {}",
+                remote.prefix,
+                &sha[..sha.len().min(12)]
+            )
+        };
+        remote.synced = synced;
+        remote.write()?;
+        crate::git::open()
+            .and_then(|repo| {
+                crate::git::commit_paths(&repo, &message, &[crate::subtree::REMOTE_FILE])
+            })
+            .map_err(|e| {
+                Error::Git(format!(
+                    "Failed to commit {}: {}",
+                    crate::subtree::REMOTE_FILE,
+                    e
+                ))
+            })?;
+    }
+    Ok(())
+}
+
+fn add_crate(args: AddCrateArgs) -> Result<(), Error> {
+    let url = match args.url {
+        Some(v) => v,
+        None => {
+            return Err(Error::Usage(
+                "Give the url of the crate repo to vendor.".into(),
+            ));
+        }
+    };
+    let prefix = args.prefix.unwrap_or_else(|| {
+        let name = url
+            .trim_end_matches('/')
+            .rsplit(|c| c == '/' || c == ':')
+            .next()
+            .unwrap();
+        format!("crates/{}", name.trim_end_matches(".git"))
+    });
+    let prefix = prefix.trim_end_matches('/').to_string();
+    if Path::new(&prefix).exists() {
+        return Err(Error::Usage(format!("{}/ already exists.", prefix)));
+    }
+    let pin = Pin::from_options(
+        args.branch.as_deref(),
+        args.tag.as_deref(),
+        args.rev.as_deref(),
+    )?
+    .unwrap_or_else(|| crate::subtree::default_branch(&url));
+    let mut remote = CrateRemote::new(&url, pin, &prefix);
+
+    log::info!("Vendoring {} of {} at {}/", remote.pin, url, prefix);
+    remote.synced = Some(crate::subtree::add(&remote)?);
+    remote.write()?;
+    git_commit_paths(
+        &format!("Record {} in .crate-remote", prefix),
+        &[crate::subtree::REMOTE_FILE],
+    )
+}
+
+fn push_subtree(args: PushSubtreeArgs) -> Result<(), Error> {
+    let (remote, _) = CrateRemote::read(args.prefix.as_deref().unwrap_or(PREFIX))?;
+    let branch = match (args.branch, &remote.pin) {
+        (Some(v), _) => v,
+        (None, Pin::Branch(v)) => v.clone(),
+        (None, pin) => {
+            return Err(Error::Usage(format!("{}/ is pinned to {}, which cannot be pushed to. Pass --branch to push to a branch.",
+                remote.prefix,
+                pin)));
+        }
+    };
+
+    log::info!(
+        "Pushing {}/ to branch {} of {}",
+        remote.prefix,
+        branch,
+        remote.url
+    );
+    crate::subtree::push(&remote, &branch)
+}
+
+fn extract_crate(args: ExtractCrateArgs) -> Result<(), Error> {
+    let path = match args.path {
+        Some(v) => v,
+        None => {
+            return Err(Error::Usage(
+                "Give the directory to create the crate's repository in.".into(),
+            ));
+        }
+    };
+    let is_empty = std::fs::read_dir(&path)
+        .map(|mut x| x.next().is_none())
+        .unwrap_or(true);
+    if !is_empty {
+        return Err(Error::Usage(format!(
+            "{} already exists and is not empty.",
+            path.display()
+        )));
+    }
+    let branch = args.branch.as_deref().unwrap_or("main");
+    let prefix = args.prefix.as_deref().unwrap_or(PREFIX);
+
+    log::info!("Extracting {}/ into {}", prefix, path.display());
+    crate::subtree::extract(prefix, &path, branch)?;
+    log::info!(
+        "Add a remote to {} and push {} to publish the crate on its own.",
+        path.display(),
+        branch
+    );
+    Ok(())
+}
+
+fn version(args: VersionArgs) -> Result<(), Error> {
+    let bump = match args.bump.as_slice() {
+        [bump] => bump,
+        _ => {
+            return Err(Error::Usage(
+                "Expected exactly one of major, minor, patch or a version.".into(),
+            ));
+        }
+    };
+
+    let has_subtree = std::fs::read_dir("./crate").is_ok();
+    let (_metadata, package, _targets) =
+        derive_manifest(pod_manifest(args.manifest_path.as_deref(), has_subtree))?;
+
+    let new_version = match crate::version::bump(&package.version, bump) {
+        Ok(v) => v,
+        Err(e) => {
+            return Err(Error::Usage(e.to_string()));
+        }
+    };
+
+    let config = crate::meta::config(&package);
+    let pod_version = match crate::version::pod_version(&new_version, &config) {
+        Ok(v) => v,
+        Err(e) => {
+            return Err(Error::Usage(e.to_string()));
         }
+    };
+
+    let manifest = crate::fs::read_to_string(&package.manifest_path)?;
+    let new_manifest =
+        match crate::version::set_manifest_version(&manifest, &new_version.to_string()) {
+            Some(v) => v,
+            None => {
+                return Err(Error::Usage(format!(
+                    "Could not find a package version in {}.",
+                    package.manifest_path.display()
+                )));
+            }
+        };
+    log::info!(
+        "Updating {} from {} to {}",
+        package.manifest_path.display(),
+        package.version,
+        new_version
+    );
+    crate::fs::write(&package.manifest_path, new_manifest)?;
+
+    set_podspec_version(Path::new("."), &pod_version, false, |_| false)?;
+
+    if !(args.commit || args.tag || args.sign) {
+        return Ok(());
     }
 
-    for (triple, path) in lib_paths {
-        let dest = dist_dir.join(triple).join(path.file_name().unwrap());
-        let result = std::fs::copy(&path, &dest);
-        match result {
-            Ok(_) => {}
-            Err(e) => {
-                panic!("Error copying {:?} -> {:?}: {:?}", path, dest, e);
-            }
-        }
+    let mut paths = vec![package.manifest_path.clone()];
+    paths.extend(find_podspec(Path::new(".")));
+    git_commit_paths(&format!("Bump version to {}", pod_version), &paths)?;
+
+    if args.tag || args.sign {
+        create_tag(&format!("v{}", pod_version), args.sign)?;
     }
+    Ok(())
 }
 
-#[allow(non_camel_case_types)]
-#[derive(Debug, Clone, Copy)]
-enum BuildTarget {
-    _iOS,
-    MacOS,
-    Both,
-}
+fn lint(args: LintArgs) -> Result<(), Error> {
+    let podspec_path = match find_podspec(Path::new(".")) {
+        Some(v) => v,
+        None => {
+            return Err(Error::Usage(
+                "No podspec found in the current directory.".into(),
+            ));
+        }
+    };
 
-impl BuildTarget {
-    fn is_ios(&self) -> bool {
-        matches!(self, BuildTarget::_iOS | BuildTarget::Both)
+    // `pod lib lint` validates against the local files, so the frameworks
+    // referenced by the spec need to have been built already.
+    if !args.remote && !Path::new("dist").is_dir() {
+        return Err(Error::Usage(
+            "No dist directory found; run `cargo pod build` first.".into(),
+        ));
     }
 
-    fn is_macos(&self) -> bool {
-        matches!(self, BuildTarget::MacOS | BuildTarget::Both)
+    let platforms = match (args.is_ios, args.is_macos) {
+        (true, false) => Some("ios"),
+        (false, true) => Some("macos"),
+        _ => None,
+    };
+
+    let mut cmd = std::process::Command::new("pod");
+    cmd.arg(if args.remote { "spec" } else { "lib" })
+        .arg("lint")
+        .arg(&podspec_path)
+        .arg("--skip-import-validation");
+    if let Some(platforms) = platforms {
+        cmd.arg(format!("--platforms={}", platforms));
+    }
+    if args.allow_warnings {
+        cmd.arg("--allow-warnings");
     }
+    cmd.args(&args.pod_args);
 
-    fn triples(&self) -> impl Iterator<Item = &'_ str> {
-        const MAC: &[&str] = &["aarch64-apple-darwin", "x86_64-apple-darwin"];
-        const IOS: &[&str] = &[
-            "aarch64-apple-ios",
-            "aarch64-apple-ios-sim",
-            "x86_64-apple-ios",
-        ];
-        IOS.iter()
-            .filter(|_| self.is_ios())
-            .chain(MAC.iter().filter(|_| self.is_macos()))
-            .copied()
+    if crate::cmd::skip(&cmd) {
+        return Ok(());
     }
+    log::trace!("Calling: {:?}", &cmd);
+    let status = match crate::cmd::status(&mut cmd) {
+        Ok(v) => v,
+        Err(e) => {
+            return Err(Error::tool(
+                "pod",
+                format!("could not be run; is CocoaPods installed? {}", e),
+            ));
+        }
+    };
 
-    fn framework_targets(&self) -> impl Iterator<Item = &'_ str> {
-        const MAC: &[&str] = &["macos-universal"];
-        const IOS: &[&str] = &["aarch64-apple-ios", "ios-simulator"];
-        IOS.iter()
-            .filter(|_| self.is_ios())
-            .chain(MAC.iter().filter(|_| self.is_macos()))
-            .copied()
+    if !status.success() {
+        return Err(Error::Check(format!(
+            "{} did not pass `pod lint`",
+            podspec_path.display()
+        )));
     }
+    Ok(())
 }
 
-fn build_safe_frameworks(
-    package: &Package,
-    targets: &[Target],
-    dist_dir: &Path,
-    build_target: BuildTarget,
-) {
-    let package_dir = package.manifest_path.parent().unwrap();
-    let bindings_path = package_dir.join("bindings");
+/// Computes the sha256 of the archive at `url` by downloading it, or reading
+/// it for `file://` URLs.
+async fn remote_sha256(url: &str) -> Result<String, String> {
+    let file_path = reqwest::Url::parse(url)
+        .ok()
+        .filter(|x| x.scheme() == "file")
+        .and_then(|x| x.to_file_path().ok());
+    if let Some(path) = file_path {
+        return crate::checksum::sha256_file(&path).map_err(|e| e.to_string());
+    }
 
-    let swift_files = WalkDir::new(&bindings_path)
-        .into_iter()
-        .filter_map(Result::ok)
-        .filter(|entry| entry.file_type().is_file())
-        .map(|entry| entry.path())
-        .collect::<Vec<_>>();
+    let client = reqwest::Client::builder()
+        .user_agent("cargo-cocoapods")
+        .build()
+        .unwrap();
+    let response = crate::publish::http::send(|| client.get(url))
+        .await
+        .map_err(|e| e.to_string())?;
+    crate::interrupt::until_interrupted(crate::checksum::sha256_stream(response.bytes_stream()))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())
+}
 
-    for target in targets {
-        let sys_name = target.name.replace('-', "_");
-        let ffi_mod_name = format!("{sys_name}_ffi").to_camel_case();
-        let ffi_fw_name = format!("{ffi_mod_name}.framework");
-
-        let mod_name = target.name.replace('-', "_").to_string().to_camel_case();
-        let fw_name = format!("{mod_name}.framework");
-
-        for triple in build_target.triples() {
-            let triple_dir = dist_dir.join(triple);
-            let ffi_fw_dir = triple_dir.join(&ffi_fw_name);
-            let fw_dir = triple_dir.join(&fw_name);
-
-            std::fs::create_dir_all(&fw_dir).unwrap();
-            dircpy::copy_dir(&ffi_fw_dir, &fw_dir).unwrap();
-            std::fs::write(
-                fw_dir.join("Info.plist"),
-                INFO_PLIST.replace("%BUNDLE_NAME%", &mod_name),
-            )
-            .unwrap();
-            std::fs::rename(fw_dir.join("Headers"), fw_dir.join("PrivateHeaders")).unwrap();
-            std::fs::rename(fw_dir.join(&ffi_mod_name), fw_dir.join(&mod_name)).unwrap();
-            std::fs::write(
-                fw_dir.join("Modules").join("module.modulemap"),
-                format!(
-                    "framework module {mod_name} {{
-}}"
-                ),
-            )
-            .unwrap();
+async fn verify(args: VerifyArgs) -> Result<(), Error> {
+    let has_subtree = std::fs::read_dir("./crate").is_ok();
+    let (metadata, package, _targets) =
+        derive_manifest(pod_manifest(args.manifest_path.as_deref(), has_subtree))?;
+    let pod_root = resolve_pod_root(&resolve_dist_dir(&metadata, &package, has_subtree));
+    let config = crate::meta::config(&package);
 
-            std::fs::write(
-                fw_dir.join("Modules").join("module.private.modulemap"),
-                format!(
-                    "framework module {mod_name}_Private {{
-    header \"{sys_name}.h\"
-    link \"{mod_name}\"
-}}"
-                ),
-            )
-            .unwrap();
+    let podspec_path = match find_podspec(&pod_root) {
+        Some(v) => v,
+        None => {
+            return Err(Error::Usage(format!(
+                "No podspec found in {}.",
+                pod_root.display()
+            )));
+        }
+    };
+    let spec = crate::fs::read_to_string(&podspec_path)?;
+    let url = match crate::podspec::source_http(&spec) {
+        Some(v) => v.to_string(),
+        None => {
+            return Err(Error::Usage(format!(
+                "No :http source found in {}.",
+                podspec_path.display()
+            )));
+        }
+    };
 
-            // Build the bindings
-            let obj_path = Swiftc::build(
-                triple,
-                &Default::default(),
-                &mod_name,
-                &triple_dir,
-                &swift_files,
+    log::info!("Downloading {}...", url);
+    let actual = match remote_sha256(&url).await {
+        Ok(v) => v,
+        Err(e) => {
+            return Err(Error::Network(format!("Failed to download {}: {}", url, e)));
+        }
+    };
+    log::info!("Published archive has sha256 {}", actual);
+
+    let mut ok = true;
+    match crate::podspec::source_sha256(&spec) {
+        Some(expected) if expected == actual => {
+            log::info!("Matches the checksum in {}", podspec_path.display());
+        }
+        Some(expected) => {
+            log::error!(
+                "Does not match the checksum {} in {}",
+                expected,
+                podspec_path.display()
             );
-            Ar::insert(&fw_dir.join(&mod_name), &obj_path);
-            let swift_mod_path = fw_dir
-                .join("Modules")
-                .join(format!("{mod_name}.swiftmodule"));
-            std::fs::create_dir_all(&swift_mod_path).unwrap();
-            let arch = current_arch(triple);
-            for ext in [
-                "swiftdoc",
-                "swiftmodule",
-                "swiftsourceinfo",
-                "abi.json",
-                "swiftinterface",
-            ] {
-                std::fs::rename(
-                    format!("{mod_name}.{ext}"),
-                    swift_mod_path.join(format!("{arch}.{ext}")),
-                )
-                .unwrap();
-            }
-            log::debug!("Deleting {}", &obj_path);
-            std::fs::remove_file(obj_path).unwrap();
-            std::fs::remove_file(format!("{mod_name}.private.swiftinterface")).unwrap();
+            ok = false;
         }
+        None => log::warn!("No :sha256 found in {}.", podspec_path.display()),
+    }
 
-        if build_target.is_ios() {
-            let output_path = dist_dir.join("ios-simulator").join(&fw_name);
-            std::fs::create_dir_all(&output_path).unwrap();
-            std::fs::write(
-                output_path.join("Info.plist"),
-                INFO_PLIST.replace("%BUNDLE_NAME%", &mod_name),
-            )
-            .unwrap();
-            let lipo_1 = dist_dir
-                .join("aarch64-apple-ios-sim")
-                .join(&fw_name)
-                .join(&mod_name);
-            let lipo_2 = dist_dir
-                .join("x86_64-apple-ios")
-                .join(&fw_name)
-                .join(&mod_name);
-
-            lipo([lipo_1, lipo_2].iter(), &output_path.join(&mod_name)).unwrap();
-
-            dircpy::copy_dir(
-                dist_dir
-                    .join("aarch64-apple-ios-sim")
-                    .join(&fw_name)
-                    .join("PrivateHeaders"),
-                output_path.join("PrivateHeaders"),
-            )
-            .unwrap();
-            dircpy::copy_dir(
-                dist_dir
-                    .join("x86_64-apple-ios")
-                    .join(&fw_name)
-                    .join("Modules"),
-                output_path.join("Modules"),
-            )
-            .unwrap();
-            dircpy::copy_dir(
-                dist_dir
-                    .join("aarch64-apple-ios-sim")
-                    .join(&fw_name)
-                    .join("Modules"),
-                output_path.join("Modules"),
-            )
-            .unwrap();
+    let archive_path = bundle_output_path(
+        &pod_root,
+        &package,
+        args.file.as_deref(),
+        config.bundle.format.unwrap_or_default(),
+        config.bundle.compression.unwrap_or_default(),
+        None,
+    )?;
+    if archive_path.exists() {
+        let local =
+            crate::checksum::sha256_file(&archive_path).map_err(Error::io(&archive_path))?;
+        if local == actual {
+            log::info!("Matches the local bundle {}", archive_path.display());
+        } else {
+            log::error!(
+                "Does not match the local bundle {} ({}); the upload may be stale.",
+                archive_path.display(),
+                local
+            );
+            ok = false;
         }
+    } else {
+        log::warn!(
+            "No local bundle at {}; not comparing against it.",
+            archive_path.display()
+        );
+    }
 
-        if build_target.is_macos() {
-            let output_path = dist_dir.join("macos-universal").join(&fw_name);
-            std::fs::create_dir_all(&output_path).unwrap();
-            std::fs::write(
-                output_path.join("Info.plist"),
-                INFO_PLIST.replace("%BUNDLE_NAME%", &mod_name),
-            )
-            .unwrap();
-            lipo(
-                [
-                    dist_dir
-                        .join("aarch64-apple-darwin")
-                        .join(&fw_name)
-                        .join(&mod_name),
-                    dist_dir
-                        .join("x86_64-apple-darwin")
-                        .join(&fw_name)
-                        .join(&mod_name),
-                ]
-                .iter(),
-                &output_path.join(&mod_name),
-            )
-            .unwrap();
+    if !ok {
+        return Err(Error::Check(format!(
+            "The published archive at {} failed verification",
+            url
+        )));
+    }
 
-            dircpy::copy_dir(
-                dist_dir
-                    .join("aarch64-apple-darwin")
-                    .join(&fw_name)
-                    .join("PrivateHeaders"),
-                output_path.join("PrivateHeaders"),
-            )
-            .unwrap();
-            dircpy::copy_dir(
-                dist_dir
-                    .join("x86_64-apple-darwin")
-                    .join(&fw_name)
-                    .join("Modules"),
-                output_path.join("Modules"),
-            )
-            .unwrap();
-            dircpy::copy_dir(
-                dist_dir
-                    .join("aarch64-apple-darwin")
-                    .join(&fw_name)
-                    .join("Modules"),
-                output_path.join("Modules"),
-            )
-            .unwrap();
+    if args.pod_install {
+        trial_pod_install(&podspec_path, &pod_name(&package), &config.min_versions())?;
+    }
+    Ok(())
+}
+
+/// Installs the pod from `podspec_path` into a throwaway project, which
+/// downloads and unpacks its source just as a consumer would.
+fn trial_pod_install(
+    podspec_path: &Path,
+    name: &str,
+    min_versions: &MinVersions,
+) -> Result<(), Error> {
+    let project = crate::fs::tempdir()?;
+    let podspec_path = crate::fs::canonicalize(podspec_path)?;
+    let podfile = format!(
+        "install! 'cocoapods', :integrate_targets => false\n\
+         platform :ios, '{}'\n\
+         \n\
+         target 'CargoPodVerify' do\n  pod '{}', :podspec => '{}'\nend\n",
+        min_versions.ios,
+        name,
+        podspec_path.display()
+    );
+    crate::fs::write(project.path().join("Podfile"), podfile)?;
+
+    log::info!(
+        "Running a trial `pod install` in {}...",
+        project.path().display()
+    );
+    let mut cmd = std::process::Command::new("pod");
+    cmd.arg("install").current_dir(project.path());
+    if crate::cmd::skip(&cmd) {
+        return Ok(());
+    }
+    log::trace!("Calling: {:?}", &cmd);
+    let status = match crate::cmd::status(&mut cmd) {
+        Ok(v) => v,
+        Err(e) => {
+            return Err(Error::tool(
+                "pod",
+                format!("could not be run; is CocoaPods installed? {}", e),
+            ));
         }
+    };
+    if !status.success() {
+        return Err(Error::Check("Trial `pod install` failed.".into()));
+    }
+    log::info!("Trial `pod install` succeeded.");
+    Ok(())
+}
 
-        Xcodebuild::create_xcframework_frameworks(
-            &mod_name,
-            build_target
-                .framework_targets()
-                .map(|x| dist_dir.join(x).join(format!("{mod_name}.framework"))),
-            dist_dir,
-        )
-        .unwrap();
+/// The directories shipped in the bundle. Source-built pods ship the crate
+/// and build `dist` during `pod install`.
+fn bundle_dirs(
+    pod_root: &Path,
+    package: &Package,
+    build_from_source: bool,
+) -> Result<Vec<PathBuf>, Error> {
+    if !build_from_source {
+        return Ok(vec![PathBuf::from("src"), PathBuf::from("dist")]);
+    }
 
-        Xcodebuild::create_xcframework_frameworks(
-            &ffi_mod_name,
-            build_target
-                .framework_targets()
-                .map(|x| dist_dir.join(x).join(format!("{ffi_mod_name}.framework"))),
-            dist_dir,
-        )
-        .unwrap();
+    let pod_root = crate::fs::canonicalize(pod_root)?;
+    let package_dir = crate::fs::canonicalize(package.manifest_path.parent().unwrap())?;
+    match package_dir.strip_prefix(&pod_root) {
+        Ok(rel) if !rel.as_os_str().is_empty() => Ok(vec![PathBuf::from("src"), rel.to_path_buf()]),
+        _ => Err(Error::Usage(
+            "Building from source requires the crate in a subdirectory of the pod.".into(),
+        )),
     }
 }
 
-fn current_arch(triple: &str) -> &str {
-    if triple.starts_with("aarch64-") {
-        return "arm64";
+fn bundle(args: BundleArgs) -> Result<(), Error> {
+    let has_subtree = std::fs::read_dir("./crate").is_ok();
+    let (metadata, package, targets) =
+        derive_manifest(pod_manifest(args.manifest_path.as_deref(), has_subtree))?;
+    let dist_dir = resolve_dist_dir(&metadata, &package, has_subtree);
+    let pod_root = resolve_pod_root(&dist_dir);
+    if dist_dir.join(INCOMPLETE_MARKER).exists() {
+        return Err(Error::Usage(format!(
+            "{} is from a build that did not finish; run `cargo pod build` again",
+            dist_dir.display()
+        )));
+    }
+    sync_podspec_version(&pod_root, &package, args.strict)?;
+    let config = crate::meta::config(&package);
+
+    let dirs = bundle_dirs(&pod_root, &package, config.build_from_source)?;
+
+    if config.acknowledgements && crate::cmd::is_dry_run() {
+        log::info!("Would write acknowledgements to {}", dist_dir.display());
+    } else if config.acknowledgements {
+        let acknowledgements = crate::acknowledgements::collect(&metadata, &package);
+        log::info!(
+            "Writing acknowledgements for {} crates",
+            acknowledgements.len()
+        );
+        crate::acknowledgements::write(&dist_dir, &acknowledgements)
+            .map_err(Error::io(&dist_dir))?;
+    }
+
+    let files = crate::bundle::collect_files(&pod_root, &dirs, &config.bundle)?;
+
+    let options = ArchiveOptions {
+        format: args.format.or(config.bundle.format).unwrap_or_default(),
+        compression: args
+            .compression
+            .or(config.bundle.compression)
+            .unwrap_or_default(),
+        level: args.compression_level.or(config.bundle.compression_level),
+    };
+    if options.compression == Compression::Zstd {
+        log::warn!("CocoaPods cannot extract zstd archives from an :http source.");
+    }
+
+    let output_path = bundle_output_path(
+        &pod_root,
+        &package,
+        args.output.as_deref(),
+        options.format,
+        options.compression,
+        None,
+    )?;
+    write_archive(&pod_root, &files, &output_path, &options)?;
+    let dry_run = crate::cmd::is_dry_run();
+    if let Some(max_size) = config.max_bundle_size.as_deref().filter(|_| !dry_run) {
+        check_bundle_size(
+            &pod_root,
+            &files,
+            &output_path,
+            max_size,
+            !args.allow_oversize,
+        )?;
+    }
+    update_podspec_sha256(&pod_root, &output_path)?;
+    if args.verify && !dry_run {
+        verify_bundle(&pod_root, &output_path, &options)?;
+    }
+    let mut artifacts = vec![output_path.clone()];
+
+    if args.split_platforms {
+        for (platform, suffix, build_target) in SPLIT_PLATFORMS {
+            let platform_output = bundle_output_path(
+                &pod_root,
+                &package,
+                args.output.as_deref(),
+                options.format,
+                options.compression,
+                Some(platform),
+            )?;
+            bundle_platform(
+                &pod_root,
+                &package,
+                &targets,
+                &dist_dir,
+                &platform_output,
+                (platform, suffix, *build_target),
+                &options,
+            )?;
+            artifacts.push(platform_output);
+        }
     }
 
-    if triple.starts_with("x86_64-") {
-        return "x86_64";
+    let output_dir = output_path
+        .parent()
+        .map(Path::to_path_buf)
+        .unwrap_or_default();
+    // Package.swift vends the zips, so keep them current alongside the pod.
+    if args.zip_xcframeworks || pod_root.join(crate::package_swift::FILE_NAME).exists() {
+        artifacts.extend(zip_xcframeworks(&dist_dir, &output_dir)?);
+    }
+    if args.artifactbundle {
+        artifacts.extend(zip_artifactbundles(
+            &package,
+            &targets,
+            &dist_dir,
+            &output_dir,
+        )?);
     }
 
-    panic!("unsupported triple: {}", triple);
+    for path in &artifacts {
+        crate::message::artifact("bundle", path);
+    }
+    write_checksums(&output_dir, &artifacts, args.sign)
 }
 
-const INFO_PLIST: &str = r#"
-<?xml version="1.0" encoding="UTF-8"?>
-<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
-<plist version="1.0">
-<dict>
-	<key>CFBundleExecutable</key>
-	<string>%BUNDLE_NAME%</string>
-	<key>CFBundleIdentifier</key>
-	<string>internal.cargo-cocoapods.%BUNDLE_NAME%</string>
-	<key>CFBundleInfoDictionaryVersion</key>
-	<string>6.0</string>
-	<key>CFBundleName</key>
-	<string>%BUNDLE_NAME%</string>
-	<key>CFBundlePackageType</key>
-	<string>FMWK</string>
-</dict>
-</plist>
-"#;
+/// Fails (or warns, if not `strict`) when the archive is larger than
+/// `max_size`, listing the largest files that went into it.
+fn check_bundle_size(
+    root: &Path,
+    files: &[PathBuf],
+    archive_path: &Path,
+    max_size: &str,
+    strict: bool,
+) -> Result<(), Error> {
+    let max_bytes = match crate::bundle::parse_size(max_size) {
+        Ok(v) => v,
+        Err(e) => {
+            return Err(Error::Usage(format!("Invalid max_bundle_size: {}", e)));
+        }
+    };
+    let size = std::fs::metadata(archive_path)
+        .map_err(Error::io(archive_path))?
+        .len();
+    if size <= max_bytes {
+        return Ok(());
+    }
 
-fn build_ffi_frameworks(
-    package: &Package,
-    targets: &[Target],
-    dist_dir: &Path,
-    build_target: BuildTarget,
-) {
-    let package_dir = package.manifest_path.parent().unwrap();
-    let headers_path = package_dir.join("headers");
+    let message = format!(
+        "{} is {}, which exceeds max_bundle_size of {}.",
+        archive_path.display(),
+        crate::bundle::format_size(size),
+        crate::bundle::format_size(max_bytes)
+    );
+    if !strict {
+        log::warn!("{}", message);
+    }
 
-    for target in targets {
-        let sys_name = target.name.replace('-', "_");
-        let mod_name = format!("{sys_name}_ffi").to_camel_case();
-        let fw_name = format!("{mod_name}.framework");
-
-        for triple in build_target.triples() {
-            let triple_dir = dist_dir.join(triple);
-            let fw_dir = triple_dir.join(&fw_name);
-
-            let headers_dir = fw_dir.join("Headers");
-            std::fs::create_dir_all(&fw_dir).unwrap();
-            std::fs::create_dir_all(&headers_dir).unwrap();
-            std::fs::create_dir_all(&fw_dir.join("Modules")).unwrap();
-            std::fs::write(
-                fw_dir.join("Info.plist"),
-                INFO_PLIST.replace("%BUNDLE_NAME%", &mod_name),
-            )
-            .unwrap();
+    let mut sizes = files
+        .iter()
+        .map(|x| {
+            let path = root.join(x);
+            let size = std::fs::metadata(&path).map_err(Error::io(&path))?.len();
+            Ok((size, x))
+        })
+        .collect::<Result<Vec<_>, Error>>()?;
+    sizes.sort_by(|a, b| b.cmp(a));
+    log::info!("Largest files (uncompressed):");
+    for (size, path) in sizes.iter().take(10) {
+        log::info!(
+            "  {:>10}  {}",
+            crate::bundle::format_size(*size),
+            path.display()
+        );
+    }
 
-            dircpy::copy_dir(&headers_path, &headers_dir).unwrap();
+    if strict {
+        return Err(Error::Check(message));
+    }
+    Ok(())
+}
 
-            std::fs::copy(
-                triple_dir.join(format!("lib{sys_name}.a")),
-                fw_dir.join(&mod_name),
-            )
-            .unwrap();
+/// Extracts the archive and checks that every path referenced by the podspec
+/// exists inside it.
+fn verify_bundle(
+    pod_root: &Path,
+    archive_path: &Path,
+    options: &ArchiveOptions,
+) -> Result<(), Error> {
+    let podspec_path = match find_podspec(pod_root) {
+        Some(v) => v,
+        None => {
+            return Err(Error::Usage(
+                "No podspec found to verify the bundle against.".into(),
+            ));
+        }
+    };
+    let spec = crate::fs::read_to_string(&podspec_path)?;
 
-            std::fs::write(
-                fw_dir.join("Modules").join("module.modulemap"),
-                format!(
-                    "framework module {mod_name} {{
-    header \"{sys_name}.h\"
-    link \"{mod_name}\"
-}}"
-                ),
-            )
-            .unwrap();
+    let tempdir = crate::fs::tempdir()?;
+    crate::bundle::extract(archive_path, options, tempdir.path())?;
+
+    let missing = crate::podspec::referenced_paths(&spec)
+        .into_iter()
+        .filter(|pattern| {
+            let full_pattern = tempdir.path().join(pattern);
+            glob(&full_pattern.to_string_lossy())
+                .map(|mut x| x.next().is_none())
+                .unwrap_or(true)
+        })
+        .collect::<Vec<_>>();
+
+    if !missing.is_empty() {
+        for pattern in &missing {
+            log::error!(
+                "{} references '{}', which is not in {}",
+                podspec_path.display(),
+                pattern,
+                archive_path.display()
+            );
         }
+        return Err(Error::Check(format!(
+            "{} is missing files referenced by {}",
+            archive_path.display(),
+            podspec_path.display()
+        )));
+    }
 
-        if build_target.is_ios() {
-            let output_path = dist_dir.join("ios-simulator").join(&fw_name);
-            std::fs::create_dir_all(&output_path).unwrap();
-            lipo(
-                [
-                    dist_dir
-                        .join("aarch64-apple-ios-sim")
-                        .join(&fw_name)
-                        .join(&mod_name),
-                    dist_dir
-                        .join("x86_64-apple-ios")
-                        .join(&fw_name)
-                        .join(&mod_name),
-                ]
-                .iter(),
-                &output_path.join(&mod_name),
-            )
-            .unwrap();
+    log::info!(
+        "Verified {} against {}",
+        archive_path.display(),
+        podspec_path.display()
+    );
+    Ok(())
+}
 
-            dircpy::copy_dir(
-                dist_dir
-                    .join("aarch64-apple-ios-sim")
-                    .join(&fw_name)
-                    .join("Headers"),
-                output_path.join("Headers"),
-            )
-            .unwrap();
-            dircpy::copy_dir(
-                dist_dir
-                    .join("aarch64-apple-ios-sim")
-                    .join(&fw_name)
-                    .join("Modules"),
-                output_path.join("Modules"),
-            )
-            .unwrap();
-            std::fs::write(
-                output_path.join("Info.plist"),
-                INFO_PLIST.replace("%BUNDLE_NAME%", &mod_name),
+/// Writes `<Ffi>.artifactbundle.zip` for each target into `output_dir`.
+fn zip_artifactbundles(
+    package: &Package,
+    targets: &[Target],
+    dist_dir: &Path,
+    output_dir: &Path,
+) -> Result<Vec<PathBuf>, Error> {
+    let headers_dir = package.manifest_path.parent().unwrap().join("headers");
+    let tempdir = crate::fs::tempdir()?;
+    targets
+        .iter()
+        .map(|target| {
+            let (_, ffi_mod_name) = framework_names(target);
+            let lib_name = target.name.replace('-', "_");
+            crate::artifactbundle::write(
+                tempdir.path(),
+                &ffi_mod_name,
+                &package.version.to_string(),
+                &lib_name,
+                dist_dir,
+                &headers_dir,
             )
-            .unwrap();
+            .map_err(Error::io(dist_dir))?;
+
+            let name = format!("{}.artifactbundle", ffi_mod_name);
+            let files = crate::bundle::collect_files(
+                tempdir.path(),
+                &[PathBuf::from(&name)],
+                &Default::default(),
+            )?
+            .into_iter()
+            .filter(|x| x.starts_with(&name))
+            .collect::<Vec<_>>();
+            let zip_path = output_dir.join(format!(
+                "{}{}",
+                ffi_mod_name,
+                crate::artifactbundle::ZIP_SUFFIX
+            ));
+            let options = ArchiveOptions {
+                format: ArchiveFormat::Zip,
+                compression: Compression::default(),
+                level: None,
+            };
+            write_archive(tempdir.path(), &files, &zip_path, &options)?;
+            Ok(zip_path)
+        })
+        .collect()
+}
+
+/// Zips each xcframework in `dist_dir` into `output_dir`.
+fn zip_xcframeworks(dist_dir: &Path, output_dir: &Path) -> Result<Vec<PathBuf>, Error> {
+    let mut xcframeworks = glob(&dist_dir.join("*.xcframework").to_string_lossy())
+        .unwrap()
+        .filter_map(Result::ok)
+        .collect::<Vec<_>>();
+    xcframeworks.sort();
+
+    xcframeworks
+        .into_iter()
+        .map(|xcframework| {
+            let name = xcframework
+                .file_name()
+                .unwrap()
+                .to_string_lossy()
+                .to_string();
+            let files = crate::bundle::collect_files(
+                dist_dir,
+                &[PathBuf::from(&name)],
+                &Default::default(),
+            )?
+            .into_iter()
+            .filter(|x| x.starts_with(&name))
+            .collect::<Vec<_>>();
+            let zip_path = output_dir.join(format!("{}.zip", name));
+            let options = ArchiveOptions {
+                format: ArchiveFormat::Zip,
+                compression: Compression::default(),
+                level: None,
+            };
+            write_archive(dist_dir, &files, &zip_path, &options)?;
+            Ok(zip_path)
+        })
+        .collect()
+}
+
+/// Writes `SHA256SUMS` for the given artifacts, optionally signing it.
+fn write_checksums(output_dir: &Path, artifacts: &[PathBuf], sign: bool) -> Result<(), Error> {
+    if crate::cmd::is_dry_run() {
+        log::info!(
+            "Would write {}{}",
+            output_dir.join(crate::checksum::SUMS_FILE_NAME).display(),
+            if sign { " and sign it with gpg" } else { "" }
+        );
+        return Ok(());
+    }
+    let sums_path = match crate::checksum::write_sums(output_dir, artifacts) {
+        Ok(v) => v,
+        Err(e) => {
+            return Err(Error::io(output_dir)(e));
         }
+    };
+    log::info!("Wrote {}", sums_path.display());
+    crate::message::artifact("checksums", &sums_path);
 
-        if build_target.is_macos() {
-            let output_path = dist_dir.join("macos-universal").join(&fw_name);
-            std::fs::create_dir_all(&output_path).unwrap();
-            lipo(
-                [
-                    dist_dir
-                        .join("aarch64-apple-darwin")
-                        .join(&fw_name)
-                        .join(&mod_name),
-                    dist_dir
-                        .join("x86_64-apple-darwin")
-                        .join(&fw_name)
-                        .join(&mod_name),
-                ]
-                .iter(),
-                &output_path.join(&mod_name),
-            )
-            .unwrap();
+    if !sign {
+        return Ok(());
+    }
 
-            dircpy::copy_dir(
-                dist_dir
-                    .join("aarch64-apple-darwin")
-                    .join(&fw_name)
-                    .join("Headers"),
-                output_path.join("Headers"),
-            )
-            .unwrap();
-            dircpy::copy_dir(
-                dist_dir
-                    .join("aarch64-apple-darwin")
-                    .join(&fw_name)
-                    .join("Modules"),
-                output_path.join("Modules"),
-            )
-            .unwrap();
-            std::fs::write(
-                output_path.join("Info.plist"),
-                INFO_PLIST.replace("%BUNDLE_NAME%", &mod_name),
-            )
-            .unwrap();
+    let status = crate::cmd::status(
+        std::process::Command::new("gpg")
+            .args(["--batch", "--yes", "--detach-sign", "--armor"])
+            .arg(&sums_path),
+    );
+    match status {
+        Ok(status) if status.success() => {
+            log::info!("Signed {}", sums_path.display());
+            crate::message::artifact(
+                "signature",
+                &output_dir.join(format!("{}.asc", crate::checksum::SUMS_FILE_NAME)),
+            );
+        }
+        Ok(_) => {
+            return Err(Error::tool(
+                "gpg",
+                format!("could not sign {}", sums_path.display()),
+            ));
+        }
+        Err(e) => {
+            return Err(Error::tool("gpg", e.to_string()));
         }
     }
+    Ok(())
 }
 
-fn build(args: BuildArgs) {
-    let has_subtree = std::fs::read_dir("./crate").is_ok();
-    let (metadata, package, targets) = derive_manifest(if has_subtree {
-        Some(Path::new("./crate/Cargo.toml"))
-    } else {
-        args.manifest_path.as_deref()
-    });
+fn write_archive(
+    root: &Path,
+    files: &[PathBuf],
+    output_path: &Path,
+    options: &ArchiveOptions,
+) -> Result<(), Error> {
+    if crate::cmd::is_dry_run() {
+        log::info!(
+            "Would write {} with {} files",
+            output_path.display(),
+            files.len()
+        );
+        return Ok(());
+    }
+    if let Some(parent) = output_path.parent() {
+        crate::fs::create_dir_all(parent)?;
+    }
+    log::info!("Writing {}", output_path.display());
 
-    let dist_dir = if has_subtree {
-        Path::new("./dist").to_path_buf()
-    } else {
-        Path::new(&metadata.target_directory)
-            .parent()
-            .unwrap()
-            .join("dist")
+    let result = match options.format {
+        ArchiveFormat::Tar => {
+            crate::bundle::write_tar(root, files, output_path, options.compression, options.level)
+        }
+        ArchiveFormat::Zip => crate::bundle::write_zip(root, files, output_path),
     };
-    std::fs::create_dir_all(&dist_dir).unwrap();
+    if result.is_err() {
+        let _ = std::fs::remove_file(output_path);
+    }
+    result
+}
 
-    let build_target = match (args.is_ios, args.is_macos) {
-        (true, true) | (false, false) => BuildTarget::Both,
-        (true, false) => BuildTarget::_iOS,
-        (false, true) => BuildTarget::MacOS,
+/// Writes a single-platform bundle to `platform_output`, containing only
+/// that platform's slices, along with a matching `<Name>-<Platform>.podspec`.
+fn bundle_platform(
+    pod_root: &Path,
+    package: &Package,
+    targets: &[Target],
+    dist_dir: &Path,
+    platform_output: &Path,
+    (platform, suffix, build_target): (&str, &str, BuildTarget),
+    options: &ArchiveOptions,
+) -> Result<(), Error> {
+    let podspec_path = match find_podspec(pod_root) {
+        Some(v) => v,
+        None => {
+            return Err(Error::Usage(
+                "No podspec found; cannot split platforms.".into(),
+            ));
+        }
     };
+    if crate::cmd::is_dry_run() {
+        log::info!(
+            "Would write the {} bundle {} and its podspec",
+            platform,
+            platform_output.display()
+        );
+        return Ok(());
+    }
 
-    build_static_libs(
-        args.cargo_args,
-        &metadata,
-        &package,
-        &targets,
-        &dist_dir,
-        build_target,
-    );
+    let staging = crate::fs::tempdir()?;
+    let staging_dist = staging.path().join("dist");
+    crate::fs::create_dir_all(&staging_dist)?;
 
-    build_ffi_frameworks(&package, &targets, &dist_dir, build_target);
-    build_safe_frameworks(&package, &targets, &dist_dir, build_target);
-}
+    for target in targets {
+        let (mod_name, ffi_mod_name) = framework_names(target);
+        for name in [mod_name, ffi_mod_name] {
+            log::debug!("Creating the {} xcframework for {}", platform, name);
+            Xcodebuild::create_xcframework_frameworks(
+                &name,
+                build_target
+                    .framework_targets()
+                    .map(|x| dist_dir.join(x).join(format!("{name}.framework"))),
+                &staging_dist,
+            )?;
+        }
+    }
 
-fn bundle(_args: BundleArgs) {
-    let mut builder = globset::GlobSetBuilder::new();
-    builder.add(globset::Glob::new("*.podspec").unwrap());
-    builder.add(globset::Glob::new("LICENSE").unwrap());
-    builder.add(globset::Glob::new("LICENSE*").unwrap());
-    builder.add(globset::Glob::new("README").unwrap());
-    builder.add(globset::Glob::new("README*").unwrap());
-    let set = builder.build().unwrap();
+    let config = crate::meta::config(package);
+    let src_files =
+        crate::bundle::collect_files(pod_root, &[PathBuf::from("src")], &config.bundle)?;
+    for file in src_files
+        .iter()
+        .filter(|x| x.extension().map(|x| x != "podspec").unwrap_or(true))
+    {
+        let dest = staging.path().join(file);
+        crate::fs::create_dir_all(dest.parent().unwrap())?;
+        crate::fs::copy(pod_root.join(file), dest)?;
+    }
 
-    let cur = std::env::current_dir().unwrap();
-    let files = std::fs::read_dir(&cur)
+    let asset_name = platform_output
+        .file_name()
         .unwrap()
-        .filter_map(Result::ok)
-        .filter(|x| set.is_match(x.path()))
-        .map(|x| x.path().strip_prefix(&cur).unwrap().to_path_buf());
-
-    std::process::Command::new("tar")
-        .arg("zcvf")
-        .arg("cargo-pod.tgz")
-        .args(files)
-        .args(["src", "dist"])
-        .status()
-        .unwrap();
-}
-
-#[derive(Debug, Deserialize)]
-struct ReleaseResponse {
-    url: String,
-    upload_url: String,
-    id: u32,
-    tag_name: String,
+        .to_string_lossy()
+        .to_string();
+
+    let pod_name = format!("{}-{}", pod_name(package), suffix);
+    let spec = crate::fs::read_to_string(&podspec_path)?;
+    let variant = crate::podspec::platform_variant(&spec, &pod_name, platform, &asset_name);
+    let variant_path = pod_root.join(format!("{}.podspec", pod_name));
+
+    let files = crate::bundle::collect_files(
+        staging.path(),
+        &[PathBuf::from("src"), PathBuf::from("dist")],
+        &Default::default(),
+    )?;
+    write_archive(staging.path(), &files, platform_output, options)?;
+
+    log::info!("Writing {}", variant_path.display());
+    crate::fs::write(&variant_path, variant)?;
+    update_podspec_sha256_at(&variant_path, platform_output)?;
+    crate::message::artifact("podspec", &variant_path);
+    Ok(())
 }
 
-#[derive(Debug, Serialize)]
-struct ReleaseRequest {
-    tag_name: String,
-}
-
-async fn publish(args: PublishArgs) {
-    if args.token.is_none() {
-        log::error!("You must provide a GitHub access token");
-        std::process::exit(1);
+/// Builds a small app that installs the pod from the local podspec, to catch
+/// linker and module map problems in the built frameworks. The app lives in
+/// `PodIntegration/` and is generated on first use.
+fn integration_test(args: IntegrationTestArgs) -> Result<(), Error> {
+    let has_subtree = std::fs::read_dir("./crate").is_ok();
+    let (metadata, package, targets) =
+        derive_manifest(pod_manifest(args.manifest_path.as_deref(), has_subtree))?;
+    let dist_dir = resolve_dist_dir(&metadata, &package, has_subtree);
+    let pod_root = resolve_pod_root(&dist_dir);
+
+    if !dist_dir.is_dir() {
+        return Err(Error::Usage(
+            "No dist directory found; run `cargo pod build` first.".into(),
+        ));
     }
-    if args.tag.is_none() {
-        log::error!("You must provide a tag name");
-        std::process::exit(1);
+    if find_podspec(&pod_root).is_none() {
+        return Err(Error::Usage(format!(
+            "No podspec found in {}.",
+            pod_root.display()
+        )));
     }
-    let tag = args.tag.unwrap();
 
-    let api_url: &str = "https://api.github.com/";
-    let mut header_map = reqwest::header::HeaderMap::new();
-    let mut auth_value =
-        reqwest::header::HeaderValue::from_str(format!("token {}", args.token.unwrap()).as_str())
-            .unwrap();
-    auth_value.set_sensitive(true);
-    header_map.insert(reqwest::header::AUTHORIZATION, auth_value);
-    header_map.insert(
-        "user-agent",
-        reqwest::header::HeaderValue::from_static("cargo-cocoapods"),
-    );
-    let api_client = reqwest::Client::builder()
-        .default_headers(header_map)
-        .build()
-        .unwrap();
+    let dir = pod_root.join(crate::example_app::INTEGRATION_DIR_NAME);
+    if !dir.exists() && crate::cmd::is_dry_run() {
+        log::info!("Would write integration test app to {}", dir.display());
+    } else if !dir.exists() {
+        let name = pod_name(&package);
+        let modules = targets
+            .iter()
+            .map(|x| framework_names(x).0)
+            .collect::<Vec<_>>();
+        let config = crate::meta::config(&package);
+        log::info!("Writing integration test app to {}", dir.display());
+        if let Err(e) = crate::example_app::write(
+            &dir,
+            &format!("{}Integration", name.replace('-', "")),
+            &name,
+            &modules,
+            &config.min_versions().ios,
+        ) {
+            return Err(Error::Io {
+                path: dir,
+                source: e,
+            });
+        }
+    }
 
-    let repo_url: String = if let Some(u) = args.url {
-        u
-    } else {
-        String::from_utf8(
-            std::process::Command::new("git")
-                .args(["remote", "get-url", "origin"])
-                .output()
-                .unwrap()
-                .stdout,
-        )
-        .unwrap()
-        .trim()
-        .to_string()
-    };
-    log::trace!("Derived repo URL {:?}", repo_url);
-
-    let repo_tail: String = {
-        let s = repo_url.as_str();
-        let git_tail = if s.starts_with("git@github") {
-            let (_, tail) = s.split_once(':').unwrap();
-            tail
-        } else if s.starts_with("https://github.com/") {
-            let (_, tail) = s.split_at("https://github.com/".len());
-            tail
-        } else {
-            panic!("Could not parse the repo url {:?}", repo_url);
-        };
-        let (head, _) = git_tail.split_at(git_tail.len() - 4);
-        head.to_string()
+    let mut cmd = std::process::Command::new("sh");
+    cmd.arg(dir.join(crate::example_app::BUILD_SCRIPT_NAME));
+    if crate::cmd::skip(&cmd) {
+        return Ok(());
+    }
+    log::trace!("Calling: {:?}", &cmd);
+    let status = match crate::cmd::status(&mut cmd) {
+        Ok(v) => v,
+        Err(e) => {
+            return Err(Error::tool(
+                "sh",
+                format!("could not run the integration test: {}", e),
+            ));
+        }
     };
-    log::trace!("Derived repo tail {:?}", repo_tail);
-
-    log::info!("Getting current releases...");
+    if !status.success() {
+        return Err(Error::Check("Integration test failed.".into()));
+    }
+    log::info!("Integration test passed.");
+    Ok(())
+}
 
-    let current_releases: Vec<ReleaseResponse> = api_client
-        .get(format!("{}repos/{}/releases", api_url, repo_tail))
-        .send()
-        .await
-        .unwrap()
-        .json()
-        .await
-        .unwrap();
+fn test(args: TestArgs) -> Result<(), Error> {
+    let has_subtree = std::fs::read_dir("./crate").is_ok();
+    let (metadata, package, targets) =
+        derive_manifest(pod_manifest(args.manifest_path.as_deref(), has_subtree))?;
+    let dist_dir = resolve_dist_dir(&metadata, &package, has_subtree);
+    let pod_root = resolve_pod_root(&dist_dir);
 
-    let relevant_release: Vec<ReleaseResponse> = current_releases
-        .into_iter()
-        .filter(|r| r.tag_name == tag)
-        .collect();
+    let pattern = pod_root.join(crate::xctest::TESTS_DIR).join("**/*.swift");
+    let tests = glob(&pattern.to_string_lossy())
+        .unwrap()
+        .filter_map(Result::ok)
+        .collect::<Vec<_>>();
+    if tests.is_empty() {
+        return Err(Error::Usage(format!(
+            "No Swift tests found in {}.",
+            pod_root.join(crate::xctest::TESTS_DIR).display()
+        )));
+    }
 
-    let release_id: u32 = match relevant_release.get(0) {
-        Some(release) => release.id,
-        None => 0,
+    let platforms = match (args.is_ios, args.is_macos) {
+        (true, false) => vec![Platform::IosSimulator],
+        (false, true) => vec![Platform::MacOS],
+        _ => vec![Platform::MacOS, Platform::IosSimulator],
     };
+    let modules = targets
+        .iter()
+        .map(|x| framework_names(x).0)
+        .collect::<Vec<_>>();
+    let name = format!("{}Tests", pod_name(&package).replace('-', ""));
+    let simulator = args.simulator.as_deref().unwrap_or("booted");
+    let tempdir = crate::fs::tempdir()?;
+
+    let mut failed = vec![];
+    for platform in platforms {
+        let frameworks_dir = dist_dir.join(platform.frameworks_dir());
+        if !frameworks_dir.is_dir() {
+            return Err(Error::Usage(format!(
+                "No frameworks built for {}; run `cargo pod build` first.",
+                platform
+            )));
+        }
 
-    if release_id != 0 {
-        if args.force {
-            log::info!("Deleting release...");
-            api_client
-                .delete(format!(
-                    "{}repos/{}/releases/{}",
-                    api_url, repo_tail, release_id
-                ))
-                .send()
-                .await
-                .unwrap();
-        } else {
-            log::error!(
-                "Tag {} already exists at release {}",
-                tag,
-                relevant_release.get(0).unwrap().url
-            );
-            std::process::exit(1);
+        log::info!("Testing on {}", platform);
+        let out_dir = tempdir.path().join(platform.frameworks_dir());
+        let bundle = match crate::xctest::build(
+            platform,
+            &name,
+            &frameworks_dir,
+            &modules,
+            &tests,
+            &out_dir,
+        ) {
+            Ok(v) => v,
+            Err(e) => {
+                return Err(Error::tool("swiftc", e.to_string()));
+            }
+        };
+        match crate::xctest::run(platform, &bundle, simulator) {
+            Ok(v) if v.success() => {}
+            Ok(_) => failed.push(platform.to_string()),
+            Err(e) => {
+                return Err(Error::tool(
+                    "xctest",
+                    format!("could not run the tests on {}: {}", platform, e),
+                ));
+            }
         }
     }
 
-    let args = ReleaseRequest { tag_name: tag };
-    log::info!("Creating new release...");
-    let new_release: ReleaseResponse = api_client
-        .post(format!("{}repos/{}/releases", api_url, repo_tail))
-        .json(&args)
-        .send()
-        .await
-        .unwrap()
-        .json()
-        .await
-        .unwrap();
+    if !failed.is_empty() {
+        return Err(Error::Check(format!(
+            "Tests failed on {}.",
+            failed.join(", ")
+        )));
+    }
+    log::info!("Tests passed.");
+    Ok(())
+}
 
-    let mut asset_data: Vec<u8> = Vec::new();
-    File::open("cargo-pod.tgz")
-        .unwrap()
-        .read_to_end(&mut asset_data)
-        .unwrap();
+/// Builds the Rust library for the example in a target directory of its own,
+/// so that the flags used do not invalidate the release build. Returns the
+/// directory holding the library.
+fn build_example_lib(
+    manifest_path: Option<&Path>,
+    triple: &str,
+    debug: bool,
+    sanitizer: Option<Sanitizer>,
+) -> Result<PathBuf, Error> {
+    let has_subtree = std::fs::read_dir("./crate").is_ok();
+    let (metadata, package, _) = derive_manifest(pod_manifest(manifest_path, has_subtree))?;
+    let package_dir = package.manifest_path.parent().unwrap();
+    let min_versions = crate::meta::config(&package).min_versions();
 
-    log::info!("Uploading cargo-pod.tgz...");
-    api_client
-        .post({
-            let (head, _) = new_release.upload_url.as_str().split_once('{').unwrap();
-            head.to_string()
-        })
-        .body(asset_data)
-        .query(&[("name", "cargo-pod.tgz")])
-        .header("content-type", "application/x-gtar")
-        .send()
-        .await
-        .unwrap();
-}
+    let target_dir = match sanitizer {
+        Some(v) => metadata
+            .target_directory
+            .join(format!("cargo-pod-example-{}", v.name())),
+        None => metadata.target_directory.join("cargo-pod-example"),
+    };
+    let mut cargo_args = vec![
+        "--lib".to_string(),
+        "--target-dir".to_string(),
+        target_dir.to_string_lossy().to_string(),
+    ];
+    if !debug {
+        cargo_args.push("--release".into());
+    }
 
-fn example(args: ExampleArgs) {
-    // swiftc example/**/*.swift src/**/*.swift -import-objc-header src/DivvunSpell/divvunspell.h \
-    // -L dist/aarch64-apple-darwin -ldivvunspell -o test
-    let tempdir = tempfile::tempdir().unwrap();
+    log::info!("Building {} for the example...", package.name);
+    crate::cargo::build(
+        package_dir,
+        triple,
+        &cargo_args,
+        &min_versions,
+        sanitizer.is_some(),
+        sanitizer,
+        false,
+    )?;
+    Ok(target_dir
+        .join(triple)
+        .join(if debug { "debug" } else { "release" }))
+}
 
-    let dist_dir = format!("dist/{}-apple-darwin", std::env::consts::ARCH);
+/// Compiler flags linking the example against the raw libraries and headers,
+/// with the bindings compiled from source.
+/// With `universal`, the macOS libraries are combined into that directory
+/// and linked instead of the `triple` ones.
+fn library_args(
+    manifest_path: Option<&Path>,
+    triple: &str,
+    universal: Option<&Path>,
+    debug: bool,
+    sanitizer: Option<Sanitizer>,
+) -> Result<Vec<String>, Error> {
+    let has_subtree = std::fs::read_dir("./crate").is_ok();
+    let (metadata, package, _) = derive_manifest(pod_manifest(manifest_path, has_subtree))?;
+    let pod_dist_dir = resolve_dist_dir(&metadata, &package, has_subtree);
+    let lib_dir = |triple: &str| {
+        let dir = if debug || sanitizer.is_some() {
+            build_example_lib(manifest_path, triple, debug, sanitizer)?
+        } else {
+            pod_dist_dir.join(triple)
+        }
+        .to_string_lossy()
+        .to_string();
+        if !Path::new(&dir).exists() {
+            return Err(Error::Usage(format!(
+                "{} not found; run `cargo pod build` first.",
+                dir
+            )));
+        }
+        Ok(dir)
+    };
+    let dist_dir = match universal {
+        None => lib_dir(triple)?,
+        Some(out_dir) => {
+            let dirs = MACOS_TRIPLES
+                .iter()
+                .map(|x| lib_dir(x))
+                .collect::<Result<Vec<_>, _>>()?;
+            crate::fs::create_dir_all(out_dir)?;
+            for lib in glob(&format!("{}/lib*.a", dirs[0]))
+                .unwrap()
+                .filter_map(Result::ok)
+            {
+                let name = lib.file_name().unwrap();
+                lipo(
+                    dirs.iter().map(|x| Path::new(x).join(name)),
+                    &out_dir.join(name),
+                )?;
+            }
+            out_dir.to_string_lossy().to_string()
+        }
+    };
 
     let headers = glob::glob("src/**/*.h")
         .unwrap()
@@ -1081,34 +2531,706 @@ fn example(args: ExampleArgs) {
     log::debug!("Headers: {:?}", &headers);
     log::debug!("Libs: {:?}", &libs);
 
-    let example_bin = tempdir.path().join("example");
-
-    let swift_example = glob("example/**/*.swift")
+    let swift_src = glob("src/**/*.swift")
         .unwrap()
         .filter_map(Result::ok)
-        .collect::<Vec<PathBuf>>();
-    let swift_src = glob("src/**/*.swift")
+        .map(|x| x.to_string_lossy().to_string());
+
+    Ok(swift_src
+        .chain(headers)
+        .chain(["-L".to_string(), dist_dir])
+        .chain(libs)
+        .collect())
+}
+
+/// The directory of the `platform` (`macos` or `ios`) device slice of an
+/// xcframework, failing if it has none.
+fn xcframework_slice(xcframework: &Path, platform: &str) -> Result<PathBuf, Error> {
+    let slice = std::fs::read_dir(xcframework)
+        .into_iter()
+        .flatten()
+        .filter_map(Result::ok)
+        .map(|x| x.path())
+        .find(|x| {
+            let name = x.file_name().unwrap().to_string_lossy();
+            x.is_dir()
+                && name.starts_with(&format!("{}-", platform))
+                && !name.ends_with("-simulator")
+        });
+    slice.ok_or_else(|| {
+        Error::Usage(format!(
+            "No {} slice in {}; run `cargo pod build` first.",
+            platform,
+            xcframework.display()
+        ))
+    })
+}
+
+/// Compiler flags importing the pod's modules from the `platform` slices
+/// (`macos` or `ios`) of the built xcframeworks, as a consumer would.
+fn xcframework_args(manifest_path: Option<&Path>, platform: &str) -> Result<Vec<String>, Error> {
+    let has_subtree = std::fs::read_dir("./crate").is_ok();
+    let (metadata, package, targets) = derive_manifest(pod_manifest(manifest_path, has_subtree))?;
+    let dist_dir = resolve_dist_dir(&metadata, &package, has_subtree);
+
+    let mut args = vec![];
+    for target in &targets {
+        let (mod_name, ffi_mod_name) = framework_names(target);
+        for name in [&ffi_mod_name, &mod_name] {
+            let xcframework = dist_dir.join(format!("{}.xcframework", name));
+            let slice = xcframework_slice(&xcframework, platform)?;
+            args.push("-F".to_string());
+            args.push(slice.to_string_lossy().to_string());
+        }
+        args.push("-framework".into());
+        args.push(mod_name);
+    }
+    Ok(args)
+}
+
+fn bench(args: BenchArgs) -> Result<(), Error> {
+    let has_subtree = std::fs::read_dir("./crate").is_ok();
+    let (metadata, package, targets) =
+        derive_manifest(pod_manifest(args.manifest_path.as_deref(), has_subtree))?;
+    let dist_dir = resolve_dist_dir(&metadata, &package, has_subtree);
+    let pod_root = resolve_pod_root(&dist_dir);
+
+    let bench_dir = pod_root.join(crate::bench::DIR_NAME);
+    let sources = glob(&bench_dir.join("**/*.swift").to_string_lossy())
         .unwrap()
         .filter_map(Result::ok)
         .collect::<Vec<_>>();
+    if sources.is_empty() {
+        return Err(Error::Usage(format!(
+            "No Swift benchmarks found in {}.",
+            bench_dir.display()
+        )));
+    }
+
+    let triples = match args.triple {
+        Some(v) if MACOS_TRIPLES.contains(&&*v) => vec![v],
+        Some(v) => {
+            return Err(Error::Usage(format!(
+                "Unsupported triple {}; expected one of {}.",
+                v,
+                MACOS_TRIPLES.join(", ")
+            )));
+        }
+        None => MACOS_TRIPLES.iter().map(|x| x.to_string()).collect(),
+    };
+
+    let frameworks_dir = dist_dir.join(Platform::MacOS.frameworks_dir());
+    if !frameworks_dir.is_dir() {
+        return Err(Error::Usage(
+            "No frameworks built for macOS; run `cargo pod build` first.".into(),
+        ));
+    }
+    let modules = targets
+        .iter()
+        .map(|x| framework_names(x).0)
+        .collect::<Vec<_>>();
+    let tempdir = crate::fs::tempdir()?;
+
+    let mut failed = vec![];
+    for triple in triples {
+        // Intel Macs cannot run arm64 code; Apple silicon runs x86_64 under
+        // Rosetta.
+        if std::env::consts::ARCH == "x86_64" && triple.starts_with("aarch64") {
+            log::warn!("Skipping {}, which this machine cannot run.", triple);
+            continue;
+        }
+
+        log::info!("Benchmarking {}", triple);
+        let out_dir = tempdir.path().join(&triple);
+        let executable =
+            match crate::bench::build(&triple, &frameworks_dir, &modules, &sources, &out_dir) {
+                Ok(v) => v,
+                Err(e) => {
+                    return Err(Error::tool("swiftc", e.to_string()));
+                }
+            };
+        match crate::bench::run(&executable) {
+            Ok(v) if v.success() => {}
+            Ok(_) => failed.push(triple),
+            Err(e) => {
+                return Err(Error::tool(
+                    "bench",
+                    format!("could not run the benchmarks for {}: {}", triple, e),
+                ));
+            }
+        }
+    }
+
+    if !failed.is_empty() {
+        return Err(Error::Check(format!(
+            "Benchmarks failed for {}.",
+            failed.join(", ")
+        )));
+    }
+    Ok(())
+}
+
+fn repl(args: ReplArgs) -> Result<(), Error> {
+    let has_subtree = std::fs::read_dir("./crate").is_ok();
+    let (metadata, package, targets) =
+        derive_manifest(pod_manifest(args.manifest_path.as_deref(), has_subtree))?;
+    let dist_dir = resolve_dist_dir(&metadata, &package, has_subtree);
+    let tempdir = crate::fs::tempdir()?;
+
+    // The REPL can only load dynamic libraries, so the static frameworks are
+    // linked whole into one.
+    let mut framework_args = vec![];
+    let mut modules = vec![];
+    let mut load_args = vec![];
+    for target in &targets {
+        let (mod_name, ffi_mod_name) = framework_names(target);
+        for name in [&ffi_mod_name, &mod_name] {
+            let slice =
+                xcframework_slice(&dist_dir.join(format!("{}.xcframework", name)), "macos")?;
+            framework_args.push("-F".to_string());
+            framework_args.push(slice.to_string_lossy().to_string());
+            if name == &mod_name {
+                let binary = slice.join(format!("{}.framework", name)).join(name);
+                load_args.extend(["-Xlinker".to_string(), "-force_load".to_string()]);
+                load_args.extend(["-Xlinker".to_string(), binary.to_string_lossy().to_string()]);
+            }
+        }
+        modules.push(mod_name);
+    }
 
+    let stub = tempdir.path().join("CargoPodRepl.swift");
+    crate::fs::write(&stub, "")?;
     let mut cmd = std::process::Command::new("swiftc");
-    cmd.args(swift_example)
-        .args(swift_src)
-        .args(headers)
-        .arg("-L")
-        .arg(dist_dir)
-        .args(libs)
-        .arg("-o")
-        .arg(&example_bin);
+    cmd.args(["-emit-library", "-module-name", "CargoPodRepl", "-o"])
+        .arg(tempdir.path().join("libCargoPodRepl.dylib"))
+        .arg(&stub)
+        .args(&framework_args)
+        .args(&load_args);
+    log::trace!("Calling: {:?}", &cmd);
+    if !crate::cmd::skip(&cmd)
+        && !crate::cmd::status(&mut cmd)
+            .map(|x| x.success())
+            .unwrap_or(false)
+    {
+        return Err(Error::tool("swiftc", "could not link the pod for the REPL"));
+    }
 
+    log::info!(
+        "Type `import {}` to use the bindings.",
+        modules.join("`, `import ")
+    );
+    let mut cmd = std::process::Command::new("swift");
+    cmd.arg("repl")
+        .args(&framework_args)
+        .arg("-L")
+        .arg(tempdir.path())
+        .arg("-lCargoPodRepl");
+    if crate::cmd::skip(&cmd) {
+        return Ok(());
+    }
     log::trace!("Calling: {:?}", &cmd);
-    cmd.status().unwrap();
+    if let Err(e) = crate::cmd::status(&mut cmd) {
+        return Err(Error::tool(
+            "swift",
+            format!("could not start the REPL: {}", e),
+        ));
+    }
+    Ok(())
+}
 
-    std::process::Command::new(example_bin)
-        .args(args.example_args)
-        .status()
-        .unwrap();
+fn playground(args: PlaygroundArgs) -> Result<(), Error> {
+    let has_subtree = std::fs::read_dir("./crate").is_ok();
+    let (metadata, package, targets) =
+        derive_manifest(pod_manifest(args.manifest_path.as_deref(), has_subtree))?;
+    let dist_dir = resolve_dist_dir(&metadata, &package, has_subtree);
+    let pod_root = resolve_pod_root(&dist_dir);
+
+    let name = pod_name(&package);
+    let dir = pod_root.join(format!(
+        "{}.{}",
+        crate::playground::name(&name),
+        crate::playground::EXTENSION
+    ));
+    if dir.exists() && !args.force {
+        return Err(Error::Usage(format!(
+            "{} already exists; pass --force to regenerate it.",
+            dir.display()
+        )));
+    }
+
+    let package_dir = package.manifest_path.parent().unwrap();
+    let mut xcframeworks = vec![];
+    let mut modules = vec![];
+    for target in &targets {
+        let (mod_name, ffi_mod_name) = framework_names(target);
+        xcframeworks.push(ffi_mod_name);
+        xcframeworks.push(mod_name.clone());
+        modules.push(mod_name);
+    }
+    let functions = crate::demo::public_functions(&package_dir.join("bindings"));
+    if crate::cmd::is_dry_run() {
+        log::info!("Would write playground to {}", dir.display());
+        return Ok(());
+    }
+    if let Err(e) =
+        crate::playground::write(&dir, &name, &dist_dir, &xcframeworks, &modules, &functions)
+    {
+        return Err(Error::Io {
+            path: dir,
+            source: e,
+        });
+    }
+    crate::message::artifact("playground", &dir);
+    log::info!(
+        "Open {} in Xcode or Swift Playgrounds. Run this again with --force after rebuilding.",
+        dir.display()
+    );
+    Ok(())
+}
+
+fn demo(args: DemoArgs) -> Result<(), Error> {
+    let has_subtree = std::fs::read_dir("./crate").is_ok();
+    let (metadata, package, targets) =
+        derive_manifest(pod_manifest(args.manifest_path.as_deref(), has_subtree))?;
+    let dist_dir = resolve_dist_dir(&metadata, &package, has_subtree);
+    let pod_root = resolve_pod_root(&dist_dir);
+
+    let dir = pod_root.join(crate::demo::DIR_NAME);
+    if dir.exists() && !args.force {
+        return Err(Error::Usage(format!(
+            "{} already exists; pass --force to regenerate it.",
+            dir.display()
+        )));
+    }
+
+    let package_dir = package.manifest_path.parent().unwrap();
+    let modules = targets
+        .iter()
+        .map(|x| framework_names(x).0)
+        .collect::<Vec<_>>();
+    let functions = crate::demo::public_functions(&package_dir.join("bindings"));
+    if crate::cmd::is_dry_run() {
+        log::info!("Would write demo app to {}", dir.display());
+        return Ok(());
+    }
+    crate::demo::write(&dir, &pod_name(&package), &modules, &functions).map_err(Error::io(&dir))?;
+    crate::message::artifact("demo", &dir);
+    log::info!(
+        "Run it with `cargo pod example --dir {} --framework`, adding `--device <id> --profile <profile>` for iOS.",
+        crate::demo::DIR_NAME
+    );
+    Ok(())
+}
+
+/// Builds and runs an example, returning the exit code to pass on.
+fn example(args: ExampleArgs) -> Result<i32, Error> {
+    // swiftc example/**/*.swift src/**/*.swift -import-objc-header src/DivvunSpell/divvunspell.h \
+    // -L dist/aarch64-apple-darwin -ldivvunspell -o test
+    let tempdir = crate::fs::tempdir()?;
+
+    if args.device.is_some() && (args.triple.is_some() || args.universal) {
+        return Err(Error::Usage(
+            "--triple and --universal select macOS slices, so cannot be combined with --device."
+                .into(),
+        ));
+    }
+
+    if args.device.is_some() && args.debug_lldb {
+        return Err(Error::Usage(
+            "--debug-lldb runs the example on this Mac, so cannot be combined with --device."
+                .into(),
+        ));
+    }
+
+    if args.device.is_some() && (!args.env.is_empty() || args.cwd.is_some()) {
+        return Err(Error::Usage("--env and --cwd apply to the example on this Mac, so cannot be combined with --device.".into()));
+    }
+    let env = args
+        .env
+        .iter()
+        .map(|x| {
+            x.split_once('=')
+                .ok_or_else(|| Error::Usage(format!("Invalid --env {:?}; expected KEY=VAL.", x)))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let signing = match (&args.device, &args.profile) {
+        (None, _) => None,
+        (Some(_), None) => {
+            return Err(Error::Usage("--device needs --profile, a development provisioning profile that includes the device.".into()));
+        }
+        (Some(_), Some(profile)) => {
+            match crate::device::Signing::new(
+                profile,
+                args.team.clone(),
+                args.identity.clone(),
+                args.bundle_id.clone(),
+            ) {
+                Ok(v) => Some(v),
+                Err(e) => {
+                    return Err(Error::Usage(e.to_string()));
+                }
+            }
+        }
+    };
+
+    if args.sanitize.is_some() && signing.is_some() {
+        return Err(Error::Usage(
+            "Sanitizers are only supported when running the example on this Mac.".into(),
+        ));
+    }
+
+    let triple = match (&signing, &args.triple) {
+        (Some(_), _) => crate::device::TRIPLE.to_string(),
+        (None, Some(triple)) if !MACOS_TRIPLES.contains(&triple.as_str()) => {
+            return Err(Error::Usage(format!(
+                "The example can only run {} here; use --device for iOS.",
+                MACOS_TRIPLES.join(" or ")
+            )));
+        }
+        (None, Some(triple)) => triple.clone(),
+        (None, None) => format!("{}-apple-darwin", std::env::consts::ARCH),
+    };
+
+    let debug = args.debug || args.debug_lldb;
+    if args.framework && (debug || args.sanitize.is_some()) {
+        return Err(Error::Usage("--framework links the release build, so cannot be combined with --debug, --debug-lldb or --sanitize.".into()));
+    }
+
+    let example_bin = tempdir.path().join("example");
+
+    let example_dir = args.dir.unwrap_or_else(|| PathBuf::from("example"));
+    let swift_example = glob(&example_dir.join("**/*.swift").to_string_lossy())
+        .unwrap()
+        .filter_map(Result::ok)
+        .collect::<Vec<PathBuf>>();
+    // SwiftUI apps, such as `cargo pod demo` writes, have an `@main` type
+    // rather than top-level code, and need macOS 11.
+    let is_app = swift_example.iter().any(|x| {
+        std::fs::read_to_string(x)
+            .map(|x| x.contains("@main"))
+            .unwrap_or(false)
+    });
+
+    let mut cmd = std::process::Command::new("swiftc");
+    cmd.args(swift_example);
+    if is_app {
+        cmd.arg("-parse-as-library");
+    }
+    if signing.is_none() && (is_app || args.triple.is_some()) {
+        let mut min_versions = MinVersions::default();
+        if is_app {
+            min_versions.macos = "11.0".into();
+        }
+        cmd.arg("-target")
+            .arg(crate::cmd::current_triple(&triple, &min_versions)?);
+    }
+    if args.framework {
+        let platform = if signing.is_some() { "ios" } else { "macos" };
+        cmd.args(xcframework_args(args.manifest_path.as_deref(), platform)?);
+    } else {
+        let universal_dir = tempdir.path().join("universal");
+        cmd.args(library_args(
+            args.manifest_path.as_deref(),
+            &triple,
+            Some(universal_dir.as_path()).filter(|_| args.universal),
+            debug,
+            args.sanitize,
+        )?);
+    }
+    cmd.arg("-o").arg(&example_bin);
+    if debug {
+        cmd.args(["-Onone", "-g"]);
+    }
+    if let Some(sanitizer) = args.sanitize {
+        cmd.arg(format!("-sanitize={}", sanitizer.name()));
+    }
+    if signing.is_some() {
+        let min_versions = MinVersions {
+            ios: crate::device::MIN_IOS_VERSION.into(),
+            ..Default::default()
+        };
+        cmd.arg("-sdk")
+            .arg(crate::cmd::current_sdk(crate::device::TRIPLE)?)
+            .arg("-target")
+            .arg(crate::cmd::current_triple(
+                crate::device::TRIPLE,
+                &min_versions,
+            )?);
+    }
+
+    crate::cmd::run("swiftc", &mut cmd)?;
+
+    let (device, signing) = match (args.device, signing) {
+        (Some(device), Some(signing)) => (device, signing),
+        _ => {
+            // lldb passes its environment and working directory on to the
+            // example.
+            let mut cmd = if args.debug_lldb {
+                crate::lldb::command(&example_bin, &args.example_args, args.sanitize.is_some())
+                    .map_err(Error::io(tempdir.path()))?
+            } else {
+                let mut cmd = std::process::Command::new(&example_bin);
+                cmd.args(&args.example_args);
+                cmd
+            };
+            cmd.envs(env);
+            if let Some(cwd) = &args.cwd {
+                cmd.current_dir(cwd);
+            }
+            if crate::cmd::skip(&cmd) {
+                return Ok(0);
+            }
+            log::trace!("Calling: {:?}", &cmd);
+            let status = match crate::cmd::status(&mut cmd) {
+                Ok(v) => v,
+                Err(e) => {
+                    return Err(Error::tool("example", format!("could not be run: {}", e)));
+                }
+            };
+            // The example's own exit code is passed on, as for `cargo run`.
+            return Ok(status.code().unwrap_or(1));
+        }
+    };
+
+    if crate::cmd::is_dry_run() {
+        log::info!(
+            "Would sign {} as {} and run it on {}",
+            example_bin.display(),
+            signing.bundle_id,
+            device
+        );
+        return Ok(0);
+    }
+    let app = match crate::device::bundle(tempdir.path(), &example_bin, &signing) {
+        Ok(v) => v,
+        Err(e) => {
+            return Err(Error::tool(
+                "codesign",
+                format!("could not bundle the example for the device: {}", e),
+            ));
+        }
+    };
+    log::info!("Running {} on {}", signing.bundle_id, device);
+    let status = crate::device::run(&device, &app, &signing.bundle_id, &args.example_args);
+    if !status.map(|x| x.success()).unwrap_or(false) {
+        return Err(Error::tool(
+            "devicectl",
+            format!("could not run the example on {}", device),
+        ));
+    }
+    Ok(0)
+}
+
+/// Where `publish` would send the release with `provider`, if configured.
+fn publish_destination(
+    provider: Option<Provider>,
+    config: &PublishConfig,
+    pod_root: &Path,
+    package: &Package,
+) -> Result<(Provider, Option<String>), Error> {
+    let key = crate::publish::render_key(
+        config.key.as_deref().unwrap_or(DEFAULT_PUBLISH_KEY),
+        &pod_name(package),
+        &pod_version(package)?,
+        "{file}",
+    );
+    let repo = crate::git::open()
+        .ok()
+        .and_then(|repo| crate::git::remote_url(&repo, "origin"))
+        .and_then(|url| Repo::from_url(&url));
+    let provider = match (provider, &repo) {
+        (Some(v), _) => v,
+        (None, Some(repo)) => Provider::infer(&repo.host),
+        (None, None) => Provider::GitHub,
+    };
+    let destination = match provider {
+        Provider::S3 => config
+            .bucket
+            .as_ref()
+            .map(|bucket| format!("s3://{}/{}", bucket, key)),
+        Provider::Http => config
+            .url
+            .as_ref()
+            .map(|url| format!("{}/{}", url.trim_end_matches('/'), key)),
+        Provider::Artifactory => match (&config.url, &config.repository) {
+            (Some(url), Some(repository)) => Some(format!(
+                "{}/{}/{}",
+                url.trim_end_matches('/'),
+                repository,
+                key
+            )),
+            _ => None,
+        },
+        Provider::Ssh => config
+            .dest
+            .as_ref()
+            .map(|dest| format!("{}/{}", dest.trim_end_matches('/'), key)),
+        Provider::Local => Some(format!("<--path>/{}", key)),
+        Provider::GitHub | Provider::Gitea => repo.map(|repo| {
+            format!(
+                "release {} of {}/{} on {}",
+                default_tag(pod_root, package).unwrap_or_default(),
+                repo.owner,
+                repo.name,
+                repo.host
+            )
+        }),
+    };
+    Ok((provider, destination))
+}
+
+fn info(args: InfoArgs) -> Result<(), Error> {
+    let has_subtree = std::fs::read_dir("./crate").is_ok();
+    let (metadata, package, targets) =
+        derive_manifest(pod_manifest(args.manifest_path.as_deref(), has_subtree))?;
+    let dist_dir = resolve_dist_dir(&metadata, &package, has_subtree);
+    let pod_root = resolve_pod_root(&dist_dir);
+    let dist_dir = std::fs::canonicalize(&dist_dir).unwrap_or(dist_dir);
+    let pod_root = std::fs::canonicalize(&pod_root).unwrap_or(pod_root);
+    let config = crate::meta::config(&package);
+    let build_target = BuildTarget::new(args.is_ios, args.is_macos, &config);
+    let min_versions = config.min_versions();
+    let podspec_path = find_podspec(&pod_root);
+    let podspec_version = podspec_path
+        .as_ref()
+        .and_then(|path| std::fs::read_to_string(path).ok())
+        .and_then(|spec| crate::podspec::spec_version(&spec).map(str::to_string));
+    let (provider, destination) = publish_destination(
+        args.provider.or(config.publish.provider),
+        &config.publish,
+        &pod_root,
+        &package,
+    )?;
+    let provider = format!("{:?}", provider).to_lowercase();
+
+    let platforms = [
+        Some("ios").filter(|_| build_target.is_ios()),
+        Some("macos").filter(|_| build_target.is_macos()),
+    ]
+    .iter()
+    .flatten()
+    .copied()
+    .collect::<Vec<_>>();
+    let triples = build_target.triples().collect::<Vec<_>>();
+    let modules = targets
+        .iter()
+        .map(|target| {
+            let (mod_name, ffi_mod_name) = framework_names(target);
+            (target.name.clone(), mod_name, ffi_mod_name)
+        })
+        .collect::<Vec<_>>();
+
+    if crate::message::is_json() {
+        crate::message::info(serde_json::json!({
+            "package": {
+                "name": package.name,
+                "version": package.version.to_string(),
+                "manifest_path": package.manifest_path,
+            },
+            "targets": modules.iter().map(|(name, module, ffi_module)| serde_json::json!({
+                "name": name,
+                "module": module,
+                "ffi_module": ffi_module,
+            })).collect::<Vec<_>>(),
+            "features": config.features,
+            "platforms": platforms,
+            "triples": triples,
+            "deployment_targets": {
+                "ios": min_versions.ios,
+                "macos": min_versions.macos,
+            },
+            "pod_root": pod_root,
+            "dist_dir": dist_dir,
+            "podspec": {
+                "name": pod_name(&package),
+                "version": pod_version(&package)?,
+                "path": podspec_path,
+                "file_version": podspec_version,
+            },
+            "publish": {
+                "provider": provider,
+                "destination": destination,
+            },
+        }));
+        return Ok(());
+    }
+
+    let none = || "(not configured)".to_string();
+    println!("package:            {} {}", package.name, package.version);
+    println!("manifest:           {}", package.manifest_path.display());
+    for (name, module, ffi_module) in &modules {
+        println!("target:             {} ({}, {})", name, module, ffi_module);
+    }
+    if !config.features.is_empty() {
+        println!("features:           {}", config.features.join(", "));
+    }
+    println!("platforms:          {}", platforms.join(", "));
+    println!("triples:            {}", triples.join(", "));
+    if build_target.is_ios() {
+        println!("iOS deployment:     {}", min_versions.ios);
+    }
+    if build_target.is_macos() {
+        println!("macOS deployment:   {}", min_versions.macos);
+    }
+    println!("pod root:           {}", pod_root.display());
+    println!("dist dir:           {}", dist_dir.display());
+    println!(
+        "podspec:            {} {} ({})",
+        pod_name(&package),
+        pod_version(&package)?,
+        match (&podspec_path, &podspec_version) {
+            (Some(path), Some(version)) => format!("{} at {}", path.display(), version),
+            (Some(path), None) => path.display().to_string(),
+            (None, _) => "not generated yet".into(),
+        }
+    );
+    println!("publish provider:   {}", provider);
+    println!("publish to:         {}", destination.unwrap_or_else(none));
+    Ok(())
+}
+
+fn doctor(args: DoctorArgs) -> Result<(), Error> {
+    let manifest_path = match args.manifest_path {
+        Some(v) => Some(v),
+        None if Path::new("./crate/Cargo.toml").exists() => Some("./crate/Cargo.toml".into()),
+        None => None,
+    };
+
+    let mut checks = crate::doctor::tools();
+    checks.push(crate::doctor::rust_targets());
+    checks.extend(crate::doctor::layout(manifest_path.as_deref()));
+
+    for check in &checks {
+        crate::message::check(check);
+    }
+
+    let failed = checks.iter().filter(|x| !x.ok).count();
+    if failed > 0 {
+        return Err(Error::Check(format!(
+            "{} of {} checks failed.",
+            failed,
+            checks.len()
+        )));
+    }
+    Ok(())
+}
+
+fn completions(args: CompletionsArgs) -> Result<(), Error> {
+    let shell = match args.shell {
+        Some(v) => v,
+        None => return Err(Error::Usage("Expected a shell: bash, zsh or fish.".into())),
+    };
+    let globals = crate::completions::parse_flags(Args::usage());
+    let subcommands =
+        crate::completions::parse_subcommands(Command::command_list().unwrap(), |name| {
+            Command::command_usage(name)
+        });
+    print!(
+        "{}",
+        crate::completions::script(shell, &globals, &subcommands)
+    );
+    Ok(())
 }
 
 fn print_help(args: &Args) {
@@ -1137,14 +3259,19 @@ fn print_help(args: &Args) {
     println!("{}", command.self_usage());
 }
 
-fn parse_args_or_exit(args: &[&str]) -> Args {
+/// Parses the command line, or fails with the code to exit with once the
+/// error or help has been printed.
+fn parse_args(args: &[&str]) -> Result<Args, i32> {
     let all_options_args = Args::parse_args(args, ParsingStyle::AllOptions);
     let free_args = Args::parse_args(args, ParsingStyle::StopAtFirstFree);
 
-    let args = all_options_args.or(free_args).unwrap_or_else(|e| {
-        eprintln!("cargo-pod: {}", e);
-        exit(2);
-    });
+    let args = match all_options_args.or(free_args) {
+        Ok(v) => v,
+        Err(e) => {
+            eprintln!("cargo-pod: {}", e);
+            return Err(2);
+        }
+    };
 
     // let args = Args::parse_args(args, ParsingStyle::StopAtFirstFree).unwrap_or_else(|e| {
     //     eprintln!("cargo-pod: {}", e);
@@ -1153,30 +3280,173 @@ fn parse_args_or_exit(args: &[&str]) -> Args {
 
     if args.help_requested() {
         print_help(&args);
-        exit(0);
+        return Err(0);
     }
 
-    args
+    Ok(args)
 }
 
-pub(crate) async fn run(args: Vec<String>) {
-    log::trace!("Args: {:?}", args);
+impl From<BuildArgs> for BuildOptions {
+    fn from(args: BuildArgs) -> BuildOptions {
+        BuildOptions {
+            manifest_path: args.manifest_path,
+            macos_only: args.is_macos,
+            ios_only: args.is_ios,
+            strict: args.strict,
+            debug_info: args.debug_info,
+            fix: args.fix,
+            bazel: args.bazel,
+            tuist: args.tuist,
+            timings: args.timings,
+            cargo_args: args.cargo_args,
+        }
+    }
+}
+
+fn podspec(args: PodspecArgs) -> Result<(), Error> {
+    let builder = PodspecBuilder::new()
+        .source_build(args.source_build)
+        .local(args.local);
+    let builder = match args.manifest_path {
+        Some(path) => builder.manifest_path(path),
+        None => builder,
+    };
+    if args.check {
+        return builder.check();
+    }
+    if !args.local {
+        print!("{}", builder.build()?);
+        return Ok(());
+    }
+
+    let path = builder.write()?;
+    if crate::message::is_json() {
+        return Ok(());
+    }
+    let name = path.file_stem().unwrap().to_string_lossy().to_string();
+    let pod_dir = crate::fs::canonicalize(path.parent().unwrap())?;
+    println!("Add the following to your Podfile:");
+    println!();
+    println!("  pod '{}', :path => '{}'", name, pod_dir.display());
+    Ok(())
+}
+
+impl From<PublishArgs> for Publisher {
+    fn from(args: PublishArgs) -> Publisher {
+        Publisher {
+            manifest_path: args.manifest_path,
+            token: args.token,
+            url: args.url,
+            provider: args.provider,
+            api_url: args.api_url,
+            tag: args.tag,
+            force: args.force,
+            draft: args.draft,
+            sign_tag: args.sign_tag,
+            verify_tag: args.verify_tag,
+            prerelease: args.prerelease,
+            channel: args.channel,
+            file: args.file,
+            verify_hash: args.verify_hash,
+            path: args.path,
+            dest: args.dest,
+            dry_run: args.dry_run,
+            commit: args.commit,
+        }
+    }
+}
 
-    let args = parse_args_or_exit(&args.iter().map(|x| &**x).collect::<Vec<_>>());
-    let command = match args.command {
+/// Runs `cargo pod` with `args`, the command line after `cargo pod`,
+/// returning the code to exit with.
+pub async fn run(args: Vec<String>) -> i32 {
+    let raw_args = args;
+    let args = match parse_args(&raw_args.iter().map(|x| &**x).collect::<Vec<_>>()) {
+        Ok(v) => v,
+        Err(code) => return code,
+    };
+    let log_file = args
+        .log_file
+        .as_ref()
+        .map(|path| std::fs::File::create(path).map_err(Error::io(path)));
+    let (log_file, log_file_error) = match log_file {
+        Some(Ok(v)) => (Some(v), None),
+        Some(Err(e)) => (None, Some(e)),
+        None => (None, None),
+    };
+    crate::message::init(
+        args.message_format.unwrap_or(MessageFormat::Human),
+        Verbosity::new(args.verbose, args.quiet),
+        log_file,
+    );
+    if let Some(e) = log_file_error {
+        crate::message::error(&e);
+        return e.exit_code();
+    }
+    crate::cmd::set_dry_run(args.dry_run);
+    log::trace!("Args: {:?}", raw_args);
+    let mut command = match args.command {
         Some(v) => v,
         None => {
             print_help(&args);
-            exit(0);
+            return 0;
         }
     };
 
-    match command {
+    // Make `--manifest-path` absolute before moving into the pod root.
+    if let Some(path) = command.manifest_path_mut().and_then(|x| x.as_mut()) {
+        if let Ok(v) = std::fs::canonicalize(&*path) {
+            *path = v;
+        }
+    }
+    if !matches!(command, Command::Init(_) | Command::Completions(_)) {
+        enter_pod_root(command.manifest_path_mut().and_then(|x| x.as_deref()));
+        enter_configured_pod_root(&mut command);
+    }
+
+    let mut code = 0;
+    let result = match command {
         Command::Init(args) => init(args),
-        Command::Build(args) => build(args),
-        Command::Publish(args) => publish(args).await,
+        Command::Build(args) => crate::build(args.into()),
+        Command::Publish(args) => {
+            let mut publisher = Publisher::from(args);
+            publisher.dry_run |= crate::cmd::is_dry_run();
+            publisher.publish().await
+        }
         Command::Bundle(args) => bundle(args),
         Command::Update(args) => update(args),
-        Command::Example(args) => example(args),
+        Command::AddCrate(args) => add_crate(args),
+        Command::PushSubtree(args) => push_subtree(args),
+        Command::ExtractCrate(args) => extract_crate(args),
+        Command::Podspec(args) => podspec(args),
+        Command::Spm(args) => spm(args),
+        Command::Version(args) => version(args),
+        Command::Lint(args) => lint(args),
+        Command::Verify(args) => verify(args).await,
+        Command::IntegrationTest(args) => integration_test(args),
+        Command::Test(args) => test(args),
+        Command::Example(args) => example(args).map(|x| code = x),
+        Command::Demo(args) => demo(args),
+        Command::Playground(args) => playground(args),
+        Command::Repl(args) => repl(args),
+        Command::Bench(args) => bench(args),
+        Command::Doctor(args) => doctor(args),
+        Command::Info(args) => info(args),
+        Command::Completions(args) => completions(args),
+    };
+    // Whatever was running when the signal came fails in its own way, but
+    // the exit should say it was interrupted.
+    let result = result.and_then(|_| crate::interrupt::check()).map_err(|e| {
+        if crate::interrupt::is_interrupted() {
+            Error::Interrupted
+        } else {
+            e
+        }
+    });
+    match result {
+        Ok(()) => code,
+        Err(e) => {
+            crate::message::error(&e);
+            e.exit_code()
+        }
     }
 }
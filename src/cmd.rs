@@ -1,20 +1,150 @@
 use std::{
     ffi::OsStr,
-    io,
+    io::{self, BufRead, BufReader, Read, Write},
     path::{Path, PathBuf},
-    process::Output,
+    process::{Child, Command, ExitStatus, Output, Stdio},
+    str::FromStr,
+    sync::atomic::{AtomicBool, Ordering},
 };
 
-pub fn lipo<S>(items: impl Iterator<Item = S>, output_path: &Path) -> io::Result<Output>
+use crate::error::{check_output, check_status, Error};
+use crate::message::Verbosity;
+
+static DRY_RUN: AtomicBool = AtomicBool::new(false);
+
+pub(crate) fn set_dry_run(dry_run: bool) {
+    DRY_RUN.store(dry_run, Ordering::Relaxed);
+}
+
+/// Whether `--dry-run` was passed, so that commands, writes and git changes
+/// are only logged.
+pub(crate) fn is_dry_run() -> bool {
+    DRY_RUN.load(Ordering::Relaxed)
+}
+
+/// In a dry run, logs `cmd` with its resolved arguments and returns true, so
+/// that the caller skips running it.
+pub(crate) fn skip(cmd: &Command) -> bool {
+    if !is_dry_run() {
+        return false;
+    }
+    log::info!("Would run {:?}", cmd);
+    true
+}
+
+/// Runs `tool` with its output shown as it runs, or with `--quiet`, captured
+/// and only shown if it fails.
+pub(crate) fn run(tool: &str, cmd: &mut Command) -> Result<(), Error> {
+    if skip(cmd) {
+        return Ok(());
+    }
+    crate::interrupt::check()?;
+    log::trace!("Calling: {:?}", cmd);
+    if crate::message::verbosity() == Verbosity::Quiet {
+        captured(tool, cmd)
+    } else if crate::message::has_log_file() {
+        tee(tool, cmd)
+    } else {
+        let status = spawn_tracked(cmd, |mut child| child.wait());
+        crate::interrupt::check()?;
+        check_status(tool, status)
+    }
+}
+
+/// Runs `tool` with its output captured, for tools that only print on
+/// failure.
+pub(crate) fn run_captured(tool: &str, cmd: &mut Command) -> Result<(), Error> {
+    if skip(cmd) {
+        return Ok(());
+    }
+    crate::interrupt::check()?;
+    log::trace!("Calling: {:?}", cmd);
+    captured(tool, cmd)
+}
+
+/// Spawns `cmd` and waits for it with `wait`, stopping it if the command is
+/// interrupted in the meantime.
+fn spawn_tracked<T>(cmd: &mut Command, wait: impl FnOnce(Child) -> io::Result<T>) -> io::Result<T> {
+    let child = cmd.spawn()?;
+    let pid = child.id();
+    crate::interrupt::track(pid);
+    let result = wait(child);
+    crate::interrupt::untrack(pid);
+    result
+}
+
+/// Like [`Command::status`], but stopped on interrupt, and failing once the
+/// command has been interrupted.
+pub(crate) fn status(cmd: &mut Command) -> io::Result<ExitStatus> {
+    crate::interrupt::check()?;
+    let status = spawn_tracked(cmd, |mut child| child.wait());
+    crate::interrupt::check()?;
+    status
+}
+
+/// Like [`Command::output`], but stopped on interrupt, and failing once the
+/// command has been interrupted.
+pub(crate) fn output(cmd: &mut Command) -> io::Result<Output> {
+    crate::interrupt::check()?;
+    cmd.stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    let output = spawn_tracked(cmd, Child::wait_with_output);
+    crate::interrupt::check()?;
+    output
+}
+
+fn captured(tool: &str, cmd: &mut Command) -> Result<(), Error> {
+    cmd.stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    let output = spawn_tracked(cmd, Child::wait_with_output);
+    crate::interrupt::check()?;
+    if let Ok(Output { stdout, stderr, .. }) = &output {
+        for stream in [stdout, stderr].iter() {
+            for line in String::from_utf8_lossy(stream).lines() {
+                crate::message::tool_output(tool, line);
+            }
+        }
+    }
+    check_output(tool, output).map(|_| ())
+}
+
+/// Runs `tool` with its output shown as it runs and copied into the
+/// `--log-file`.
+fn tee(tool: &str, cmd: &mut Command) -> Result<(), Error> {
+    cmd.stdout(Stdio::piped()).stderr(Stdio::piped());
+    let status = spawn_tracked(cmd, |mut child| {
+        let stdout = child.stdout.take().unwrap();
+        let stderr = child.stderr.take().unwrap();
+        let name = tool.to_string();
+        let stdout = std::thread::spawn(move || copy_lines(&name, stdout, std::io::stdout()));
+        copy_lines(tool, stderr, std::io::stderr());
+        let _ = stdout.join();
+        child.wait()
+    });
+    crate::interrupt::check()?;
+    check_status(tool, status)
+}
+
+fn copy_lines(tool: &str, from: impl Read, mut to: impl Write) {
+    for line in BufReader::new(from).split(b'\n').flatten() {
+        let _ = to.write_all(&line);
+        let _ = to.write_all(b"\n");
+        crate::message::tool_output(tool, &String::from_utf8_lossy(&line));
+    }
+}
+
+pub fn lipo<S>(items: impl Iterator<Item = S>, output_path: &Path) -> Result<(), Error>
 where
     S: AsRef<OsStr>,
 {
-    let mut cmd = std::process::Command::new("lipo");
+    let mut cmd = Command::new("lipo");
     cmd.arg("-create").arg("-output").arg(output_path);
     items.for_each(|item| {
         cmd.arg(item);
     });
-    cmd.output()
+    run_captured("lipo", &mut cmd)
 }
 
 pub struct Xcodebuild;
@@ -24,15 +154,15 @@ impl Xcodebuild {
         name: &str,
         paths: impl Iterator<Item = P>,
         output_path: &Path,
-    ) -> io::Result<Output> {
-        let mut cmd = std::process::Command::new("xcodebuild");
+    ) -> Result<(), Error> {
+        let mut cmd = Command::new("xcodebuild");
         cmd.arg("-create-xcframework")
             .arg("-output")
             .arg(output_path.join(format!("{name}.xcframework")));
         paths.for_each(|path| {
             cmd.arg("-framework").arg(path.as_ref());
         });
-        cmd.output()
+        run_captured("xcodebuild", &mut cmd)
     }
 }
 
@@ -45,92 +175,90 @@ impl Swiftc {
         module_name: &str,
         frameworks_path: &Path,
         swift_files: &[PathBuf],
-    ) -> String {
-        let sdk = current_sdk(triple);
-        let swift_triple = current_triple(triple, min_versions);
+    ) -> Result<String, Error> {
+        let sdk = current_sdk(triple)?;
+        let swift_triple = current_triple(triple, min_versions)?;
         let obj_name = format!("{}.o", module_name);
 
-        let mut output = std::process::Command::new("swiftc")
-            .args([
-                "-emit-library",
-                "-emit-object",
-                "-static",
-                "-swift-version",
-                "5",
-                "-enable-library-evolution",
-                "-sdk",
-                &sdk,
-                "-target",
-                &swift_triple,
-                "-module-name",
-                module_name,
-                "-o",
-                &obj_name,
-                "-F",
-            ])
-            .arg(frameworks_path)
-            .args(swift_files)
-            .spawn()
-            .unwrap();
-        output.wait().unwrap();
-
-        let mut output = std::process::Command::new("swiftc")
-            .args([
-                "-emit-module",
-                "-static",
-                "-swift-version",
-                "5",
-                "-sdk",
-                &sdk,
-                "-enable-library-evolution",
-                "-emit-parseable-module-interface",
-                "-target",
-                &swift_triple,
-                "-module-name",
-                module_name,
-                "-F",
-            ])
-            .arg(frameworks_path)
-            .args(swift_files)
-            .spawn()
-            .unwrap();
-        output.wait().unwrap();
-
-        obj_name
+        run(
+            "swiftc",
+            Command::new("swiftc")
+                .args([
+                    "-emit-library",
+                    "-emit-object",
+                    "-static",
+                    "-swift-version",
+                    "5",
+                    "-enable-library-evolution",
+                    "-sdk",
+                    &sdk,
+                    "-target",
+                    &swift_triple,
+                    "-module-name",
+                    module_name,
+                    "-o",
+                    &obj_name,
+                    "-F",
+                ])
+                .arg(frameworks_path)
+                .args(swift_files),
+        )?;
+
+        run(
+            "swiftc",
+            Command::new("swiftc")
+                .args([
+                    "-emit-module",
+                    "-static",
+                    "-swift-version",
+                    "5",
+                    "-sdk",
+                    &sdk,
+                    "-enable-library-evolution",
+                    "-emit-parseable-module-interface",
+                    "-target",
+                    &swift_triple,
+                    "-module-name",
+                    module_name,
+                    "-F",
+                ])
+                .arg(frameworks_path)
+                .args(swift_files),
+        )?;
+
+        Ok(obj_name)
     }
 }
 
 pub struct Ar;
 
 impl Ar {
-    pub fn insert(path: &Path, input: &str) {
-        let _output = std::process::Command::new("ar")
-            .arg("rs")
-            .arg(path)
-            .arg(input)
-            .output()
-            .unwrap();
-        let _output = std::process::Command::new("ranlib")
-            .arg(path)
-            .output()
-            .unwrap();
+    pub fn insert(path: &Path, input: &str) -> Result<(), Error> {
+        run_captured("ar", Command::new("ar").arg("rs").arg(path).arg(input))?;
+        run_captured("ranlib", Command::new("ranlib").arg(path))
     }
 }
 
-fn current_sdk(triple: &str) -> String {
-    let output = std::process::Command::new("xcrun")
-        .args(["--show-sdk-path", "--sdk"])
-        .arg(match triple {
-            "aarch64-apple-darwin" => "macosx",
-            "aarch64-apple-ios" => "iphoneos",
-            "aarch64-apple-ios-sim" => "iphonesimulator",
-            "x86_64-apple-darwin" => "macosx",
-            "x86_64-apple-ios" => "iphonesimulator",
-            _ => panic!("unsupported triple: {}", triple),
-        })
-        .output()
-        .unwrap();
-    String::from_utf8(output.stdout).unwrap().trim().to_string()
+pub(crate) fn current_sdk(triple: &str) -> Result<String, Error> {
+    let sdk = match triple {
+        "aarch64-apple-darwin" => "macosx",
+        "aarch64-apple-ios" => "iphoneos",
+        "aarch64-apple-ios-sim" => "iphonesimulator",
+        "x86_64-apple-darwin" => "macosx",
+        "x86_64-apple-ios" => "iphonesimulator",
+        _ => return Err(Error::Usage(format!("unsupported triple: {}", triple))),
+    };
+    let mut cmd = Command::new("xcrun");
+    cmd.args(["--show-sdk-path", "--sdk", sdk]);
+    // Stands in for the path in the commands a dry run logs, so that it
+    // works without Xcode.
+    if skip(&cmd) {
+        return Ok(format!("<{} sdk>", sdk));
+    }
+    let output = check_output("xcrun", output(&mut cmd))?;
+    String::from_utf8(output.stdout)
+        .map(|x| x.trim().to_string())
+        .map_err(|_| Error::tool("xcrun", "printed an SDK path that is not UTF-8"))
 }
 
 #[derive(Debug, Clone)]
@@ -148,13 +276,42 @@ impl Default for MinVersions {
     }
 }
 
-fn current_triple(triple: &str, min_versions: &MinVersions) -> String {
-    match triple {
+pub(crate) fn current_triple(triple: &str, min_versions: &MinVersions) -> Result<String, Error> {
+    Ok(match triple {
         "aarch64-apple-darwin" => format!("arm64-apple-macosx{}", &min_versions.macos),
         "aarch64-apple-ios" => format!("arm64-apple-ios{}", &min_versions.ios),
         "aarch64-apple-ios-sim" => format!("arm64-apple-ios{}-simulator", &min_versions.ios),
         "x86_64-apple-darwin" => format!("x86_64-apple-macosx{}", &min_versions.macos),
         "x86_64-apple-ios" => format!("x86_64-apple-ios{}-simulator", &min_versions.ios),
-        _ => panic!("unsupported triple: {}", triple),
+        _ => return Err(Error::Usage(format!("unsupported triple: {}", triple))),
+    })
+}
+
+/// A sanitizer the Rust library and Swift example can be built with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Sanitizer {
+    Address,
+    Thread,
+}
+
+impl Sanitizer {
+    /// The name both `rustc -Zsanitizer` and `swiftc -sanitize` take.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Sanitizer::Address => "address",
+            Sanitizer::Thread => "thread",
+        }
+    }
+}
+
+impl FromStr for Sanitizer {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "address" => Ok(Sanitizer::Address),
+            "thread" => Ok(Sanitizer::Thread),
+            other => Err(format!("unsupported sanitizer: {}", other)),
+        }
     }
 }
@@ -0,0 +1,356 @@
+//! Shell completion scripts for `cargo pod completions`, built from the usage
+//! text gumdrop derives for each command, so new flags are picked up without
+//! listing them again here.
+
+use std::{fmt::Write, str::FromStr};
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+}
+
+impl FromStr for Shell {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "bash" => Ok(Shell::Bash),
+            "zsh" => Ok(Shell::Zsh),
+            "fish" => Ok(Shell::Fish),
+            other => Err(format!("unsupported shell: {}", other)),
+        }
+    }
+}
+
+pub(crate) struct Flag {
+    short: Option<char>,
+    long: Option<String>,
+    takes_value: bool,
+    help: String,
+}
+
+pub(crate) struct Subcommand {
+    pub name: String,
+    pub help: String,
+    pub flags: Vec<Flag>,
+}
+
+/// Splits a `name  help` line from gumdrop's usage text.
+fn split_line(line: &str) -> (&str, &str) {
+    let line = line.trim();
+    match line.find("  ") {
+        Some(i) => (&line[..i], line[i..].trim()),
+        None => (line, ""),
+    }
+}
+
+/// The optional arguments listed in a gumdrop usage text, such as
+/// `-m, --manifest-path MANIFEST-PATH  help`. Help for a long flag is wrapped
+/// onto the next line.
+pub(crate) fn parse_flags(usage: &str) -> Vec<Flag> {
+    let mut flags: Vec<Flag> = vec![];
+    let lines = usage
+        .lines()
+        .skip_while(|line| *line != "Optional arguments:")
+        .skip(1)
+        .take_while(|line| !line.is_empty());
+    for line in lines {
+        if !line.trim_start().starts_with('-') {
+            if let Some(flag) = flags.last_mut() {
+                flag.help = line.trim().to_string();
+            }
+            continue;
+        }
+        let (spec, help) = split_line(line);
+        let mut flag = Flag {
+            short: None,
+            long: None,
+            takes_value: false,
+            help: help.to_string(),
+        };
+        for word in spec.split(|c| c == ',' || c == ' ') {
+            if let Some(long) = word.strip_prefix("--") {
+                flag.long = Some(long.to_string());
+            } else if let Some(short) = word.strip_prefix('-') {
+                flag.short = short.chars().next();
+            } else if !word.is_empty() {
+                flag.takes_value = true;
+            }
+        }
+        flags.push(flag);
+    }
+    flags
+}
+
+/// The subcommands listed in a gumdrop command list, with `usage` giving the
+/// usage text of each.
+pub(crate) fn parse_subcommands(
+    list: &str,
+    usage: impl Fn(&str) -> Option<&'static str>,
+) -> Vec<Subcommand> {
+    list.lines()
+        .map(|line| {
+            let (name, help) = split_line(line);
+            Subcommand {
+                name: name.to_string(),
+                help: help.to_string(),
+                flags: usage(name).map(parse_flags).unwrap_or_default(),
+            }
+        })
+        .collect()
+}
+
+pub(crate) fn script(shell: Shell, globals: &[Flag], subcommands: &[Subcommand]) -> String {
+    match shell {
+        Shell::Bash => bash(globals, subcommands),
+        Shell::Zsh => zsh(globals, subcommands),
+        Shell::Fish => fish(globals, subcommands),
+    }
+}
+
+fn flag_names<'a>(flags: impl IntoIterator<Item = &'a Flag>) -> Vec<String> {
+    flags
+        .into_iter()
+        .flat_map(|flag| {
+            flag.short
+                .map(|x| format!("-{}", x))
+                .into_iter()
+                .chain(flag.long.as_ref().map(|x| format!("--{}", x)))
+        })
+        .collect()
+}
+
+fn value_flag_names(flags: &[Flag]) -> Vec<String> {
+    flag_names(flags.iter().filter(|flag| flag.takes_value))
+}
+
+/// Completes `cargo pod` by wrapping the completion for `cargo`, which is
+/// still used for every other cargo command.
+fn bash(globals: &[Flag], subcommands: &[Subcommand]) -> String {
+    let names = subcommands.iter().map(|x| &*x.name).collect::<Vec<_>>();
+    let mut out = String::new();
+
+    writeln!(out, "_cargo_pod() {{").unwrap();
+    writeln!(out, "    local cur prev cmd i opts values").unwrap();
+    writeln!(out, "    cur=\"${{COMP_WORDS[COMP_CWORD]}}\"").unwrap();
+    writeln!(out, "    prev=\"${{COMP_WORDS[COMP_CWORD-1]}}\"").unwrap();
+    writeln!(out, "    cmd=\"\"").unwrap();
+    writeln!(out, "    for ((i = 2; i < COMP_CWORD; i++)); do").unwrap();
+    writeln!(out, "        case \"${{COMP_WORDS[i]}}\" in").unwrap();
+    writeln!(
+        out,
+        "            {}) cmd=\"${{COMP_WORDS[i]}}\"; break ;;",
+        names.join("|")
+    )
+    .unwrap();
+    writeln!(out, "        esac").unwrap();
+    writeln!(out, "    done").unwrap();
+    writeln!(out, "    case \"$cmd\" in").unwrap();
+    writeln!(out, "        \"\")").unwrap();
+    writeln!(
+        out,
+        "            opts=\"{} {}\"",
+        flag_names(globals).join(" "),
+        names.join(" ")
+    )
+    .unwrap();
+    writeln!(
+        out,
+        "            values=\"{}\" ;;",
+        value_flag_names(globals).join(" ")
+    )
+    .unwrap();
+    for subcommand in subcommands {
+        writeln!(out, "        {})", subcommand.name).unwrap();
+        writeln!(
+            out,
+            "            opts=\"{}\"",
+            flag_names(&subcommand.flags).join(" ")
+        )
+        .unwrap();
+        writeln!(
+            out,
+            "            values=\"{}\" ;;",
+            value_flag_names(&subcommand.flags).join(" ")
+        )
+        .unwrap();
+    }
+    writeln!(out, "    esac").unwrap();
+    writeln!(out, "    if [[ \" $values \" == *\" $prev \"* ]]; then").unwrap();
+    writeln!(out, "        COMPREPLY=($(compgen -f -- \"$cur\"))").unwrap();
+    writeln!(out, "    else").unwrap();
+    writeln!(
+        out,
+        "        COMPREPLY=($(compgen -W \"$opts\" -- \"$cur\"))"
+    )
+    .unwrap();
+    writeln!(out, "    fi").unwrap();
+    writeln!(out, "}}").unwrap();
+    writeln!(out).unwrap();
+    writeln!(out, "_cargo_pod_wrapper() {{").unwrap();
+    writeln!(
+        out,
+        "    if [[ \"${{COMP_WORDS[1]}}\" == pod && $COMP_CWORD -gt 1 ]]; then"
+    )
+    .unwrap();
+    writeln!(out, "        _cargo_pod").unwrap();
+    writeln!(out, "    elif declare -F _cargo >/dev/null; then").unwrap();
+    writeln!(out, "        _cargo \"$@\"").unwrap();
+    writeln!(out, "    fi").unwrap();
+    writeln!(out, "}}").unwrap();
+    writeln!(out).unwrap();
+    writeln!(
+        out,
+        "complete -F _cargo_pod_wrapper -o bashdefault -o default cargo"
+    )
+    .unwrap();
+    out
+}
+
+fn zsh_escape(text: &str) -> String {
+    text.replace('\'', "'\\''")
+        .replace('[', "\\[")
+        .replace(']', "\\]")
+        .replace(':', "\\:")
+}
+
+fn zsh_specs(flags: &[Flag]) -> Vec<String> {
+    flags
+        .iter()
+        .map(|flag| {
+            let help = zsh_escape(&flag.help);
+            let value = if flag.takes_value {
+                ":value:_files"
+            } else {
+                ""
+            };
+            match (flag.short, &flag.long) {
+                (Some(short), Some(long)) => {
+                    format!("'(-{short} --{long})'{{-{short},--{long}}}'[{help}]{value}'")
+                }
+                (Some(short), None) => format!("'-{}[{}]{}'", short, help, value),
+                (None, Some(long)) => format!("'--{}[{}]{}'", long, help, value),
+                (None, None) => unreachable!(),
+            }
+        })
+        .collect()
+}
+
+/// Defines `_cargo-pod`, which cargo's zsh completion calls for `cargo pod`.
+fn zsh(globals: &[Flag], subcommands: &[Subcommand]) -> String {
+    let mut out = String::new();
+
+    writeln!(out, "#compdef cargo-pod").unwrap();
+    writeln!(out).unwrap();
+    writeln!(out, "_cargo-pod() {{").unwrap();
+    writeln!(out, "    local curcontext=\"$curcontext\" state line").unwrap();
+    writeln!(out, "    _arguments -C \\").unwrap();
+    for spec in zsh_specs(globals) {
+        writeln!(out, "        {} \\", spec).unwrap();
+    }
+    writeln!(out, "        '1: :->command' \\").unwrap();
+    writeln!(out, "        '*:: :->args'").unwrap();
+    writeln!(out).unwrap();
+    writeln!(out, "    case $state in").unwrap();
+    writeln!(out, "        command)").unwrap();
+    writeln!(out, "            local -a commands").unwrap();
+    writeln!(out, "            commands=(").unwrap();
+    for subcommand in subcommands {
+        writeln!(
+            out,
+            "                '{}:{}'",
+            subcommand.name,
+            zsh_escape(&subcommand.help)
+        )
+        .unwrap();
+    }
+    writeln!(out, "            )").unwrap();
+    writeln!(out, "            _describe 'command' commands").unwrap();
+    writeln!(out, "            ;;").unwrap();
+    writeln!(out, "        args)").unwrap();
+    writeln!(out, "            case $words[1] in").unwrap();
+    for subcommand in subcommands {
+        writeln!(out, "                {})", subcommand.name).unwrap();
+        writeln!(out, "                    _arguments \\").unwrap();
+        for spec in zsh_specs(&subcommand.flags) {
+            writeln!(out, "                        {} \\", spec).unwrap();
+        }
+        writeln!(out, "                        '*:file:_files'").unwrap();
+        writeln!(out, "                    ;;").unwrap();
+    }
+    writeln!(out, "            esac").unwrap();
+    writeln!(out, "            ;;").unwrap();
+    writeln!(out, "    esac").unwrap();
+    writeln!(out, "}}").unwrap();
+    writeln!(out).unwrap();
+    writeln!(out, "_cargo-pod \"$@\"").unwrap();
+    out
+}
+
+fn fish_escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('\'', "\\'")
+}
+
+fn fish_flags(out: &mut String, condition: &str, flags: &[Flag]) {
+    for flag in flags {
+        write!(out, "complete -c cargo -n '{}'", condition).unwrap();
+        if let Some(short) = flag.short {
+            write!(out, " -s {}", short).unwrap();
+        }
+        if let Some(long) = &flag.long {
+            write!(out, " -l {}", long).unwrap();
+        }
+        if flag.takes_value {
+            out.push_str(" -r -F");
+        }
+        if !flag.help.is_empty() {
+            write!(out, " -d '{}'", fish_escape(&flag.help)).unwrap();
+        }
+        out.push('\n');
+    }
+}
+
+fn fish(globals: &[Flag], subcommands: &[Subcommand]) -> String {
+    let names = subcommands
+        .iter()
+        .map(|x| &*x.name)
+        .collect::<Vec<_>>()
+        .join(" ");
+    let top_level = format!(
+        "__fish_seen_subcommand_from pod; and not __fish_seen_subcommand_from {}",
+        names
+    );
+    let mut out = String::new();
+
+    writeln!(
+        out,
+        "complete -c cargo -n '__fish_use_subcommand' -f -a pod -d 'Build and publish CocoaPods'"
+    )
+    .unwrap();
+    fish_flags(&mut out, &top_level, globals);
+    for subcommand in subcommands {
+        write!(
+            out,
+            "complete -c cargo -n '{}' -f -a {}",
+            top_level, subcommand.name
+        )
+        .unwrap();
+        if !subcommand.help.is_empty() {
+            write!(out, " -d '{}'", fish_escape(&subcommand.help)).unwrap();
+        }
+        out.push('\n');
+    }
+    for subcommand in subcommands {
+        fish_flags(
+            &mut out,
+            &format!(
+                "__fish_seen_subcommand_from pod; and __fish_seen_subcommand_from {}",
+                subcommand.name
+            ),
+            &subcommand.flags,
+        );
+    }
+    out
+}
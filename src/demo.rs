@@ -0,0 +1,135 @@
+use std::{io, path::Path};
+
+/// Where `cargo pod demo` writes the app, next to the podspec.
+pub const DIR_NAME: &str = "demo";
+
+/// The signature of the function the plain template scaffolds, which the
+/// demo knows how to call.
+const TEMPLATE_ADD: &str = "public func add(_ a: Int32, _ b: Int32) -> Int32";
+
+const APP_SWIFT: &str = r#"import SwiftUI
+#if os(macOS)
+import AppKit
+#endif
+
+@main
+struct %APP_NAME%: App {
+    #if os(macOS)
+    init() {
+        // `cargo pod example` builds a bare executable rather than an app
+        // bundle, so bring the window to the front by hand.
+        NSApplication.shared.setActivationPolicy(.regular)
+        NSApplication.shared.activate(ignoringOtherApps: true)
+    }
+    #endif
+
+    var body: some Scene {
+        WindowGroup {
+            ContentView()
+        }
+    }
+}
+"#;
+
+const ADD_VIEW: &str = r#"import SwiftUI
+%IMPORTS%
+struct ContentView: View {
+    @State private var a: Int32 = 1
+    @State private var b: Int32 = 2
+
+    var body: some View {
+        VStack(spacing: 16) {
+            Text("%POD_NAME%")
+                .font(.title)
+            Stepper("a = \(a)", value: $a)
+            Stepper("b = \(b)", value: $b)
+            Text("add(a, b) = \(add(a, b))")
+                .font(.headline)
+        }
+        .padding()
+        .frame(minWidth: 320)
+    }
+}
+"#;
+
+const PLACEHOLDER_VIEW: &str = r#"import SwiftUI
+%IMPORTS%
+struct ContentView: View {
+    var body: some View {
+        VStack(spacing: 16) {
+            Text("%POD_NAME%")
+                .font(.title)
+            // Call into the pod here.%FUNCTIONS%
+            Text("Linked %MODULES%")
+        }
+        .padding()
+        .frame(minWidth: 320)
+    }
+}
+"#;
+
+/// The `public func` declarations in the Swift files under `bindings_dir`.
+pub fn public_functions(bindings_dir: &Path) -> Vec<String> {
+    let pattern = bindings_dir.join("**").join("*.swift");
+    glob::glob(&pattern.to_string_lossy())
+        .unwrap()
+        .filter_map(Result::ok)
+        .filter_map(|x| std::fs::read_to_string(x).ok())
+        .flat_map(|x| {
+            x.lines()
+                .map(str::trim)
+                .filter(|x| x.starts_with("public func "))
+                .map(|x| x.trim_end_matches('{').trim().to_string())
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+/// Writes a SwiftUI app importing `modules` into `dir`. The view calls the
+/// plain template's `add` if `functions` has it, and lists `functions`
+/// otherwise.
+pub fn write(
+    dir: &Path,
+    pod_name: &str,
+    modules: &[String],
+    functions: &[String],
+) -> io::Result<()> {
+    std::fs::create_dir_all(dir)?;
+
+    // Without --framework, the example compiles the bindings into the app
+    // itself, so there is no module to import.
+    let imports = modules
+        .iter()
+        .map(|x| format!("#if canImport({x})\nimport {x}\n#endif\n"))
+        .collect::<String>();
+    let app_name = format!("{}Demo", pod_name.replace('-', ""));
+
+    let view = if functions.iter().any(|x| x == TEMPLATE_ADD) {
+        ADD_VIEW.to_string()
+    } else {
+        let functions = functions
+            .iter()
+            .map(|x| format!("\n            // {}", x))
+            .collect::<String>();
+        PLACEHOLDER_VIEW
+            .replace("%FUNCTIONS%", &functions)
+            .replace("%MODULES%", &modules.join(", "))
+    };
+
+    for (name, contents) in [
+        (
+            format!("{}.swift", app_name),
+            APP_SWIFT.replace("%APP_NAME%", &app_name),
+        ),
+        (
+            "ContentView.swift".to_string(),
+            view.replace("%IMPORTS%", &imports)
+                .replace("%POD_NAME%", pod_name),
+        ),
+    ] {
+        let path = dir.join(name);
+        log::info!("Writing {}", path.display());
+        std::fs::write(path, contents)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,218 @@
+use std::{
+    io,
+    path::{Path, PathBuf},
+    process::{Command, ExitStatus},
+};
+
+/// What the example is built for when run on a device.
+pub const TRIPLE: &str = "aarch64-apple-ios";
+
+/// The oldest iOS current Xcode releases build apps for.
+pub const MIN_IOS_VERSION: &str = "14.0";
+
+/// Used when the provisioning profile allows any bundle identifier.
+const DEFAULT_BUNDLE_ID: &str = "dev.cargo-pod.example";
+
+const APP_NAME: &str = "Example";
+
+/// `devicectl` only installs apps, so the example binary is wrapped in a
+/// minimal bundle.
+const INFO_PLIST: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+	<key>CFBundleExecutable</key>
+	<string>%APP_NAME%</string>
+	<key>CFBundleIdentifier</key>
+	<string>%BUNDLE_ID%</string>
+	<key>CFBundleName</key>
+	<string>%APP_NAME%</string>
+	<key>CFBundlePackageType</key>
+	<string>APPL</string>
+	<key>CFBundleShortVersionString</key>
+	<string>1.0</string>
+	<key>CFBundleVersion</key>
+	<string>1</string>
+	<key>CFBundleSupportedPlatforms</key>
+	<array>
+		<string>iPhoneOS</string>
+	</array>
+	<key>LSRequiresIPhoneOS</key>
+	<true/>
+	<key>MinimumOSVersion</key>
+	<string>%IOS_VERSION%</string>
+	<key>UIDeviceFamily</key>
+	<array>
+		<integer>1</integer>
+		<integer>2</integer>
+	</array>
+</dict>
+</plist>
+"#;
+
+/// `get-task-allow` lets the example be launched with a console attached.
+const ENTITLEMENTS: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+	<key>application-identifier</key>
+	<string>%TEAM%.%BUNDLE_ID%</string>
+	<key>com.apple.developer.team-identifier</key>
+	<string>%TEAM%</string>
+	<key>get-task-allow</key>
+	<true/>
+</dict>
+</plist>
+"#;
+
+/// How the example app is signed for the device.
+#[derive(Debug)]
+pub struct Signing {
+    pub team: String,
+    pub profile: PathBuf,
+    pub identity: String,
+    pub bundle_id: String,
+}
+
+impl Signing {
+    /// Fills in the team and bundle identifier from the provisioning profile
+    /// where they are not given.
+    pub fn new(
+        profile: &Path,
+        team: Option<String>,
+        identity: Option<String>,
+        bundle_id: Option<String>,
+    ) -> Result<Signing, String> {
+        let decoded = tempfile::NamedTempFile::new().map_err(|e| e.to_string())?;
+        let status = crate::cmd::status(
+            Command::new("security")
+                .args(["cms", "-D", "-i"])
+                .arg(profile)
+                .arg("-o")
+                .arg(decoded.path()),
+        )
+        .map_err(|e| format!("Failed to run security: {}", e))?;
+        if !status.success() {
+            return Err(format!("Could not decode {}", profile.display()));
+        }
+
+        let team = match team.or_else(|| plist_value(decoded.path(), "TeamIdentifier.0")) {
+            Some(v) => v,
+            None => return Err("Could not find the team in the profile; pass --team".into()),
+        };
+        let bundle_id = bundle_id.unwrap_or_else(|| {
+            plist_value(decoded.path(), "Entitlements.application-identifier")
+                .and_then(|x| {
+                    x.strip_prefix(&format!("{}.", team))
+                        .filter(|x| !x.contains('*'))
+                        .map(str::to_string)
+                })
+                .unwrap_or_else(|| DEFAULT_BUNDLE_ID.into())
+        });
+
+        Ok(Signing {
+            team,
+            profile: profile.to_path_buf(),
+            identity: identity.unwrap_or_else(|| "Apple Development".into()),
+            bundle_id,
+        })
+    }
+}
+
+/// Reads a string at `key_path` from a plist with `plutil`.
+fn plist_value(path: &Path, key_path: &str) -> Option<String> {
+    let output = crate::cmd::output(
+        Command::new("plutil")
+            .args(["-extract", key_path, "raw", "-o", "-"])
+            .arg(path),
+    )
+    .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Wraps `executable` in a signed app bundle inside `dir`.
+pub fn bundle(dir: &Path, executable: &Path, signing: &Signing) -> io::Result<PathBuf> {
+    let app = dir.join(format!("{}.app", APP_NAME));
+    std::fs::create_dir_all(&app)?;
+    std::fs::copy(executable, app.join(APP_NAME))?;
+    std::fs::copy(&signing.profile, app.join("embedded.mobileprovision"))?;
+    std::fs::write(
+        app.join("Info.plist"),
+        INFO_PLIST
+            .replace("%APP_NAME%", APP_NAME)
+            .replace("%BUNDLE_ID%", &signing.bundle_id)
+            .replace("%IOS_VERSION%", MIN_IOS_VERSION),
+    )?;
+
+    let entitlements = dir.join("Example.entitlements");
+    std::fs::write(
+        &entitlements,
+        ENTITLEMENTS
+            .replace("%TEAM%", &signing.team)
+            .replace("%BUNDLE_ID%", &signing.bundle_id),
+    )?;
+
+    let status = crate::cmd::status(
+        Command::new("codesign")
+            .args(["--force", "--timestamp=none", "--sign", &signing.identity])
+            .arg("--entitlements")
+            .arg(&entitlements)
+            .arg(&app),
+    )?;
+    if !status.success() {
+        return Err(io::Error::new(
+            io::ErrorKind::Other,
+            format!("codesign failed with identity {:?}", signing.identity),
+        ));
+    }
+    Ok(app)
+}
+
+/// Whether Xcode's `devicectl` is available, falling back to `ios-deploy`.
+fn has_devicectl() -> bool {
+    crate::cmd::output(Command::new("xcrun").args(["--find", "devicectl"]))
+        .map(|x| x.status.success())
+        .unwrap_or(false)
+}
+
+/// Installs `app` on `device` and launches it with `args`, streaming its
+/// output.
+pub fn run(device: &str, app: &Path, bundle_id: &str, args: &[String]) -> io::Result<ExitStatus> {
+    if !has_devicectl() {
+        log::debug!("devicectl not found; using ios-deploy");
+        return crate::cmd::status(
+            Command::new("ios-deploy")
+                .args(["--id", device, "--noninteractive", "--bundle"])
+                .arg(app)
+                .arg("--args")
+                .arg(args.join(" ")),
+        );
+    }
+
+    let status = crate::cmd::status(
+        Command::new("xcrun")
+            .args(["devicectl", "device", "install", "app", "--device", device])
+            .arg(app),
+    )?;
+    if !status.success() {
+        return Ok(status);
+    }
+    crate::cmd::status(
+        Command::new("xcrun")
+            .args([
+                "devicectl",
+                "device",
+                "process",
+                "launch",
+                "--device",
+                device,
+                "--console",
+                "--terminate-existing",
+                bundle_id,
+            ])
+            .args(args),
+    )
+}
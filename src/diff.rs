@@ -0,0 +1,124 @@
+//! Unified diffs between a file on disk and the one `cargo pod` would
+//! generate, so that drift shows which lines changed.
+
+use std::fmt::Write;
+
+use console::Style;
+
+const CONTEXT: usize = 3;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Change {
+    Same,
+    Removed,
+    Added,
+}
+
+/// The lines of `old` and `new` in order, matched by their longest common
+/// subsequence.
+fn changes<'a>(old: &'a str, new: &'a str) -> Vec<(Change, &'a str)> {
+    let a = old.lines().collect::<Vec<_>>();
+    let b = new.lines().collect::<Vec<_>>();
+
+    // lcs[i][j] is the length of the longest common subsequence of a[i..]
+    // and b[j..].
+    let mut lcs = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lcs[i][j] = if a[i] == b[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut out = vec![];
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() || j < b.len() {
+        if i < a.len() && j < b.len() && a[i] == b[j] {
+            out.push((Change::Same, a[i]));
+            i += 1;
+            j += 1;
+        } else if j == b.len() || (i < a.len() && lcs[i + 1][j] >= lcs[i][j + 1]) {
+            out.push((Change::Removed, a[i]));
+            i += 1;
+        } else {
+            out.push((Change::Added, b[j]));
+            j += 1;
+        }
+    }
+    out
+}
+
+/// A unified diff from `old` to `new`, labelled with their paths, or `None`
+/// if they have the same lines. Colored if `color` is set.
+pub(crate) fn unified(
+    old_name: &str,
+    new_name: &str,
+    old: &str,
+    new: &str,
+    color: bool,
+) -> Option<String> {
+    let changes = changes(old, new);
+    let changed = changes
+        .iter()
+        .enumerate()
+        .filter(|(_, (change, _))| *change != Change::Same)
+        .map(|(i, _)| i)
+        .collect::<Vec<_>>();
+    if changed.is_empty() {
+        return None;
+    }
+
+    // Group the changes into hunks, merging those whose context overlaps.
+    let mut hunks: Vec<(usize, usize)> = vec![];
+    for i in changed {
+        let start = i.saturating_sub(CONTEXT);
+        let end = (i + CONTEXT + 1).min(changes.len());
+        match hunks.last_mut() {
+            Some(last) if start <= last.1 => last.1 = end,
+            _ => hunks.push((start, end)),
+        }
+    }
+
+    let header = Style::new().bold().force_styling(color);
+    let range = Style::new().cyan().force_styling(color);
+    let removed = Style::new().red().force_styling(color);
+    let added = Style::new().green().force_styling(color);
+
+    let mut out = String::new();
+    writeln!(out, "{}", header.apply_to(format!("--- {}", old_name))).unwrap();
+    writeln!(out, "{}", header.apply_to(format!("+++ {}", new_name))).unwrap();
+    for (start, end) in hunks {
+        let before = &changes[..start];
+        let hunk = &changes[start..end];
+        let count = |lines: &[(Change, &str)], change: Change| {
+            lines
+                .iter()
+                .filter(|(x, _)| *x == Change::Same || *x == change)
+                .count()
+        };
+        let (old_count, new_count) = (count(hunk, Change::Removed), count(hunk, Change::Added));
+        let old_start = count(before, Change::Removed) + (old_count > 0) as usize;
+        let new_start = count(before, Change::Added) + (new_count > 0) as usize;
+        writeln!(
+            out,
+            "{}",
+            range.apply_to(format!(
+                "@@ -{},{} +{},{} @@",
+                old_start, old_count, new_start, new_count
+            ))
+        )
+        .unwrap();
+        for (change, line) in hunk {
+            match change {
+                Change::Same => writeln!(out, " {}", line),
+                Change::Removed => writeln!(out, "{}", removed.apply_to(format!("-{}", line))),
+                Change::Added => writeln!(out, "{}", added.apply_to(format!("+{}", line))),
+            }
+            .unwrap();
+        }
+    }
+    Some(out)
+}
@@ -0,0 +1,206 @@
+//! Checks for `cargo pod doctor`: the Apple tools, Rust targets and project
+//! layout a build needs, each with a suggestion for fixing it.
+
+use std::{path::Path, process::Command};
+
+use cargo_metadata::MetadataCommand;
+
+use crate::{IOS_TRIPLES, MACOS_TRIPLES};
+
+pub(crate) struct Check {
+    pub name: String,
+    pub ok: bool,
+    pub detail: String,
+    pub fix: Option<String>,
+}
+
+impl Check {
+    fn pass(name: impl Into<String>, detail: impl Into<String>) -> Check {
+        Check {
+            name: name.into(),
+            ok: true,
+            detail: detail.into(),
+            fix: None,
+        }
+    }
+
+    fn fail(name: impl Into<String>, detail: impl Into<String>, fix: impl Into<String>) -> Check {
+        Check {
+            name: name.into(),
+            ok: false,
+            detail: detail.into(),
+            fix: Some(fix.into()),
+        }
+    }
+}
+
+const INSTALL_CLT: &str = "Install the command line tools with `xcode-select --install`.";
+const INSTALL_XCODE: &str =
+    "Install Xcode from the App Store, then select it with `sudo xcode-select -s /Applications/Xcode.app`.";
+
+/// Runs a tool, giving the first line of its output if it succeeded.
+fn run(program: &str, args: &[&str]) -> Result<String, String> {
+    let output = crate::cmd::output(Command::new(program).args(args))
+        .map_err(|e| format!("{}: {}", program, e))?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let text = match stdout.trim() {
+        "" => stderr.trim(),
+        v => v,
+    };
+    let first_line = text.lines().next().unwrap_or_default().to_string();
+    if output.status.success() {
+        Ok(first_line)
+    } else if first_line.is_empty() {
+        Err(format!("{} {}", program, output.status))
+    } else {
+        Err(first_line)
+    }
+}
+
+fn tool(name: &str, program: &str, args: &[&str], fix: &str) -> Check {
+    match run(program, args) {
+        Ok(v) => Check::pass(name, v),
+        Err(e) => Check::fail(name, e, fix),
+    }
+}
+
+pub(crate) fn tools() -> Vec<Check> {
+    let mut checks = vec![tool(
+        "Xcode command line tools",
+        "xcode-select",
+        &["-p"],
+        INSTALL_CLT,
+    )];
+    for sdk in ["macosx", "iphoneos", "iphonesimulator"] {
+        checks.push(tool(
+            &format!("{} SDK", sdk),
+            "xcrun",
+            &["--show-sdk-path", "--sdk", sdk],
+            INSTALL_XCODE,
+        ));
+    }
+    checks.push(tool(
+        "xcodebuild",
+        "xcodebuild",
+        &["-version"],
+        INSTALL_XCODE,
+    ));
+    checks.push(tool("swiftc", "swiftc", &["--version"], INSTALL_CLT));
+    checks.push(tool("lipo", "xcrun", &["--find", "lipo"], INSTALL_CLT));
+    checks.push(tool(
+        "CocoaPods",
+        "pod",
+        &["--version"],
+        "Install CocoaPods with `brew install cocoapods` or `sudo gem install cocoapods`.",
+    ));
+    checks.push(tool(
+        "git",
+        "git",
+        &["--version"],
+        "Install git, for example with the command line tools.",
+    ));
+    checks
+}
+
+pub(crate) fn rust_targets() -> Check {
+    let name = "Rust targets";
+    let mut cmd = Command::new("rustup");
+    cmd.args(["target", "list", "--installed"]);
+    let installed = match crate::cmd::output(&mut cmd) {
+        Ok(v) if v.status.success() => String::from_utf8_lossy(&v.stdout).into_owned(),
+        Ok(v) => {
+            return Check::fail(
+                name,
+                format!("rustup target list {}", v.status),
+                "Install Rust with rustup from https://rustup.rs.",
+            )
+        }
+        Err(e) => {
+            return Check::fail(
+                name,
+                format!("rustup: {}", e),
+                "Install Rust with rustup from https://rustup.rs.",
+            )
+        }
+    };
+    let missing = IOS_TRIPLES
+        .iter()
+        .chain(MACOS_TRIPLES)
+        .filter(|triple| !installed.lines().any(|x| x.trim() == **triple))
+        .copied()
+        .collect::<Vec<_>>();
+    if missing.is_empty() {
+        Check::pass(name, "all iOS and macOS targets installed")
+    } else {
+        Check::fail(
+            name,
+            format!("missing {}", missing.join(", ")),
+            format!("rustup target add {}", missing.join(" ")),
+        )
+    }
+}
+
+/// Checks that the crate builds a staticlib, and has the `headers/` and
+/// `bindings/` directories the frameworks are made from.
+pub(crate) fn layout(manifest_path: Option<&Path>) -> Vec<Check> {
+    let mut cmd = MetadataCommand::new();
+    if let Some(path) = manifest_path {
+        cmd.manifest_path(path);
+    }
+    let metadata = match cmd.exec() {
+        Ok(v) => v,
+        Err(e) => {
+            return vec![Check::fail(
+                "Cargo manifest",
+                e.to_string(),
+                "Run this in the pod's directory, or pass --manifest-path.",
+            )]
+        }
+    };
+
+    let packages = metadata
+        .packages
+        .iter()
+        .filter(|p| metadata.workspace_members.contains(&p.id))
+        .collect::<Vec<_>>();
+    let staticlibs = packages
+        .iter()
+        .filter(|p| {
+            p.targets
+                .iter()
+                .any(|t| t.kind.contains(&"staticlib".into()))
+        })
+        .collect::<Vec<_>>();
+
+    if staticlibs.is_empty() {
+        return vec![Check::fail(
+            "staticlib crate-type",
+            "no workspace package builds a staticlib",
+            "Add `crate-type = [\"staticlib\", \"lib\"]` to the [lib] section, or run `cargo pod build --fix`.",
+        )];
+    }
+
+    let mut checks = vec![];
+    for package in staticlibs {
+        let dir = package.manifest_path.parent().unwrap();
+        checks.push(Check::pass(
+            format!("{} staticlib crate-type", package.name),
+            package.manifest_path.display().to_string(),
+        ));
+        for name in ["headers", "bindings"] {
+            let path = dir.join(name);
+            let check_name = format!("{} {}/", package.name, name);
+            if path.is_dir() {
+                checks.push(Check::pass(check_name, path.display().to_string()));
+            } else {
+                checks.push(Check::fail(
+                    check_name,
+                    format!("{} is missing", path.display()),
+                    "Scaffold headers/ and bindings/ with `cargo pod init --template plain`, or cbindgen or uniffi.",
+                ));
+            }
+        }
+    }
+    checks
+}
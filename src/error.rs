@@ -0,0 +1,140 @@
+use std::{
+    fmt, io,
+    path::{Path, PathBuf},
+    process::{ExitStatus, Output},
+};
+
+/// Why a command failed. Each kind exits with its own code, so that scripts
+/// can tell a bad invocation from a failed build or upload.
+#[derive(Debug)]
+pub enum Error {
+    /// The command line, manifest or pod configuration is wrong.
+    Usage(String),
+    /// Reading or writing a file failed.
+    Io { path: PathBuf, source: io::Error },
+    /// An external tool such as cargo, swiftc or xcodebuild failed.
+    Tool { tool: String, message: String },
+    /// A git operation failed.
+    Git(String),
+    /// Talking to a release host failed.
+    Network(String),
+    /// A check, such as a lint, test or verification, did not pass.
+    Check(String),
+    /// The command was stopped by Ctrl-C or SIGTERM.
+    Interrupted,
+}
+
+impl Error {
+    /// The process exit code for this kind of failure. Argument parsing
+    /// errors also exit with 2.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Error::Usage(_) => 2,
+            Error::Io { .. } => 3,
+            Error::Tool { .. } => 4,
+            Error::Git(_) => 5,
+            Error::Network(_) => 6,
+            Error::Check(_) => 7,
+            Error::Interrupted => crate::interrupt::EXIT_CODE,
+        }
+    }
+
+    /// A short name for the kind of failure, as reported by
+    /// `--message-format json`.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Error::Usage(_) => "usage",
+            Error::Io { .. } => "io",
+            Error::Tool { .. } => "tool",
+            Error::Git(_) => "git",
+            Error::Network(_) => "network",
+            Error::Check(_) => "check",
+            Error::Interrupted => "interrupted",
+        }
+    }
+
+    /// Wraps an I/O error with the `path` it happened on, for `map_err`.
+    pub(crate) fn io(path: impl AsRef<Path>) -> impl FnOnce(io::Error) -> Error {
+        let path = path.as_ref().to_path_buf();
+        move |source| Error::Io { path, source }
+    }
+
+    pub(crate) fn tool(tool: &str, message: impl Into<String>) -> Error {
+        Error::Tool {
+            tool: tool.into(),
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Usage(v) | Error::Git(v) | Error::Network(v) | Error::Check(v) => f.write_str(v),
+            Error::Io { path, source } => write!(f, "{}: {}", path.display(), source),
+            Error::Tool { tool, message } => write!(f, "{} failed: {}", tool, message),
+            Error::Interrupted => f.write_str("interrupted"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+impl From<git2::Error> for Error {
+    fn from(e: git2::Error) -> Error {
+        Error::Git(e.message().to_string())
+    }
+}
+
+impl From<reqwest::Error> for Error {
+    fn from(e: reqwest::Error) -> Error {
+        Error::Network(e.to_string())
+    }
+}
+
+/// For the helpers that report plain I/O errors, such as the test and bench
+/// harness builds.
+impl From<Error> for io::Error {
+    fn from(e: Error) -> io::Error {
+        let kind = match e {
+            Error::Interrupted => io::ErrorKind::Interrupted,
+            _ => io::ErrorKind::Other,
+        };
+        io::Error::new(kind, e.to_string())
+    }
+}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// Turns the output of running `tool` into an error if it could not be
+/// started or did not succeed, keeping its stderr for the message.
+pub(crate) fn check_output(tool: &str, output: io::Result<Output>) -> Result<Output> {
+    let output = output.map_err(|e| Error::tool(tool, e.to_string()))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(Error::tool(
+            tool,
+            match stderr.trim() {
+                "" => output.status.to_string(),
+                v => v.to_string(),
+            },
+        ));
+    }
+    Ok(output)
+}
+
+/// Like [`check_output`], for tools whose output goes to the terminal.
+pub(crate) fn check_status(tool: &str, status: io::Result<ExitStatus>) -> Result<()> {
+    let status = status.map_err(|e| Error::tool(tool, e.to_string()))?;
+    if !status.success() {
+        return Err(Error::tool(tool, status.to_string()));
+    }
+    Ok(())
+}
@@ -0,0 +1,356 @@
+use std::{io, path::Path};
+
+/// Where `cargo pod init --example-app` writes the app, next to the podspec.
+pub const DIR_NAME: &str = "ExampleApp";
+
+/// Where `cargo pod integration-test` keeps its app, next to the podspec.
+pub const INTEGRATION_DIR_NAME: &str = "PodIntegration";
+
+pub const BUILD_SCRIPT_NAME: &str = "build.sh";
+
+/// The SwiftUI app lifecycle needs iOS 14.
+const MIN_IOS_VERSION: &str = "14.0";
+
+const APP_SWIFT: &str = r#"import SwiftUI
+%IMPORTS%
+@main
+struct %APP_NAME%: App {
+    var body: some Scene {
+        WindowGroup {
+            Text("Linked %POD_NAME%")
+                .padding()
+        }
+    }
+}
+"#;
+
+const PODFILE: &str = r#"platform :ios, '%IOS_VERSION%'
+
+target '%APP_NAME%' do
+  use_frameworks!
+  pod '%POD_NAME%', :path => '..'
+end
+"#;
+
+/// Installs the pod from the local podspec and compiles the app against it
+/// for the simulator, without code signing.
+const BUILD_SCRIPT: &str = r#"#!/bin/sh
+set -eu
+cd "$(dirname "$0")"
+
+pod install
+xcodebuild build \
+  -workspace %APP_NAME%.xcworkspace \
+  -scheme %APP_NAME% \
+  -configuration Debug \
+  -sdk iphonesimulator \
+  -destination 'generic/platform=iOS Simulator' \
+  CODE_SIGNING_ALLOWED=NO
+"#;
+
+/// Shared so that `xcodebuild -scheme` works without Xcode having opened the
+/// project first.
+const SCHEME: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<Scheme
+   LastUpgradeVersion = "1500"
+   version = "1.7">
+   <BuildAction
+      parallelizeBuildables = "YES"
+      buildImplicitDependencies = "YES">
+      <BuildActionEntries>
+         <BuildActionEntry
+            buildForTesting = "YES"
+            buildForRunning = "YES"
+            buildForProfiling = "YES"
+            buildForArchiving = "YES"
+            buildForAnalyzing = "YES">
+            <BuildableReference
+               BuildableIdentifier = "primary"
+               BlueprintIdentifier = "C0C0A0000000000000000008"
+               BuildableName = "%APP_NAME%.app"
+               BlueprintName = "%APP_NAME%"
+               ReferencedContainer = "container:%APP_NAME%.xcodeproj">
+            </BuildableReference>
+         </BuildActionEntry>
+      </BuildActionEntries>
+   </BuildAction>
+   <LaunchAction
+      buildConfiguration = "Debug"
+      launchStyle = "0"
+      useCustomWorkingDirectory = "NO"
+      debugDocumentVersioning = "YES">
+      <BuildableProductRunnable
+         runnableDebuggingMode = "0">
+         <BuildableReference
+            BuildableIdentifier = "primary"
+            BlueprintIdentifier = "C0C0A0000000000000000008"
+            BuildableName = "%APP_NAME%.app"
+            BlueprintName = "%APP_NAME%"
+            ReferencedContainer = "container:%APP_NAME%.xcodeproj">
+         </BuildableReference>
+      </BuildableProductRunnable>
+   </LaunchAction>
+</Scheme>
+"#;
+
+/// A single-target iOS app project. The object IDs only need to be unique
+/// within the file.
+const PROJECT_PBXPROJ: &str = r#"// !$*UTF8*$!
+{
+	archiveVersion = 1;
+	classes = {
+	};
+	objectVersion = 54;
+	objects = {
+
+/* Begin PBXBuildFile section */
+		C0C0A0000000000000000001 /* %APP_NAME%.swift in Sources */ = {isa = PBXBuildFile; fileRef = C0C0A0000000000000000002 /* %APP_NAME%.swift */; };
+/* End PBXBuildFile section */
+
+/* Begin PBXFileReference section */
+		C0C0A0000000000000000002 /* %APP_NAME%.swift */ = {isa = PBXFileReference; lastKnownFileType = sourcecode.swift; path = %APP_NAME%.swift; sourceTree = "<group>"; };
+		C0C0A0000000000000000003 /* %APP_NAME%.app */ = {isa = PBXFileReference; explicitFileType = wrapper.application; includeInIndex = 0; path = %APP_NAME%.app; sourceTree = BUILT_PRODUCTS_DIR; };
+/* End PBXFileReference section */
+
+/* Begin PBXFrameworksBuildPhase section */
+		C0C0A0000000000000000004 /* Frameworks */ = {
+			isa = PBXFrameworksBuildPhase;
+			buildActionMask = 2147483647;
+			files = (
+			);
+			runOnlyForDeploymentPostprocessing = 0;
+		};
+/* End PBXFrameworksBuildPhase section */
+
+/* Begin PBXGroup section */
+		C0C0A0000000000000000005 = {
+			isa = PBXGroup;
+			children = (
+				C0C0A0000000000000000006 /* %APP_NAME% */,
+				C0C0A0000000000000000007 /* Products */,
+			);
+			sourceTree = "<group>";
+		};
+		C0C0A0000000000000000006 /* %APP_NAME% */ = {
+			isa = PBXGroup;
+			children = (
+				C0C0A0000000000000000002 /* %APP_NAME%.swift */,
+			);
+			path = %APP_NAME%;
+			sourceTree = "<group>";
+		};
+		C0C0A0000000000000000007 /* Products */ = {
+			isa = PBXGroup;
+			children = (
+				C0C0A0000000000000000003 /* %APP_NAME%.app */,
+			);
+			name = Products;
+			sourceTree = "<group>";
+		};
+/* End PBXGroup section */
+
+/* Begin PBXNativeTarget section */
+		C0C0A0000000000000000008 /* %APP_NAME% */ = {
+			isa = PBXNativeTarget;
+			buildConfigurationList = C0C0A000000000000000000F /* Build configuration list for PBXNativeTarget "%APP_NAME%" */;
+			buildPhases = (
+				C0C0A0000000000000000009 /* Sources */,
+				C0C0A0000000000000000004 /* Frameworks */,
+				C0C0A000000000000000000A /* Resources */,
+			);
+			buildRules = (
+			);
+			dependencies = (
+			);
+			name = %APP_NAME%;
+			productName = %APP_NAME%;
+			productReference = C0C0A0000000000000000003 /* %APP_NAME%.app */;
+			productType = "com.apple.product-type.application";
+		};
+/* End PBXNativeTarget section */
+
+/* Begin PBXProject section */
+		C0C0A000000000000000000B /* Project object */ = {
+			isa = PBXProject;
+			attributes = {
+				BuildIndependentTargetsInParallel = 1;
+				LastUpgradeCheck = 1500;
+			};
+			buildConfigurationList = C0C0A000000000000000000E /* Build configuration list for PBXProject "%APP_NAME%" */;
+			compatibilityVersion = "Xcode 12.0";
+			developmentRegion = en;
+			hasScannedForEncodings = 0;
+			knownRegions = (
+				en,
+				Base,
+			);
+			mainGroup = C0C0A0000000000000000005;
+			productRefGroup = C0C0A0000000000000000007 /* Products */;
+			projectDirPath = "";
+			projectRoot = "";
+			targets = (
+				C0C0A0000000000000000008 /* %APP_NAME% */,
+			);
+		};
+/* End PBXProject section */
+
+/* Begin PBXResourcesBuildPhase section */
+		C0C0A000000000000000000A /* Resources */ = {
+			isa = PBXResourcesBuildPhase;
+			buildActionMask = 2147483647;
+			files = (
+			);
+			runOnlyForDeploymentPostprocessing = 0;
+		};
+/* End PBXResourcesBuildPhase section */
+
+/* Begin PBXSourcesBuildPhase section */
+		C0C0A0000000000000000009 /* Sources */ = {
+			isa = PBXSourcesBuildPhase;
+			buildActionMask = 2147483647;
+			files = (
+				C0C0A0000000000000000001 /* %APP_NAME%.swift in Sources */,
+			);
+			runOnlyForDeploymentPostprocessing = 0;
+		};
+/* End PBXSourcesBuildPhase section */
+
+/* Begin XCBuildConfiguration section */
+		C0C0A0000000000000000010 /* Debug */ = {
+			isa = XCBuildConfiguration;
+			buildSettings = {
+				IPHONEOS_DEPLOYMENT_TARGET = %IOS_VERSION%;
+				ONLY_ACTIVE_ARCH = YES;
+				SDKROOT = iphoneos;
+				SWIFT_OPTIMIZATION_LEVEL = "-Onone";
+				SWIFT_VERSION = 5.0;
+			};
+			name = Debug;
+		};
+		C0C0A0000000000000000011 /* Release */ = {
+			isa = XCBuildConfiguration;
+			buildSettings = {
+				IPHONEOS_DEPLOYMENT_TARGET = %IOS_VERSION%;
+				SDKROOT = iphoneos;
+				SWIFT_COMPILATION_MODE = wholemodule;
+				SWIFT_VERSION = 5.0;
+				VALIDATE_PRODUCT = YES;
+			};
+			name = Release;
+		};
+		C0C0A0000000000000000012 /* Debug */ = {
+			isa = XCBuildConfiguration;
+			buildSettings = {
+				CODE_SIGN_STYLE = Automatic;
+				GENERATE_INFOPLIST_FILE = YES;
+				INFOPLIST_KEY_UILaunchScreen_Generation = YES;
+				PRODUCT_BUNDLE_IDENTIFIER = "%BUNDLE_ID%";
+				PRODUCT_NAME = "$(TARGET_NAME)";
+				TARGETED_DEVICE_FAMILY = "1,2";
+			};
+			name = Debug;
+		};
+		C0C0A0000000000000000013 /* Release */ = {
+			isa = XCBuildConfiguration;
+			buildSettings = {
+				CODE_SIGN_STYLE = Automatic;
+				GENERATE_INFOPLIST_FILE = YES;
+				INFOPLIST_KEY_UILaunchScreen_Generation = YES;
+				PRODUCT_BUNDLE_IDENTIFIER = "%BUNDLE_ID%";
+				PRODUCT_NAME = "$(TARGET_NAME)";
+				TARGETED_DEVICE_FAMILY = "1,2";
+			};
+			name = Release;
+		};
+/* End XCBuildConfiguration section */
+
+/* Begin XCConfigurationList section */
+		C0C0A000000000000000000E /* Build configuration list for PBXProject "%APP_NAME%" */ = {
+			isa = XCConfigurationList;
+			buildConfigurations = (
+				C0C0A0000000000000000010 /* Debug */,
+				C0C0A0000000000000000011 /* Release */,
+			);
+			defaultConfigurationIsVisible = 0;
+			defaultConfigurationName = Release;
+		};
+		C0C0A000000000000000000F /* Build configuration list for PBXNativeTarget "%APP_NAME%" */ = {
+			isa = XCConfigurationList;
+			buildConfigurations = (
+				C0C0A0000000000000000012 /* Debug */,
+				C0C0A0000000000000000013 /* Release */,
+			);
+			defaultConfigurationIsVisible = 0;
+			defaultConfigurationName = Release;
+		};
+/* End XCConfigurationList section */
+	};
+	rootObject = C0C0A000000000000000000B /* Project object */;
+}
+"#;
+
+/// The later of two dotted versions.
+fn max_version<'a>(a: &'a str, b: &'a str) -> &'a str {
+    let parse = |v: &str| {
+        v.split('.')
+            .map(|x| x.parse::<u32>().unwrap_or(0))
+            .collect::<Vec<_>>()
+    };
+    if parse(a) >= parse(b) {
+        a
+    } else {
+        b
+    }
+}
+
+/// Writes an iOS app project named `app_name` under `dir` whose Podfile
+/// installs the pod from the podspec in `dir`'s parent, importing each of
+/// `modules`, along with a script that builds it.
+pub fn write(
+    dir: &Path,
+    app_name: &str,
+    pod_name: &str,
+    modules: &[String],
+    ios_version: &str,
+) -> io::Result<()> {
+    let ios_version = max_version(ios_version, MIN_IOS_VERSION);
+    let bundle_id = format!("org.cocoapods.{}", app_name);
+    let imports = modules
+        .iter()
+        .map(|x| format!("import {}\n", x))
+        .collect::<String>();
+    let fill = |template: &str| {
+        template
+            .replace("%APP_NAME%", app_name)
+            .replace("%POD_NAME%", pod_name)
+            .replace("%IOS_VERSION%", ios_version)
+            .replace("%BUNDLE_ID%", &bundle_id)
+            .replace("%IMPORTS%", &imports)
+    };
+
+    let project_dir = dir.join(format!("{}.xcodeproj", app_name));
+    let sources_dir = dir.join(app_name);
+    let schemes_dir = project_dir.join("xcshareddata").join("xcschemes");
+    std::fs::create_dir_all(&schemes_dir)?;
+    std::fs::create_dir_all(&sources_dir)?;
+
+    std::fs::write(dir.join("Podfile"), fill(PODFILE))?;
+    std::fs::write(project_dir.join("project.pbxproj"), fill(PROJECT_PBXPROJ))?;
+    std::fs::write(
+        schemes_dir.join(format!("{}.xcscheme", app_name)),
+        fill(SCHEME),
+    )?;
+    std::fs::write(
+        sources_dir.join(format!("{}.swift", app_name)),
+        fill(APP_SWIFT),
+    )?;
+
+    let script_path = dir.join(BUILD_SCRIPT_NAME);
+    std::fs::write(&script_path, fill(BUILD_SCRIPT))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(&script_path, std::fs::Permissions::from_mode(0o755))?;
+    }
+    Ok(())
+}
@@ -0,0 +1,86 @@
+//! The `std::fs` operations the build uses, failing with the path involved.
+//! In a dry run, changes are logged instead of made.
+
+use std::path::{Path, PathBuf};
+
+use crate::error::Error;
+
+pub fn create_dir_all(path: impl AsRef<Path>) -> Result<(), Error> {
+    let path = path.as_ref();
+    if crate::cmd::is_dry_run() {
+        log::debug!("Would create {}", path.display());
+        return Ok(());
+    }
+    std::fs::create_dir_all(path).map_err(Error::io(path))
+}
+
+pub fn write(path: impl AsRef<Path>, contents: impl AsRef<[u8]>) -> Result<(), Error> {
+    let path = path.as_ref();
+    if crate::cmd::is_dry_run() {
+        log::info!("Would write {}", path.display());
+        return Ok(());
+    }
+    std::fs::write(path, contents).map_err(Error::io(path))
+}
+
+pub fn copy(from: impl AsRef<Path>, to: impl AsRef<Path>) -> Result<u64, Error> {
+    let from = from.as_ref();
+    if crate::cmd::is_dry_run() {
+        log::debug!("Would copy {} to {}", from.display(), to.as_ref().display());
+        return Ok(0);
+    }
+    std::fs::copy(from, to).map_err(Error::io(from))
+}
+
+pub fn rename(from: impl AsRef<Path>, to: impl AsRef<Path>) -> Result<(), Error> {
+    let from = from.as_ref();
+    if crate::cmd::is_dry_run() {
+        log::debug!("Would move {} to {}", from.display(), to.as_ref().display());
+        return Ok(());
+    }
+    std::fs::rename(from, to).map_err(Error::io(from))
+}
+
+pub fn remove_file(path: impl AsRef<Path>) -> Result<(), Error> {
+    let path = path.as_ref();
+    if crate::cmd::is_dry_run() {
+        log::debug!("Would remove {}", path.display());
+        return Ok(());
+    }
+    std::fs::remove_file(path).map_err(Error::io(path))
+}
+
+pub fn remove_dir_all(path: impl AsRef<Path>) -> Result<(), Error> {
+    let path = path.as_ref();
+    if crate::cmd::is_dry_run() {
+        log::debug!("Would remove {}", path.display());
+        return Ok(());
+    }
+    std::fs::remove_dir_all(path).map_err(Error::io(path))
+}
+
+/// Copies the contents of the `from` directory into `to`.
+pub fn copy_dir(from: impl AsRef<Path>, to: impl AsRef<Path>) -> Result<(), Error> {
+    let from = from.as_ref();
+    if crate::cmd::is_dry_run() {
+        log::debug!("Would copy {} to {}", from.display(), to.as_ref().display());
+        return Ok(());
+    }
+    dircpy::copy_dir(from, to).map_err(Error::io(from))
+}
+
+pub fn read_to_string(path: impl AsRef<Path>) -> Result<String, Error> {
+    let path = path.as_ref();
+    std::fs::read_to_string(path).map_err(Error::io(path))
+}
+
+pub fn canonicalize(path: impl AsRef<Path>) -> Result<PathBuf, Error> {
+    let path = path.as_ref();
+    std::fs::canonicalize(path).map_err(Error::io(path))
+}
+
+/// A scratch directory, removed when dropped. Made even in a dry run, as
+/// it is never part of the output.
+pub fn tempdir() -> Result<tempfile::TempDir, Error> {
+    tempfile::tempdir().map_err(Error::io(std::env::temp_dir()))
+}
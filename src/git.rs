@@ -0,0 +1,249 @@
+//! Repository operations done in-process with libgit2. `git subtree`, signed
+//! tags and pushes still run the git binary, as libgit2 has no subtree
+//! support and does not use git's signing programs or credential helpers.
+
+use std::path::{Path, PathBuf};
+
+use git2::{build::TreeUpdateBuilder, FileMode, Oid, Repository, Signature, Status, StatusOptions};
+
+pub use git2::Error;
+
+/// Opens the repository containing the current directory.
+pub fn open() -> Result<Repository, Error> {
+    Repository::discover(".")
+}
+
+/// Opens the repository containing the current directory, creating one here
+/// if there is none. In a dry run, a missing repository is only logged and
+/// there is none to return.
+pub fn open_or_init() -> Result<Option<Repository>, Error> {
+    match open() {
+        Ok(repo) => Ok(Some(repo)),
+        Err(_) if would(format_args!("git init")) => Ok(None),
+        Err(_) => Repository::init(".").map(Some),
+    }
+}
+
+/// In a dry run, logs `change` and returns true, so the caller skips it.
+fn would(change: std::fmt::Arguments) -> bool {
+    if crate::cmd::is_dry_run() {
+        log::info!("Would {}", change);
+        return true;
+    }
+    false
+}
+
+fn display_paths<P: AsRef<Path>>(paths: &[P]) -> String {
+    paths
+        .iter()
+        .map(|x| x.as_ref().display().to_string())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Whether the current branch has any commits.
+pub fn has_commits(repo: &Repository) -> bool {
+    repo.head().is_ok()
+}
+
+/// Who commits are made by, following git's environment variables before
+/// its configuration.
+fn signature(repo: &Repository, kind: &str) -> Result<Signature<'static>, Error> {
+    let name = std::env::var(format!("GIT_{}_NAME", kind));
+    let email = std::env::var(format!("GIT_{}_EMAIL", kind));
+    match (name, email) {
+        (Ok(name), Ok(email)) => Signature::now(&name, &email),
+        _ => repo.signature(),
+    }
+}
+
+/// `path` relative to the repository's working directory.
+fn repo_path(repo: &Repository, path: &Path) -> Result<PathBuf, Error> {
+    let workdir = repo
+        .workdir()
+        .ok_or_else(|| Error::from_str("The repository has no working directory"))?;
+    let workdir = std::fs::canonicalize(workdir).map_err(|e| Error::from_str(&e.to_string()))?;
+    let path = std::fs::canonicalize(path)
+        .map_err(|e| Error::from_str(&format!("{}: {}", path.display(), e)))?;
+    path.strip_prefix(&workdir)
+        .map(Path::to_path_buf)
+        .map_err(|_| Error::from_str(&format!("{} is outside the repository", path.display())))
+}
+
+/// Stages `paths`, like `git add`.
+pub fn add<P: AsRef<Path>>(repo: &Repository, paths: &[P]) -> Result<(), Error> {
+    if would(format_args!("git add {}", display_paths(paths))) {
+        return Ok(());
+    }
+    let mut index = repo.index()?;
+    for path in paths {
+        let path = repo_path(repo, path.as_ref())?;
+        if repo.workdir().unwrap().join(&path).is_dir() {
+            index.add_all([&path], git2::IndexAddOption::DEFAULT, None)?;
+        } else {
+            index.add_path(&path)?;
+        }
+    }
+    index.write()
+}
+
+/// Commits `tree` on top of `HEAD`, if there is one.
+fn commit_tree(repo: &Repository, message: &str, tree: Oid) -> Result<Oid, Error> {
+    let tree = repo.find_tree(tree)?;
+    let author = signature(repo, "AUTHOR")?;
+    let committer = signature(repo, "COMMITTER")?;
+    let parent = match repo.head() {
+        Ok(v) => Some(v.peel_to_commit()?),
+        Err(_) => None,
+    };
+    let parents = parent.iter().collect::<Vec<_>>();
+    repo.commit(Some("HEAD"), &author, &committer, message, &tree, &parents)
+}
+
+/// Stages and commits only `paths`, like `git commit <paths>`, leaving
+/// anything else staged as it is.
+pub fn commit_paths<P: AsRef<Path>>(
+    repo: &Repository,
+    message: &str,
+    paths: &[P],
+) -> Result<Oid, Error> {
+    if would(format_args!(
+        "git commit -m {:?} {}",
+        message,
+        display_paths(paths)
+    )) {
+        return Ok(Oid::zero());
+    }
+    add(repo, paths)?;
+    let index = repo.index()?;
+    let baseline = match repo.head() {
+        Ok(v) => v.peel_to_tree()?,
+        Err(_) => repo.find_tree(repo.treebuilder(None)?.write()?)?,
+    };
+    let mut update = TreeUpdateBuilder::new();
+    for path in paths {
+        let path = repo_path(repo, path.as_ref())?;
+        let entry = index
+            .get_path(&path, 0)
+            .ok_or_else(|| Error::from_str(&format!("{} is not staged", path.display())))?;
+        let mode = if entry.mode == u32::from(FileMode::BlobExecutable) {
+            FileMode::BlobExecutable
+        } else {
+            FileMode::Blob
+        };
+        update.upsert(path.to_string_lossy().replace('\\', "/"), entry.id, mode);
+    }
+    let tree = update.create_updated(repo, &baseline)?;
+    commit_tree(repo, message, tree)
+}
+
+/// The URL of the remote `name`.
+pub fn remote_url(repo: &Repository, name: &str) -> Option<String> {
+    repo.find_remote(name).ok()?.url().map(str::to_string)
+}
+
+/// Adds the remote `name`, unless it already exists.
+pub fn add_remote(repo: &Repository, name: &str, url: &str) -> Result<(), Error> {
+    if repo.find_remote(name).is_err() && !would(format_args!("git remote add {} {}", name, url)) {
+        repo.remote(name, url)?;
+    }
+    Ok(())
+}
+
+pub fn tag_exists(repo: &Repository, tag: &str) -> bool {
+    repo.find_reference(&format!("refs/tags/{}", tag)).is_ok()
+}
+
+/// Creates a lightweight tag at `HEAD`.
+pub fn create_tag(repo: &Repository, tag: &str) -> Result<(), Error> {
+    if would(format_args!("git tag {}", tag)) {
+        return Ok(());
+    }
+    let head = repo.head()?.peel(git2::ObjectType::Commit)?;
+    repo.tag_lightweight(tag, &head, false)?;
+    Ok(())
+}
+
+/// Whether tracked files have uncommitted changes.
+pub fn has_local_changes(repo: &Repository) -> Result<bool, Error> {
+    let mut options = StatusOptions::new();
+    options.include_untracked(false).include_ignored(false);
+    let statuses = repo.statuses(Some(&mut options))?;
+    Ok(statuses
+        .iter()
+        .any(|x| !x.status().is_empty() && x.status() != Status::CURRENT))
+}
+
+/// Stashes uncommitted changes to tracked files.
+pub fn stash(repo: &mut Repository, message: &str) -> Result<(), Error> {
+    if would(format_args!("git stash push -m {:?}", message)) {
+        return Ok(());
+    }
+    let stasher = signature(repo, "COMMITTER")?;
+    repo.stash_save(&stasher, message, None)?;
+    Ok(())
+}
+
+/// Restores the most recent stash.
+pub fn unstash(repo: &mut Repository) -> Result<(), Error> {
+    if would(format_args!("git stash pop")) {
+        return Ok(());
+    }
+    repo.stash_pop(0, None)
+}
+
+/// The commit the last fetch brought in, from `FETCH_HEAD`.
+pub fn fetched_commit(repo: &Repository) -> Result<String, Error> {
+    let mut fetched = vec![];
+    repo.fetchhead_foreach(|_, _, oid, is_merge| {
+        fetched.push((*oid, is_merge));
+        true
+    })?;
+    let oid = fetched
+        .iter()
+        .find(|(_, is_merge)| *is_merge)
+        .or_else(|| fetched.first())
+        .map(|(oid, _)| *oid)
+        .ok_or_else(|| Error::from_str("Nothing has been fetched"))?;
+    Ok(repo
+        .find_object(oid, None)?
+        .peel_to_commit()?
+        .id()
+        .to_string())
+}
+
+/// Creates a repository at `dest` holding `branch` of the repository at
+/// `source`, checked out as `dest_branch`.
+pub fn clone_branch(
+    source: &Path,
+    branch: &str,
+    dest: &Path,
+    dest_branch: &str,
+) -> Result<(), Error> {
+    if would(format_args!(
+        "clone {} of {} into {} as {}",
+        branch,
+        source.display(),
+        dest.display(),
+        dest_branch
+    )) {
+        return Ok(());
+    }
+    let source = std::fs::canonicalize(source).map_err(|e| Error::from_str(&e.to_string()))?;
+    let mut options = git2::RepositoryInitOptions::new();
+    options.initial_head(dest_branch);
+    let repo = Repository::init_opts(dest, &options)?;
+    repo.remote_anonymous(&source.to_string_lossy())?.fetch(
+        &[format!("refs/heads/{}:refs/heads/{}", branch, dest_branch)],
+        None,
+        None,
+    )?;
+    repo.checkout_head(Some(git2::build::CheckoutBuilder::new().force()))
+}
+
+pub fn delete_branch(repo: &Repository, branch: &str) -> Result<(), Error> {
+    if would(format_args!("git branch -D {}", branch)) {
+        return Ok(());
+    }
+    repo.find_branch(branch, git2::BranchType::Local)?.delete()
+}
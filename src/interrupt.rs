@@ -0,0 +1,100 @@
+//! Ctrl-C and SIGTERM handling. The first signal stops the tools being run
+//! and any request or retry wait in progress, and makes the command fail
+//! where it is, so that it cleans up on its usual
+//! error path. The binary listens for the signals and calls [`interrupt`];
+//! a second signal exits at once.
+
+use std::{
+    future::Future,
+    process::Command,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
+};
+
+use futures_util::future::Either;
+use once_cell::sync::Lazy;
+use tokio::sync::Notify;
+
+use crate::error::Error;
+
+/// The exit code of a process stopped by SIGINT.
+pub const EXIT_CODE: i32 = 130;
+
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+static CHILDREN: Lazy<Mutex<Vec<u32>>> = Lazy::new(Default::default);
+static NOTIFY: Lazy<Notify> = Lazy::new(Notify::new);
+
+/// Stops the tools being run and any request or retry wait in progress.
+/// Returns `false` if the command had already been interrupted, in which case
+/// the caller should exit with [`EXIT_CODE`] without cleaning up.
+pub fn interrupt() -> bool {
+    if INTERRUPTED.swap(true, Ordering::SeqCst) {
+        log::error!("Interrupted again; exiting without cleaning up");
+        return false;
+    }
+    log::warn!("Interrupted; stopping and cleaning up (interrupt again to exit now)");
+    for pid in CHILDREN.lock().unwrap().iter() {
+        terminate_child(*pid);
+    }
+    NOTIFY.notify_waiters();
+    true
+}
+
+/// Whether Ctrl-C or SIGTERM has been received.
+pub(crate) fn is_interrupted() -> bool {
+    INTERRUPTED.load(Ordering::SeqCst)
+}
+
+/// Fails with [`Error::Interrupted`] once the command has been interrupted.
+pub(crate) fn check() -> Result<(), Error> {
+    if is_interrupted() {
+        return Err(Error::Interrupted);
+    }
+    Ok(())
+}
+
+/// Runs `future` to completion, unless the command is interrupted first, in
+/// which case it is dropped, cancelling the request or wait in progress.
+pub(crate) async fn until_interrupted<F: Future>(future: F) -> Result<F::Output, Error> {
+    // Created before checking, so that a signal in between still wakes it.
+    let interrupted = NOTIFY.notified();
+    check()?;
+    futures_util::pin_mut!(future, interrupted);
+    match futures_util::future::select(future, interrupted).await {
+        Either::Left((output, _)) => {
+            check()?;
+            Ok(output)
+        }
+        Either::Right(_) => Err(Error::Interrupted),
+    }
+}
+
+/// Records a running child process, to be stopped on interrupt.
+pub(crate) fn track(pid: u32) {
+    CHILDREN.lock().unwrap().push(pid);
+    // The signal may have arrived while the child was being spawned.
+    if is_interrupted() {
+        terminate_child(pid);
+    }
+}
+
+/// Forgets a child process once it has exited.
+pub(crate) fn untrack(pid: u32) {
+    CHILDREN.lock().unwrap().retain(|x| *x != pid);
+}
+
+fn terminate_child(pid: u32) {
+    log::debug!("Stopping process {}", pid);
+    #[cfg(unix)]
+    let _ = Command::new("kill")
+        .arg("-TERM")
+        .arg(pid.to_string())
+        .status();
+    #[cfg(not(unix))]
+    let _ = Command::new("taskkill")
+        .args(["/F", "/PID"])
+        .arg(pid.to_string())
+        .status();
+}
@@ -0,0 +1,74 @@
+//! The library behind `cargo pod`, for tools that drive it directly rather
+//! than running the command.
+//!
+//! Like the command, these functions work on the pod in the current
+//! directory and log their progress with the `log` crate. They fail with an
+//! [`Error`], whose [`Error::exit_code`] is what `cargo pod` would exit with.
+//!
+//! ```no_run
+//! # async fn release() -> Result<(), cargo_cocoapods::Error> {
+//! cargo_cocoapods::build(cargo_cocoapods::BuildOptions::default())?;
+//! let podspec = cargo_cocoapods::PodspecBuilder::new().build()?;
+//! cargo_cocoapods::Publisher::new().draft(true).publish().await?;
+//! # Ok(())
+//! # }
+//! ```
+
+mod acknowledgements;
+mod artifactbundle;
+mod bazel;
+mod bench;
+mod build;
+mod bundle;
+mod cargo;
+mod checksum;
+mod cli;
+mod cmd;
+mod completions;
+mod demo;
+mod device;
+mod diff;
+mod doctor;
+mod error;
+mod example_app;
+mod fs;
+mod git;
+mod interrupt;
+mod license;
+mod lldb;
+mod message;
+mod meta;
+mod package_swift;
+mod playground;
+mod pod;
+mod podspec;
+mod progress;
+mod publish;
+mod subtree;
+mod template;
+mod timings;
+mod tuist;
+mod version;
+mod workflow;
+mod xctest;
+
+pub(crate) static MACOS_TRIPLES: &[&str] = &[
+    "x86_64-apple-darwin",
+    "aarch64-apple-darwin",
+    // "x86_64-apple-ios-macabi",
+];
+
+pub(crate) static IOS_TRIPLES: &[&str] = &[
+    "x86_64-apple-ios",
+    "aarch64-apple-ios",
+    "aarch64-apple-ios-sim",
+];
+
+pub use build::{build, BuildOptions};
+pub use cli::run;
+pub use error::Error;
+pub use interrupt::{interrupt, EXIT_CODE};
+pub use message::MessageFormat;
+pub use podspec::PodspecBuilder;
+pub use publish::{Provider, Publisher};
+pub use timings::Format as TimingsFormat;
@@ -0,0 +1,81 @@
+use std::{
+    io,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+/// Where the standard library's sources are in the toolchain's debug info,
+/// and where rustup installed them, so lldb can step into them.
+fn rust_source_map(is_nightly: bool) -> Option<(String, PathBuf)> {
+    let rustc = |args: &[&str]| {
+        let mut cmd = Command::new("rustc");
+        if is_nightly {
+            cmd.arg("+nightly");
+        }
+        let output = crate::cmd::output(cmd.args(args)).ok()?;
+        if !output.status.success() {
+            return None;
+        }
+        Some(String::from_utf8_lossy(&output.stdout).to_string())
+    };
+
+    let version = rustc(&["-vV"])?;
+    let hash = version
+        .lines()
+        .find_map(|x| x.strip_prefix("commit-hash: "))?
+        .trim()
+        .to_string();
+    let sysroot = PathBuf::from(rustc(&["--print", "sysroot"])?.trim());
+    Some((format!("/rustc/{}", hash), sysroot))
+}
+
+/// The commands `rust-lldb` would run, plus the source maps, for
+/// `lldb -s`.
+fn commands(is_nightly: bool) -> String {
+    let mut commands = String::new();
+    let (remapped, sysroot) = match rust_source_map(is_nightly) {
+        Some(v) => v,
+        None => {
+            log::warn!("Could not find the Rust sources; stepping into std will show no source.");
+            return commands;
+        }
+    };
+
+    let src = sysroot.join("lib").join("rustlib").join("src").join("rust");
+    if src.is_dir() {
+        commands.push_str(&format!(
+            "settings append target.source-map {:?} {:?}\n",
+            remapped,
+            src.to_string_lossy()
+        ));
+    } else {
+        log::warn!("Run `rustup component add rust-src` to step into the Rust standard library.");
+    }
+
+    let etc = sysroot.join("lib").join("rustlib").join("etc");
+    let lookup = etc.join("lldb_lookup.py");
+    let formatters = etc.join("lldb_commands");
+    if lookup.is_file() && formatters.is_file() {
+        commands.push_str(&format!(
+            "command script import {:?}\ncommand source -s 0 {:?}\n",
+            lookup.to_string_lossy(),
+            formatters.to_string_lossy()
+        ));
+    }
+    commands
+}
+
+/// The command launching `executable` with `args` under lldb, with Rust's
+/// source maps and formatters loaded.
+pub fn command(executable: &Path, args: &[String], is_nightly: bool) -> io::Result<Command> {
+    let script = executable.with_extension("lldb");
+    std::fs::write(&script, commands(is_nightly))?;
+
+    let mut cmd = Command::new("lldb");
+    cmd.arg("-s")
+        .arg(&script)
+        .arg("--")
+        .arg(executable)
+        .args(args);
+    Ok(cmd)
+}
@@ -0,0 +1,254 @@
+use std::{
+    fs::File,
+    io::Write,
+    path::Path,
+    str::FromStr,
+    sync::{
+        atomic::{AtomicBool, AtomicU8, Ordering},
+        Mutex,
+    },
+    time::SystemTime,
+};
+
+use once_cell::sync::OnceCell;
+
+use crate::Error;
+
+/// How `cargo pod` reports what it is doing, as set by `--message-format`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MessageFormat {
+    /// Log lines on stderr.
+    Human,
+    /// One JSON object per line on stdout, for tools to parse.
+    Json,
+}
+
+impl FromStr for MessageFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "human" => Ok(MessageFormat::Human),
+            "json" => Ok(MessageFormat::Json),
+            other => Err(format!("unsupported message format: {}", other)),
+        }
+    }
+}
+
+/// How much `cargo pod` prints, from `-v`, `-vv` and `--quiet`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum Verbosity {
+    /// Only warnings and errors. Tool output is captured and shown only if
+    /// the tool fails.
+    Quiet,
+    Normal,
+    /// Debug logs.
+    Verbose,
+    /// Trace logs, and cargo's own verbose output.
+    VeryVerbose,
+}
+
+impl Verbosity {
+    pub fn new(verbose: u32, quiet: bool) -> Verbosity {
+        match (quiet, verbose) {
+            (true, _) => Verbosity::Quiet,
+            (false, 0) => Verbosity::Normal,
+            (false, 1) => Verbosity::Verbose,
+            (false, _) => Verbosity::VeryVerbose,
+        }
+    }
+
+    fn filter(self) -> &'static str {
+        match self {
+            Verbosity::Quiet => "cargo_cocoapods=warn",
+            Verbosity::Normal => "cargo_cocoapods=info",
+            Verbosity::Verbose => "cargo_cocoapods=debug",
+            Verbosity::VeryVerbose => "cargo_cocoapods=trace",
+        }
+    }
+}
+
+static JSON: AtomicBool = AtomicBool::new(false);
+static VERBOSITY: AtomicU8 = AtomicU8::new(Verbosity::Normal as u8);
+static LOG_FILE: OnceCell<Mutex<File>> = OnceCell::new();
+
+/// Selects the message format and installs the logger. `RUST_LOG`, if set,
+/// overrides the filter chosen by `verbosity`. With `log_file`, every log
+/// line and the output of the tools run are also written there.
+pub(crate) fn init(format: MessageFormat, verbosity: Verbosity, log_file: Option<File>) {
+    JSON.store(format == MessageFormat::Json, Ordering::Relaxed);
+    VERBOSITY.store(verbosity as u8, Ordering::Relaxed);
+    if let Some(file) = log_file {
+        let _ = LOG_FILE.set(Mutex::new(file));
+    }
+
+    let logger = Logger {
+        inner: env_logger::Builder::from_env(
+            env_logger::Env::default().default_filter_or(verbosity.filter()),
+        )
+        .build(),
+    };
+    log::set_max_level(if has_log_file() {
+        log::LevelFilter::Trace
+    } else {
+        logger.inner.filter()
+    });
+    let _ = log::set_boxed_logger(Box::new(logger));
+}
+
+pub(crate) fn is_json() -> bool {
+    JSON.load(Ordering::Relaxed)
+}
+
+pub(crate) fn verbosity() -> Verbosity {
+    match VERBOSITY.load(Ordering::Relaxed) {
+        0 => Verbosity::Quiet,
+        1 => Verbosity::Normal,
+        2 => Verbosity::Verbose,
+        _ => Verbosity::VeryVerbose,
+    }
+}
+
+/// Whether `--log-file` was given, so tool output should be recorded.
+pub(crate) fn has_log_file() -> bool {
+    LOG_FILE.get().is_some()
+}
+
+fn write_log_file(level: &str, target: &str, message: &str) {
+    if let Some(file) = LOG_FILE.get() {
+        let timestamp = humantime::format_rfc3339_millis(SystemTime::now());
+        let mut file = file.lock().unwrap();
+        for line in message.split('\n') {
+            let _ = writeln!(file, "[{} {:<5} {}] {}", timestamp, level, target, line);
+        }
+    }
+}
+
+/// Records a line of a tool's output in the `--log-file`.
+pub(crate) fn tool_output(tool: &str, line: &str) {
+    write_log_file("OUT", tool, line);
+}
+
+fn is_own(target: &str) -> bool {
+    target.starts_with("cargo_cocoapods")
+}
+
+fn emit(message: serde_json::Value) {
+    let stdout = std::io::stdout();
+    let mut stdout = stdout.lock();
+    let _ = writeln!(stdout, "{}", message);
+}
+
+/// Logs through `env_logger`, or as `{"reason":"message"}` lines once the
+/// JSON message format is selected.
+struct Logger {
+    inner: env_logger::Logger,
+}
+
+impl log::Log for Logger {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        (has_log_file() && is_own(metadata.target())) || self.inner.enabled(metadata)
+    }
+
+    fn log(&self, record: &log::Record) {
+        if is_own(record.target()) {
+            write_log_file(
+                record.level().as_str(),
+                record.target(),
+                &record.args().to_string(),
+            );
+        }
+        if !is_json() {
+            return self.inner.log(record);
+        }
+        if self.inner.matches(record) {
+            emit(serde_json::json!({
+                "reason": "message",
+                "level": record.level().as_str().to_lowercase(),
+                "target": record.target(),
+                "message": record.args().to_string(),
+            }));
+        }
+    }
+
+    fn flush(&self) {
+        self.inner.flush();
+        if let Some(file) = LOG_FILE.get() {
+            let _ = file.lock().unwrap().flush();
+        }
+    }
+}
+
+/// Reports a file or directory the command produced, such as an xcframework
+/// or a bundle. Only emitted in the JSON format; the human format logs its
+/// own progress.
+pub(crate) fn artifact(kind: &str, path: &Path) {
+    if !is_json() {
+        return;
+    }
+    let path = std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    emit(serde_json::json!({
+        "reason": "artifact",
+        "kind": kind,
+        "path": path,
+    }));
+}
+
+/// Reports where a command published something.
+pub(crate) fn published(url: &str) {
+    if !is_json() {
+        return;
+    }
+    emit(serde_json::json!({
+        "reason": "published",
+        "url": url,
+    }));
+}
+
+/// Reports the error a command failed with, before exiting.
+pub(crate) fn error(error: &Error) {
+    if !is_json() {
+        log::error!("{}", error);
+        return;
+    }
+    write_log_file("ERROR", module_path!(), &error.to_string());
+    emit(serde_json::json!({
+        "reason": "error",
+        "kind": error.kind(),
+        "exit_code": error.exit_code(),
+        "message": error.to_string(),
+    }));
+}
+
+/// Reports the configuration `cargo pod info` resolved.
+pub(crate) fn info(fields: serde_json::Value) {
+    let mut message = serde_json::json!({ "reason": "info" });
+    if let (Some(message), serde_json::Value::Object(fields)) = (message.as_object_mut(), fields) {
+        message.extend(fields);
+    }
+    emit(message);
+}
+
+/// Prints the result of a `cargo pod doctor` check, with how to fix it if it
+/// failed.
+pub(crate) fn check(check: &crate::doctor::Check) {
+    if is_json() {
+        emit(serde_json::json!({
+            "reason": "check",
+            "name": check.name,
+            "ok": check.ok,
+            "detail": check.detail,
+            "fix": check.fix,
+        }));
+        return;
+    }
+    println!(
+        "{:<4}  {}: {}",
+        if check.ok { "ok" } else { "FAIL" },
+        check.name,
+        check.detail
+    );
+    if let Some(fix) = &check.fix {
+        println!("      {}", fix);
+    }
+}
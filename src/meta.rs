@@ -1,16 +1,131 @@
+use std::collections::BTreeMap;
+
 use cargo_metadata::Package;
-use serde::Deserialize;
+use serde::{de::DeserializeOwned, Deserialize};
+
+use crate::{
+    bundle::{ArchiveFormat, Compression},
+    cmd::MinVersions,
+    publish::Provider,
+    Error,
+};
 
 #[derive(Debug, Deserialize, Default)]
 struct Metadata {
     pod: Option<Config>,
 }
 
+/// `[package.metadata.pod]`. Unknown keys are rejected by [`validate`], so
+/// that a misspelt setting is not silently ignored.
 #[derive(Debug, Deserialize, Default)]
+#[serde(deny_unknown_fields)]
 pub struct Config {
     pub name: Option<String>,
+    pub description: Option<String>,
     #[serde(default = "Vec::new")]
     pub features: Vec<String>,
+    /// The platforms `build` builds for when neither `--ios` nor `--macos`
+    /// is passed. Defaults to both.
+    #[serde(default = "Vec::new")]
+    pub platforms: Vec<Platform>,
+    pub ios_deployment_target: Option<String>,
+    pub macos_deployment_target: Option<String>,
+    #[serde(default)]
+    pub xcconfig: BTreeMap<String, String>,
+    #[serde(default = "Vec::new")]
+    pub preserve_paths: Vec<String>,
+    #[serde(default = "Vec::new")]
+    pub exclude_files: Vec<String>,
+    #[serde(default)]
+    pub version_map: BTreeMap<String, String>,
+    #[serde(default)]
+    pub dev_timestamp: bool,
+    #[serde(default)]
+    pub build_from_source: bool,
+    #[serde(default)]
+    pub bundle: BundleConfig,
+    #[serde(default)]
+    pub acknowledgements: bool,
+    #[serde(default)]
+    pub acknowledgements_resource: bool,
+    pub max_bundle_size: Option<String>,
+    /// Directory holding the podspec, `src` and `dist`, relative to the
+    /// crate. Defaults to the workspace root.
+    pub pod_root: Option<String>,
+    #[serde(default)]
+    pub publish: PublishConfig,
+    #[serde(default)]
+    pub hooks: HooksConfig,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Platform {
+    Ios,
+    Macos,
+}
+
+/// Shell commands run around `build` and `publish`, from
+/// `[package.metadata.pod.hooks]`.
+#[derive(Debug, Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+pub struct HooksConfig {
+    pub pre_build: Option<String>,
+    pub post_build: Option<String>,
+    pub pre_publish: Option<String>,
+    pub post_publish: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+pub struct PublishConfig {
+    pub provider: Option<Provider>,
+    pub bucket: Option<String>,
+    /// Object key template; supports `{name}`, `{version}` and `{file}`.
+    pub key: Option<String>,
+    pub region: Option<String>,
+    pub endpoint: Option<String>,
+    /// Public or CDN URL template for uploaded objects; supports `{key}`.
+    pub public_url: Option<String>,
+    /// Base URL that `http` uploads are `PUT` under, followed by the key,
+    /// or the Artifactory root for `artifactory`.
+    pub url: Option<String>,
+    /// Basic auth user for `http` uploads; the password is read from
+    /// `CARGO_POD_PUBLISH_PASSWORD`.
+    pub username: Option<String>,
+    /// Artifactory repository key.
+    pub repository: Option<String>,
+    /// `user@host:/path/` that `ssh` copies to.
+    pub dest: Option<String>,
+    /// Extra properties set on deployed Artifactory artifacts.
+    #[serde(default)]
+    pub properties: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+pub struct BundleConfig {
+    pub output: Option<String>,
+    pub format: Option<ArchiveFormat>,
+    pub compression: Option<Compression>,
+    pub compression_level: Option<u32>,
+    #[serde(default = "Vec::new")]
+    pub include: Vec<String>,
+    #[serde(default = "Vec::new")]
+    pub exclude: Vec<String>,
+}
+
+impl Config {
+    pub fn min_versions(&self) -> MinVersions {
+        let defaults = MinVersions::default();
+        MinVersions {
+            ios: self.ios_deployment_target.clone().unwrap_or(defaults.ios),
+            macos: self
+                .macos_deployment_target
+                .clone()
+                .unwrap_or(defaults.macos),
+        }
+    }
 }
 
 pub fn config(package: &Package) -> Config {
@@ -22,3 +137,79 @@ pub fn config(package: &Package) -> Config {
     };
     meta.pod.unwrap_or_default()
 }
+
+fn parse<T: DeserializeOwned + Default>(value: Option<&serde_json::Value>) -> Result<T, String> {
+    match value {
+        Some(v) => serde_json::from_value(v.clone()).map_err(|e| e.to_string()),
+        None => Ok(Default::default()),
+    }
+}
+
+/// Whether `version` looks like a deployment target, such as `13.0`.
+fn is_deployment_target(version: &str) -> bool {
+    let parts = version.split('.').collect::<Vec<_>>();
+    parts.len() <= 3
+        && parts
+            .iter()
+            .all(|x| !x.is_empty() && x.chars().all(|c| c.is_ascii_digit()))
+}
+
+/// Checks `[package.metadata.pod]` in the manifest of `package`, naming the
+/// table and key at fault.
+pub fn validate(package: &Package) -> Result<(), Error> {
+    let value = match package.metadata.get("pod") {
+        Some(v) => v,
+        None => return Ok(()),
+    };
+    let invalid = |table: &str, message: String| {
+        Error::Usage(format!(
+            "Invalid [package.metadata.pod{}] in {}: {}",
+            table,
+            package.manifest_path.display(),
+            message
+        ))
+    };
+
+    // The nested tables first, as serde does not say which table a field is
+    // in.
+    parse::<BundleConfig>(value.get("bundle")).map_err(|e| invalid(".bundle", e))?;
+    parse::<PublishConfig>(value.get("publish")).map_err(|e| invalid(".publish", e))?;
+    parse::<HooksConfig>(value.get("hooks")).map_err(|e| invalid(".hooks", e))?;
+    let config = parse::<Config>(Some(value)).map_err(|e| invalid("", e))?;
+
+    for (key, version) in [
+        ("ios_deployment_target", &config.ios_deployment_target),
+        ("macos_deployment_target", &config.macos_deployment_target),
+    ] {
+        if let Some(version) = version {
+            if !is_deployment_target(version) {
+                return Err(invalid(
+                    "",
+                    format!(
+                        "{} should be a version such as \"13.0\", not {:?}",
+                        key, version
+                    ),
+                ));
+            }
+        }
+    }
+    if let Some(size) = &config.max_bundle_size {
+        crate::bundle::parse_size(size)
+            .map_err(|e| invalid("", format!("max_bundle_size: {}", e)))?;
+    }
+    if let Some(level) = config.bundle.compression_level {
+        let compression = config.bundle.compression.unwrap_or_default();
+        if level > compression.max_level() {
+            return Err(invalid(
+                ".bundle",
+                format!(
+                    "compression_level should be at most {} for {:?}, not {}",
+                    compression.max_level(),
+                    format!("{:?}", compression).to_lowercase(),
+                    level
+                ),
+            ));
+        }
+    }
+    Ok(())
+}
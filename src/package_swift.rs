@@ -0,0 +1,88 @@
+use regex::Regex;
+
+pub const FILE_NAME: &str = "Package.swift";
+
+/// Stands in for the checksum of an archive that has not been published yet.
+pub const PLACEHOLDER_CHECKSUM: &str =
+    "0000000000000000000000000000000000000000000000000000000000000000";
+
+/// The extension `bundle` gives zipped xcframeworks.
+pub const ZIP_SUFFIX: &str = ".xcframework.zip";
+
+pub struct BinaryTarget {
+    pub name: String,
+    pub url: String,
+    pub checksum: String,
+}
+
+/// A Swift package manifest vending the pod's xcframeworks as binary targets.
+pub struct PackageSwift {
+    pub name: String,
+    pub ios: String,
+    pub macos: String,
+    pub targets: Vec<BinaryTarget>,
+}
+
+impl std::fmt::Display for PackageSwift {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("// swift-tools-version:5.3\n")?;
+        f.write_str("import PackageDescription\n\n")?;
+        f.write_str("let package = Package(\n")?;
+        f.write_fmt(format_args!("    name: \"{}\",\n", self.name))?;
+        f.write_str("    platforms: [\n")?;
+        f.write_fmt(format_args!("        .iOS(\"{}\"),\n", self.ios))?;
+        f.write_fmt(format_args!("        .macOS(\"{}\"),\n", self.macos))?;
+        f.write_str("    ],\n")?;
+        f.write_str("    products: [\n")?;
+        f.write_fmt(format_args!(
+            "        .library(name: \"{}\", targets: [{}]),\n",
+            self.name,
+            self.targets
+                .iter()
+                .map(|x| format!("\"{}\"", x.name))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ))?;
+        f.write_str("    ],\n")?;
+        f.write_str("    targets: [\n")?;
+        for target in &self.targets {
+            f.write_str("        .binaryTarget(\n")?;
+            f.write_fmt(format_args!("            name: \"{}\",\n", target.name))?;
+            f.write_fmt(format_args!("            url: \"{}\",\n", target.url))?;
+            f.write_fmt(format_args!(
+                "            checksum: \"{}\"\n",
+                target.checksum
+            ))?;
+            f.write_str("        ),\n")?;
+        }
+        f.write_str("    ]\n")?;
+        f.write_str(")\n")
+    }
+}
+
+/// The URL of `file` when published next to the file at `url`.
+pub fn sibling_url(url: &str, file: &str) -> String {
+    match url.rsplit_once('/') {
+        Some((base, _)) => format!("{}/{}", base, file),
+        None => file.to_string(),
+    }
+}
+
+/// Updates the checksum, and optionally the URL, of the binary target `name`
+/// in an existing manifest. Returns `None` if there is no such target.
+pub fn set_binary_target(
+    manifest: &str,
+    name: &str,
+    url: Option<&str>,
+    checksum: &str,
+) -> Option<String> {
+    let re = Regex::new(&format!(
+        r#"(\.binaryTarget\(\s*name:\s*"{}",\s*url:\s*)"([^"]*)"(,\s*checksum:\s*)"[^"]*""#,
+        regex::escape(name)
+    ))
+    .unwrap();
+    let captures = re.captures(manifest)?;
+    let url = url.unwrap_or_else(|| captures.get(2).unwrap().as_str());
+    let replacement = format!("${{1}}\"{}\"${{3}}\"{}\"", url.replace('$', "$$"), checksum);
+    Some(re.replace(manifest, replacement.as_str()).into_owned())
+}
@@ -0,0 +1,119 @@
+use std::{io, path::Path};
+
+/// The extension Xcode and the Swift Playgrounds app open app packages by.
+pub const EXTENSION: &str = "swiftpm";
+
+/// The oldest iOS that Swift Playgrounds app packages target.
+const MIN_IOS_VERSION: &str = "15.2";
+
+/// Where the copied xcframeworks live inside the package.
+const FRAMEWORKS_DIR: &str = "Frameworks";
+
+/// An app package manifest. The app's sources sit at the package root, as
+/// Swift Playgrounds expects.
+const PACKAGE_SWIFT: &str = r#"// swift-tools-version: 5.6
+
+import PackageDescription
+import AppleProductTypes
+
+let package = Package(
+    name: "%NAME%",
+    platforms: [
+        .iOS("%IOS_VERSION%")
+    ],
+    products: [
+        .iOSApplication(
+            name: "%NAME%",
+            targets: ["AppModule"],
+            bundleIdentifier: "dev.cargo-pod.%NAME%",
+            displayVersion: "1.0",
+            bundleVersion: "1",
+            appIcon: .placeholder(icon: .gamepad),
+            accentColor: .presetColor(.orange),
+            supportedDeviceFamilies: [
+                .pad,
+                .phone
+            ],
+            supportedInterfaceOrientations: [
+                .portrait,
+                .landscapeRight,
+                .landscapeLeft,
+                .portraitUpsideDown(.when(deviceFamilies: [.pad]))
+            ]
+        )
+    ],
+    targets: [
+        .executableTarget(
+            name: "AppModule",
+            dependencies: [%DEPENDENCIES%],
+            path: ".",
+            exclude: ["%FRAMEWORKS_DIR%"]
+        ),
+%BINARY_TARGETS%    ]
+)
+"#;
+
+/// The name of the app package, without its extension.
+pub fn name(pod_name: &str) -> String {
+    format!("{}Playground", pod_name.replace('-', ""))
+}
+
+/// Writes an app package into `dir` that links the `xcframeworks` found in
+/// `dist_dir` and runs the demo app importing `modules`.
+pub fn write(
+    dir: &Path,
+    pod_name: &str,
+    dist_dir: &Path,
+    xcframeworks: &[String],
+    modules: &[String],
+    functions: &[String],
+) -> io::Result<()> {
+    if dir.exists() {
+        std::fs::remove_dir_all(dir)?;
+    }
+    let frameworks_dir = dir.join(FRAMEWORKS_DIR);
+    std::fs::create_dir_all(&frameworks_dir)?;
+
+    // Binary targets must be inside the package, so the frameworks are
+    // copied rather than referenced from `dist`.
+    let mut binary_targets = String::new();
+    for name in xcframeworks {
+        let file_name = format!("{}.xcframework", name);
+        let source = dist_dir.join(&file_name);
+        if !source.is_dir() {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!(
+                    "{} not found; run `cargo pod build` first",
+                    source.display()
+                ),
+            ));
+        }
+        log::info!("Copying {}", file_name);
+        dircpy::copy_dir(&source, frameworks_dir.join(&file_name))?;
+        binary_targets.push_str(&format!(
+            "        .binaryTarget(name: \"{}\", path: \"{}/{}\"),\n",
+            name, FRAMEWORKS_DIR, file_name
+        ));
+    }
+
+    let name = name(pod_name);
+    let dependencies = xcframeworks
+        .iter()
+        .map(|x| format!("\"{}\"", x))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let path = dir.join(crate::package_swift::FILE_NAME);
+    log::info!("Writing {}", path.display());
+    std::fs::write(
+        path,
+        PACKAGE_SWIFT
+            .replace("%NAME%", &name)
+            .replace("%IOS_VERSION%", MIN_IOS_VERSION)
+            .replace("%DEPENDENCIES%", &dependencies)
+            .replace("%FRAMEWORKS_DIR%", FRAMEWORKS_DIR)
+            .replace("%BINARY_TARGETS%", &binary_targets),
+    )?;
+
+    crate::demo::write(dir, pod_name, modules, functions)
+}
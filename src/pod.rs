@@ -0,0 +1,463 @@
+//! Finding the crate and the pod around it: the manifest, the pod root,
+//! `dist` and where bundles are written.
+
+use std::path::{Path, PathBuf};
+
+use cargo_metadata::{Metadata, MetadataCommand, Package, Target};
+use glob::glob;
+use heck::CamelCase;
+
+use crate::{
+    build::BuildTarget,
+    bundle::{ArchiveFormat, Compression},
+    error::Error,
+};
+
+pub(crate) fn derive_manifest(
+    manifest_path: Option<&Path>,
+) -> Result<(Metadata, Package, Vec<Target>), Error> {
+    derive_manifest_with(manifest_path, ManifestOptions::default())
+}
+
+/// How `derive_manifest_with` picks the pod's package.
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct ManifestOptions<'a> {
+    /// Add the staticlib crate-type without asking if no package has one.
+    pub(crate) fix: bool,
+    /// The package to use when several build a staticlib.
+    pub(crate) package: Option<&'a str>,
+    /// Record the chosen package in `[workspace.metadata.pod]`.
+    pub(crate) record_package: bool,
+}
+
+/// Asks a yes/no question on the terminal. Always `false` when not
+/// interactive, so scripts and CI never block.
+pub(crate) fn confirm(question: &str) -> bool {
+    if !atty::is(atty::Stream::Stdin) || !atty::is(atty::Stream::Stderr) {
+        return false;
+    }
+    eprint!("{} [y/N] ", question);
+    let mut answer = String::new();
+    if std::io::stdin().read_line(&mut answer).is_err() {
+        return false;
+    }
+    matches!(answer.trim(), "y" | "Y" | "yes")
+}
+
+/// Asks which of `choices` to use on the terminal, returning its index.
+/// Always `None` when not interactive.
+pub(crate) fn choose(question: &str, choices: &[&str]) -> Option<usize> {
+    if !atty::is(atty::Stream::Stdin) || !atty::is(atty::Stream::Stderr) {
+        return None;
+    }
+    eprintln!("{}", question);
+    for (i, choice) in choices.iter().enumerate() {
+        eprintln!("  {}) {}", i + 1, choice);
+    }
+    loop {
+        eprint!("[1-{}] ", choices.len());
+        let mut answer = String::new();
+        if std::io::stdin().read_line(&mut answer).unwrap_or(0) == 0 {
+            return None;
+        }
+        match answer.trim().parse::<usize>() {
+            Ok(v) if v >= 1 && v <= choices.len() => return Some(v - 1),
+            _ => continue,
+        }
+    }
+}
+
+/// Like `derive_manifest`, but when no package builds a staticlib, offers to
+/// add the crate type to the first lib package, and when several do, uses the
+/// one recorded in the workspace metadata or asks which to use.
+pub(crate) fn derive_manifest_with(
+    manifest_path: Option<&Path>,
+    options: ManifestOptions,
+) -> Result<(Metadata, Package, Vec<Target>), Error> {
+    let mut cmd = MetadataCommand::new();
+
+    if let Some(path) = manifest_path {
+        cmd.manifest_path(path);
+    }
+
+    let metadata = match cmd.exec() {
+        Ok(v) => v,
+        Err(e) => return Err(Error::tool("cargo metadata", e.to_string())),
+    };
+    let packages = metadata
+        .packages
+        .iter()
+        .filter(|p| metadata.workspace_members.contains(&p.id))
+        .cloned()
+        .collect::<Vec<_>>();
+
+    log::trace!("Got these packages:");
+    log::trace!("{:#?}", packages);
+
+    let lib_targets = packages
+        .iter()
+        .filter_map(|x| {
+            let targets = x
+                .targets
+                .iter()
+                .filter(|x| x.kind.contains(&"staticlib".into()))
+                .collect::<Vec<_>>();
+
+            if targets.is_empty() {
+                return None;
+            }
+
+            Some((x, targets))
+        })
+        .collect::<Vec<_>>();
+
+    if lib_targets.is_empty() {
+        let lib_package = packages.iter().find(|x| {
+            options.package.map(|p| p == x.name).unwrap_or(true)
+                && x.targets.iter().any(|t| {
+                    t.kind
+                        .iter()
+                        .any(|k| matches!(k.as_str(), "lib" | "rlib" | "cdylib" | "dylib"))
+                })
+        });
+        let lib_package = match lib_package {
+            Some(v) => v,
+            None => return Err(Error::Usage("No lib crates found!".into())),
+        };
+        let manifest = lib_package.manifest_path.as_path();
+
+        if options.fix
+            || confirm(&format!(
+                "{} does not build a staticlib. Add `staticlib` to the [lib] crate-type in {}?",
+                lib_package.name,
+                manifest.display()
+            ))
+        {
+            log::info!("Adding staticlib crate-type to {}", manifest.display());
+            if let Err(e) = crate::cargo::add_staticlib_crate_type(manifest) {
+                return Err(Error::Io {
+                    path: manifest.into(),
+                    source: e,
+                });
+            }
+            return derive_manifest_with(
+                manifest_path,
+                ManifestOptions {
+                    fix: false,
+                    ..options
+                },
+            );
+        }
+
+        return Err(Error::Usage(format!(
+            "No lib crates found! {} does not build a staticlib. Add `crate-type = [\"staticlib\", \"lib\"]` to the [lib] section of {}, or rerun with --fix.",
+            lib_package.name,
+            manifest.display()
+        )));
+    }
+
+    log::trace!("Got these libs:");
+    log::trace!("{:#?}", &lib_targets);
+
+    let names = lib_targets
+        .iter()
+        .map(|(x, _)| x.name.as_str())
+        .collect::<Vec<_>>();
+    let recorded = crate::cargo::recorded_package(&metadata.workspace_root);
+    let wanted = options
+        .package
+        .map(str::to_string)
+        .or_else(|| recorded.clone());
+    let index = match wanted {
+        Some(name) => match names.iter().position(|x| *x == name) {
+            Some(v) => v,
+            None => {
+                return Err(Error::Usage(format!(
+                    "Package {} does not build a staticlib; expected one of: {}",
+                    name,
+                    names.join(", ")
+                )));
+            }
+        },
+        None if names.len() > 1 => {
+            match choose(
+                "Several packages build a staticlib. Which is the pod?",
+                &names,
+            ) {
+                Some(v) => v,
+                None if options.record_package => {
+                    return Err(Error::Usage(format!(
+                        "Several packages build a staticlib: {}. Pass --package to choose one.",
+                        names.join(", ")
+                    )));
+                }
+                None => {
+                    log::warn!(
+                        "Several packages build a staticlib: {}. Using {}; run `cargo pod init --package <name>` to choose.",
+                        names.join(", "),
+                        names[0]
+                    );
+                    0
+                }
+            }
+        }
+        None => 0,
+    };
+
+    if options.record_package && names.len() > 1 && recorded.as_deref() != Some(names[index]) {
+        log::info!(
+            "Recording {} as the pod package in {}",
+            names[index],
+            metadata.workspace_root.join("Cargo.toml").display()
+        );
+        crate::cargo::record_package(&metadata.workspace_root, names[index])
+            .map_err(Error::io(metadata.workspace_root.join("Cargo.toml")))?;
+    }
+
+    let (package, targets) = &lib_targets[index];
+    crate::meta::validate(package)?;
+    Ok((
+        metadata,
+        (**package).clone(),
+        targets.iter().map(|x| (*x).clone()).collect::<Vec<_>>(),
+    ))
+}
+
+pub(crate) fn pod_name(package: &Package) -> String {
+    crate::meta::config(package)
+        .name
+        .unwrap_or_else(|| package.name.to_camel_case())
+}
+
+pub(crate) fn pod_version(package: &Package) -> Result<String, Error> {
+    let config = crate::meta::config(package);
+    crate::version::pod_version(&package.version, &config).map_err(Error::Usage)
+}
+
+/// The module names of the Swift and FFI frameworks built for a target.
+pub(crate) fn framework_names(target: &Target) -> (String, String) {
+    let sys_name = target.name.replace('-', "_");
+    let ffi_mod_name = format!("{sys_name}_ffi").to_camel_case();
+    let mod_name = sys_name.to_camel_case();
+    (mod_name, ffi_mod_name)
+}
+
+/// The directory containing the podspec, `src` and `dist`.
+pub(crate) fn resolve_pod_root(dist_dir: &Path) -> PathBuf {
+    dist_dir.parent().unwrap().to_path_buf()
+}
+
+pub(crate) fn has_subtree_at(dir: &Path) -> bool {
+    dir.join("crate").is_dir() && dir.join(crate::subtree::REMOTE_FILE).exists()
+}
+
+/// The `pod_root` configured for `package`, which commands run in.
+pub(crate) fn configured_pod_root(package: &Package) -> Option<PathBuf> {
+    crate::meta::config(package)
+        .pod_root
+        .map(|x| package.manifest_path.parent().unwrap().join(x))
+        .map(|x| std::fs::canonicalize(&x).unwrap_or(x))
+}
+
+/// The `pod_root` configured for the pod package of the crate at
+/// `manifest_path`, with that package's manifest. Never asks anything: with
+/// several staticlib packages, uses the recorded one or else the first.
+pub(crate) fn find_configured_pod_root(manifest_path: Option<&Path>) -> Option<(PathBuf, PathBuf)> {
+    let mut cmd = MetadataCommand::new();
+    cmd.no_deps();
+    if let Some(path) = manifest_path {
+        cmd.manifest_path(path);
+    }
+    let metadata = cmd.exec().ok()?;
+    let recorded = crate::cargo::recorded_package(&metadata.workspace_root);
+    let packages = metadata
+        .packages
+        .iter()
+        .filter(|p| metadata.workspace_members.contains(&p.id))
+        .filter(|p| {
+            p.targets
+                .iter()
+                .any(|t| t.kind.contains(&"staticlib".into()))
+        })
+        .collect::<Vec<_>>();
+    let package = packages
+        .iter()
+        .find(|p| recorded.as_deref() == Some(p.name.as_str()))
+        .or_else(|| packages.first())?;
+    let pod_root = configured_pod_root(package)?;
+    Some((pod_root, package.manifest_path.clone()))
+}
+
+pub(crate) fn is_pod_root(dir: &Path) -> bool {
+    find_podspec(dir).is_some()
+        || dir.join(crate::subtree::REMOTE_FILE).exists()
+        || dir.join("crate").join("Cargo.toml").exists()
+}
+
+/// Finds the pod root for a command run in `start`: the nearest directory
+/// with a podspec, `.crate-remote` or `crate/Cargo.toml`, without leaving
+/// the git repository, or else the nearest one with a `Cargo.toml`, as cargo
+/// does.
+pub(crate) fn find_pod_root(start: &Path) -> Option<PathBuf> {
+    let mut manifest_dir = None;
+    for dir in start.ancestors() {
+        if is_pod_root(dir) {
+            return Some(dir.to_path_buf());
+        }
+        if manifest_dir.is_none() && dir.join("Cargo.toml").exists() {
+            manifest_dir = Some(dir.to_path_buf());
+        }
+        if dir.join(".git").exists() {
+            break;
+        }
+    }
+    manifest_dir
+}
+
+/// `to` relative to `from`, where both are absolute.
+pub(crate) fn relative_path(from: &Path, to: &Path) -> PathBuf {
+    let from = from.components().collect::<Vec<_>>();
+    let to = to.components().collect::<Vec<_>>();
+    let common = from.iter().zip(&to).take_while(|(a, b)| a == b).count();
+    let mut out = PathBuf::new();
+    for _ in common..from.len() {
+        out.push("..");
+    }
+    for component in &to[common..] {
+        out.push(component);
+    }
+    if out.as_os_str().is_empty() {
+        out.push(".");
+    }
+    out
+}
+
+/// The manifest a command builds: `--manifest-path` when given, otherwise
+/// the vendored crate's in a subtree layout, or cargo's own lookup.
+pub(crate) fn pod_manifest(manifest_path: Option<&Path>, has_subtree: bool) -> Option<&Path> {
+    match manifest_path {
+        Some(v) => Some(v),
+        None if has_subtree => Some(Path::new("./crate/Cargo.toml")),
+        None => None,
+    }
+}
+
+pub(crate) fn resolve_dist_dir(
+    metadata: &Metadata,
+    package: &Package,
+    has_subtree: bool,
+) -> PathBuf {
+    if let Some(pod_root) = configured_pod_root(package) {
+        return pod_root.join("dist");
+    }
+    if has_subtree {
+        Path::new("./dist").to_path_buf()
+    } else {
+        Path::new(&metadata.target_directory)
+            .parent()
+            .unwrap()
+            .join("dist")
+    }
+}
+
+/// Commits only `paths`.
+pub(crate) fn git_commit_paths<P: AsRef<Path>>(message: &str, paths: &[P]) -> Result<(), Error> {
+    if crate::cmd::is_dry_run() && crate::git::open().is_err() {
+        // Nothing to commit to until the repository `git init` would create.
+        log::info!("Would git commit -m {:?}", message);
+        return Ok(());
+    }
+    crate::git::open()
+        .and_then(|repo| crate::git::commit_paths(&repo, message, paths))
+        .map_err(|e| Error::Git(format!("Failed to commit \"{}\": {}", message, e.message())))?;
+    Ok(())
+}
+
+/// Finds the main podspec in `dir`, ignoring the per-platform variants
+/// written by `bundle --split-platforms`.
+pub(crate) fn find_podspec(dir: &Path) -> Option<PathBuf> {
+    let mut podspecs = glob(&dir.join("*.podspec").to_string_lossy())
+        .unwrap()
+        .filter_map(Result::ok)
+        .collect::<Vec<_>>();
+    podspecs.sort_by_key(|x| {
+        let name = x.file_name().unwrap().to_string_lossy();
+        (
+            SPLIT_PLATFORMS
+                .iter()
+                .any(|(_, suffix, _)| name.ends_with(&format!("-{}.podspec", suffix))),
+            name.len(),
+        )
+    });
+    podspecs.into_iter().next()
+}
+
+/// Resolves the archive path, or that of a single `platform`'s archive. An
+/// explicit `output` is taken relative to the current directory; configured
+/// and default outputs to the pod root.
+pub(crate) fn bundle_output_path(
+    pod_root: &Path,
+    package: &Package,
+    output: Option<&Path>,
+    format: ArchiveFormat,
+    compression: Compression,
+    platform: Option<&str>,
+) -> Result<PathBuf, Error> {
+    let config = crate::meta::config(package);
+    let (base, template) = match output {
+        Some(v) => (Path::new(""), v.to_string_lossy().to_string()),
+        None => (
+            pod_root,
+            config
+                .bundle
+                .output
+                .unwrap_or_else(|| format.default_output(compression)),
+        ),
+    };
+    let name = pod_name(package);
+    let version = pod_version(package)?;
+    Ok(match platform {
+        Some(platform) => base.join(crate::bundle::render_platform_output(
+            &template, &name, &version, platform,
+        )),
+        None => base.join(crate::bundle::render_output(
+            &template,
+            &name,
+            &version,
+            crate::bundle::UNIVERSAL_PLATFORM,
+        )),
+    })
+}
+
+/// Platform name, pod name suffix and build target for each split bundle.
+pub(crate) const SPLIT_PLATFORMS: &[(&str, &str, BuildTarget)] = &[
+    ("ios", "iOS", BuildTarget::_iOS),
+    ("macos", "macOS", BuildTarget::MacOS),
+];
+
+/// Runs the `name` hook from `[package.metadata.pod.hooks]`, if set, with
+/// `sh -c` in the pod root.
+pub(crate) fn run_hook(name: &str, command: Option<&str>, package: &Package) -> Result<(), Error> {
+    let command = match command {
+        Some(v) => v,
+        None => return Ok(()),
+    };
+    log::info!("Running {} hook: {}", name, command);
+    let mut cmd = std::process::Command::new("sh");
+    cmd.arg("-c")
+        .arg(command)
+        .env("CARGO_POD_NAME", pod_name(package))
+        .env("CARGO_POD_VERSION", pod_version(package)?);
+    crate::cmd::run(&format!("{} hook", name), &mut cmd)
+}
+
+/// The xcframework zips `bundle` wrote into `output_dir`.
+pub(crate) fn xcframework_zips(output_dir: &Path) -> Vec<PathBuf> {
+    let pattern = output_dir.join(format!("*{}", crate::package_swift::ZIP_SUFFIX));
+    let mut zips = glob(&pattern.to_string_lossy())
+        .unwrap()
+        .filter_map(Result::ok)
+        .collect::<Vec<_>>();
+    zips.sort();
+    zips
+}
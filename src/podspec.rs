@@ -1,12 +1,26 @@
-use cargo_metadata::{Package, Target};
+use cargo_metadata::{DependencyKind, Metadata, Package, Target};
+use glob::glob;
 use heck::CamelCase;
 use indexmap::IndexMap;
 use once_cell::sync::Lazy;
 use regex::Regex;
-use std::fmt::Display;
+use std::{
+    fmt::Display,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    cmd::MinVersions,
+    error::Error,
+    pod::{
+        derive_manifest, find_podspec, framework_names, pod_manifest, pod_name, pod_version,
+        relative_path, resolve_dist_dir, xcframework_zips,
+    },
+};
 
 pub struct Source {
     pub http: String,
+    pub sha256: Option<String>,
 }
 
 #[non_exhaustive]
@@ -18,18 +32,23 @@ pub struct OsSubspec {
 pub struct Podspec {
     pub name: String,
     pub summary: String,
+    pub description: Option<String>,
     pub version: String,
     pub authors: IndexMap<String, String>,
     pub license: String,
+    pub license_file: Option<String>,
     pub homepage: String,
-    pub source: Source,
+    pub source: Option<Source>,
     pub source_files: Vec<String>,
+    pub exclude_files: Vec<String>,
     pub macos: OsSubspec,
     pub ios: OsSubspec,
     pub vendored_frameworks: Vec<String>,
+    pub resources: Vec<String>,
     pub pod_target_xcconfig: IndexMap<String, String>,
     pub prepare_command: Option<String>,
     pub preserve_paths: Vec<String>,
+    pub dependencies: IndexMap<String, String>,
 }
 
 impl Podspec {
@@ -47,6 +66,63 @@ impl Podspec {
         }
     }
 
+    pub(crate) fn set_deployment_targets(&mut self, min_versions: &MinVersions) {
+        self.ios.deployment_target = min_versions.ios.clone();
+        self.macos.deployment_target = min_versions.macos.clone();
+    }
+
+    /// Merges user-provided xcconfig entries. `OTHER_LDFLAGS` is appended to
+    /// so that the generated link flags are kept; other keys are replaced.
+    pub(crate) fn merge_xcconfig<'a>(
+        &mut self,
+        entries: impl IntoIterator<Item = (&'a String, &'a String)>,
+    ) {
+        for (key, value) in entries {
+            match self.pod_target_xcconfig.get_mut(key) {
+                Some(v) if key == "OTHER_LDFLAGS" => {
+                    v.push(' ');
+                    v.push_str(value);
+                }
+                _ => {
+                    self.pod_target_xcconfig.insert(key.clone(), value.clone());
+                }
+            }
+        }
+    }
+
+    /// Switches the spec to building the Rust crate in `crate_dir` during
+    /// `pod install` rather than shipping prebuilt frameworks.
+    pub(crate) fn set_source_build(&mut self, crate_dir: &str) {
+        let crate_glob = if crate_dir.is_empty() {
+            "**/*".to_string()
+        } else {
+            format!("{}/**/*", crate_dir)
+        };
+        if !self.preserve_paths.contains(&crate_glob) {
+            self.preserve_paths.push(crate_glob);
+        }
+
+        self.prepare_command = Some(
+            [
+                "if ! command -v cargo >/dev/null 2>&1; then",
+                "  echo 'cargo is required to build this pod from source' >&2",
+                "  exit 1",
+                "fi",
+                "cargo pod build",
+            ]
+            .join("\n"),
+        );
+    }
+
+    /// Points the `:http` source at a differently named release asset.
+    pub(crate) fn set_source_asset(&mut self, asset_name: &str) {
+        if let Some(source) = &mut self.source {
+            if let Some((base, _)) = source.http.rsplit_once('/') {
+                source.http = format!("{}/{}", base, asset_name);
+            }
+        }
+    }
+
     pub(crate) fn disable_bitcode(&mut self) {
         self.pod_target_xcconfig
             .insert("ENABLE_BITCODE".into(), "NO".into());
@@ -56,6 +132,217 @@ impl Podspec {
 static AUTHOR_RE: Lazy<Regex> = regex_static::lazy_regex!(r"^\s*(.+?)(?: <(.+?)>)?\s*$");
 static SOURCE_RE: Lazy<Regex> =
     regex_static::lazy_regex!(r"^https://github\.com/(.*?)/(.*?)(?:\.git)?/?$");
+static SHA256_RE: Lazy<Regex> = regex_static::lazy_regex!(r"(?m)^(\s*):sha256 => '(.*?)',?$");
+static HTTP_RE: Lazy<Regex> = regex_static::lazy_regex!(r"(?m)^(\s*):http => '.*?',?\n");
+
+static VERSION_RE: Lazy<Regex> =
+    regex_static::lazy_regex!(r"(?m)^(\s*spec\.version\s*=\s*)'(.*?)'");
+
+/// Reads `spec.version` from an existing podspec.
+pub(crate) fn spec_version(spec: &str) -> Option<&str> {
+    VERSION_RE
+        .captures(spec)
+        .and_then(|c| c.get(2))
+        .map(|x| x.as_str())
+}
+
+/// Sets `spec.version` in an existing podspec.
+pub(crate) fn set_spec_version(spec: &str, version: &str) -> Option<String> {
+    if !VERSION_RE.is_match(spec) {
+        return None;
+    }
+    let replacement = format!("${{1}}'{}'", escape_apos(version));
+    Some(VERSION_RE.replace(spec, replacement.as_str()).into_owned())
+}
+
+static PATHS_RE: Lazy<Regex> = regex_static::lazy_regex!(
+    r"(?m)^\s*spec(?:\.\w+)?\.(?:source_files|vendored_frameworks|vendored_libraries|preserve_paths|resources)\s*=\s*(.*)$"
+);
+static QUOTED_RE: Lazy<Regex> = regex_static::lazy_regex!(r"'((?:[^'\\]|\\.)*)'");
+
+/// Lists the file patterns an existing podspec expects to find in the pod.
+pub(crate) fn referenced_paths(spec: &str) -> Vec<String> {
+    PATHS_RE
+        .captures_iter(spec)
+        .flat_map(|c| {
+            QUOTED_RE
+                .captures_iter(c.get(1).unwrap().as_str())
+                .map(|x| x[1].replace("\\'", "'"))
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+static NAME_RE: Lazy<Regex> = regex_static::lazy_regex!(r"(?m)^(\s*spec\.name\s*=\s*)'(.*?)'");
+static HTTP_ASSET_RE: Lazy<Regex> =
+    regex_static::lazy_regex!(r"(?m)^(\s*:http => '.*/)([^/']*)(',?)$");
+
+/// Derives a single-platform variant of an existing podspec: renames the pod,
+/// drops the other platform's deployment target and points the `:http`
+/// source at the platform's asset.
+pub(crate) fn platform_variant(spec: &str, name: &str, platform: &str, asset_name: &str) -> String {
+    let name_replacement = format!("${{1}}'{}'", escape_apos(name));
+    let spec = NAME_RE.replace(spec, name_replacement.as_str());
+
+    let asset_replacement = format!("${{1}}{}${{3}}", asset_name);
+    let spec = HTTP_ASSET_RE.replace(&spec, asset_replacement.as_str());
+
+    let other_prefix = match platform {
+        "ios" => "spec.macos.",
+        _ => "spec.ios.",
+    };
+    spec.lines()
+        .filter(|line| !line.trim_start().starts_with(other_prefix))
+        .map(|line| format!("{}\n", line))
+        .collect()
+}
+
+static HTTP_URL_RE: Lazy<Regex> = regex_static::lazy_regex!(r"(?m)^(\s*:http => )'(.*?)'(,?)$");
+
+/// Reads the `:http` source URL from an existing podspec.
+pub(crate) fn source_http(spec: &str) -> Option<&str> {
+    HTTP_URL_RE
+        .captures(spec)
+        .and_then(|c| c.get(2))
+        .map(|x| x.as_str())
+}
+
+/// Reads the `:sha256` source checksum from an existing podspec.
+pub(crate) fn source_sha256(spec: &str) -> Option<&str> {
+    SHA256_RE
+        .captures(spec)
+        .and_then(|c| c.get(2))
+        .map(|x| x.as_str())
+}
+
+/// Points the `:http` source of an existing podspec at `url`.
+pub(crate) fn set_source_http(spec: &str, url: &str) -> Option<String> {
+    if !HTTP_URL_RE.is_match(spec) {
+        return None;
+    }
+    let replacement = format!("${{1}}'{}'${{3}}", escape_apos(url).replace('$', "$$"));
+    Some(HTTP_URL_RE.replace(spec, replacement.as_str()).into_owned())
+}
+
+/// Sets the `:sha256` checksum of the spec source in an existing podspec,
+/// replacing any previous checksum or inserting one after the `:http` line.
+pub(crate) fn set_source_sha256(spec: &str, sha256: &str) -> Option<String> {
+    if SHA256_RE.is_match(spec) {
+        let replacement = format!("${{1}}:sha256 => '{}',", sha256);
+        return Some(SHA256_RE.replace(spec, replacement.as_str()).into_owned());
+    }
+
+    let m = HTTP_RE.captures(spec)?;
+    let whole = m.get(0).unwrap();
+    let indent = m.get(1).unwrap().as_str();
+    let mut out = spec.to_string();
+    out.insert_str(
+        whole.end(),
+        &format!("{}:sha256 => '{}',\n", indent, sha256),
+    );
+    Some(out)
+}
+
+static SPEC_VAR_RE: Lazy<Regex> =
+    regex_static::lazy_regex!(r"Pod::Spec\.new\s*(?:\{|do)\s*\|\s*(\w+)\s*\|");
+
+/// A podspec split into the lines before the first attribute, one entry per
+/// top-level attribute, and the closing lines.
+struct Statements<'a> {
+    head: Vec<&'a str>,
+    body: Vec<(String, Vec<&'a str>)>,
+    tail: Vec<&'a str>,
+}
+
+fn statements(spec: &str) -> Option<Statements<'_>> {
+    let var = SPEC_VAR_RE.captures(spec)?.get(1).unwrap().as_str();
+    let prefix = format!("{}.", var);
+    let lines = spec.lines().collect::<Vec<_>>();
+    let close = lines
+        .iter()
+        .rposition(|x| matches!(x.trim(), "}" | "end"))?;
+
+    let mut out = Statements {
+        head: vec![],
+        body: vec![],
+        tail: lines[close..].to_vec(),
+    };
+    for line in &lines[..close] {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with(&prefix) {
+            // Attributes are keyed by name; dependencies by name and pod.
+            let key = trimmed
+                .split(" = ")
+                .next()
+                .unwrap()
+                .split(',')
+                .next()
+                .unwrap()
+                .trim()
+                .to_string();
+            out.body.push((key, vec![line]));
+        } else {
+            match out.body.last_mut() {
+                Some((_, v)) => v.push(line),
+                None => out.head.push(line),
+            }
+        }
+    }
+    Some(out)
+}
+
+/// Merges a freshly generated podspec into an existing one: attributes the
+/// existing spec sets are kept as they are, and any it lacks are added.
+/// Returns the merged spec and the added attributes, or `None` if the
+/// existing spec is not in a form that can be merged.
+pub(crate) fn merge(existing: &str, generated: &str) -> Option<(String, Vec<String>)> {
+    let existing_statements = statements(existing)?;
+    let generated_statements = statements(generated)?;
+    let existing_var = SPEC_VAR_RE.captures(existing)?.get(1).unwrap().as_str();
+    let generated_var = SPEC_VAR_RE.captures(generated)?.get(1).unwrap().as_str();
+    let rename = |x: &str| {
+        format!(
+            "{}.{}",
+            existing_var,
+            x.strip_prefix(&format!("{}.", generated_var)).unwrap_or(x)
+        )
+    };
+
+    let mut out = String::new();
+    for line in &existing_statements.head {
+        out.push_str(line);
+        out.push('\n');
+    }
+    for (_, lines) in &existing_statements.body {
+        for line in lines {
+            out.push_str(line);
+            out.push('\n');
+        }
+    }
+
+    let mut added = vec![];
+    for (key, lines) in &generated_statements.body {
+        let key = rename(key);
+        if existing_statements.body.iter().any(|(x, _)| *x == key) {
+            continue;
+        }
+        for line in lines {
+            out.push_str(&line.replacen(
+                &format!("{}.", generated_var),
+                &format!("{}.", existing_var),
+                1,
+            ));
+            out.push('\n');
+        }
+        added.push(key);
+    }
+
+    for line in &existing_statements.tail {
+        out.push_str(line);
+        out.push('\n');
+    }
+    Some((out, added))
+}
 
 impl From<Package> for Podspec {
     fn from(p: Package) -> Self {
@@ -74,6 +361,8 @@ impl From<Package> for Podspec {
             }
         }
 
+        let min_versions = MinVersions::default();
+
         if authors.is_empty() {
             authors.insert("Unknown".to_string(), "<EMAIL>".to_string());
         }
@@ -95,22 +384,30 @@ impl From<Package> for Podspec {
         Podspec {
             name: p.name.to_camel_case(),
             summary: p.description.unwrap_or_else(|| "UNKNOWN".into()),
+            description: None,
             version: p.version.to_string(),
             authors,
             license: p.license.unwrap_or_else(|| "UNKNOWN".into()),
+            license_file: None,
             homepage: p.repository.clone().unwrap_or_else(|| "UNKNOWN".into()),
-            source: Source { http: source },
+            source: Some(Source {
+                http: source,
+                sha256: None,
+            }),
             macos: OsSubspec {
-                deployment_target: "10.10".into(),
+                deployment_target: min_versions.macos,
             },
             ios: OsSubspec {
-                deployment_target: "8.0".into(),
+                deployment_target: min_versions.ios,
             },
             source_files: vec!["src/**/*".into()],
+            exclude_files: vec![],
             vendored_frameworks: vec![format!("dist/{}.xcframework", p.name.to_camel_case())],
+            resources: vec![],
             pod_target_xcconfig: Default::default(),
             prepare_command: None,
             preserve_paths: vec![],
+            dependencies: Default::default(),
         }
     }
 }
@@ -119,6 +416,26 @@ fn escape_apos(input: &str) -> String {
     input.replace('\'', "\\'")
 }
 
+/// Writes `value` as a single-quoted Ruby heredoc, which performs no
+/// interpolation or escape processing, choosing a terminator that does not
+/// occur in the text.
+fn write_heredoc(f: &mut std::fmt::Formatter<'_>, key: &str, value: &str) -> std::fmt::Result {
+    let mut terminator = "DESC".to_string();
+    while value.lines().any(|line| line.trim() == terminator) {
+        terminator.push('_');
+    }
+
+    f.write_fmt(format_args!("  {} = <<-'{}'\n", key, terminator))?;
+    for line in value.trim_end().lines() {
+        if line.is_empty() {
+            f.write_str("\n")?;
+        } else {
+            f.write_fmt(format_args!("    {}\n", line))?;
+        }
+    }
+    f.write_fmt(format_args!("  {}\n", terminator))
+}
+
 impl Display for Podspec {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.write_str("Pod::Spec.new { |spec|\n")?;
@@ -134,6 +451,9 @@ impl Display for Podspec {
             "  spec.summary = '{}'\n",
             escape_apos(&self.summary)
         ))?;
+        if let Some(description) = &self.description {
+            write_heredoc(f, "spec.description", description)?;
+        }
         f.write_str("  spec.authors = {\n")?;
         for (name, email) in self.authors.iter() {
             f.write_fmt(format_args!(
@@ -143,10 +463,17 @@ impl Display for Podspec {
             ))?;
         }
         f.write_str("  }\n")?;
-        f.write_fmt(format_args!(
-            "  spec.license = {{ :type => '{}' }}\n",
-            escape_apos(&self.license)
-        ))?;
+        match &self.license_file {
+            Some(file) => f.write_fmt(format_args!(
+                "  spec.license = {{ :type => '{}', :file => '{}' }}\n",
+                escape_apos(&self.license),
+                escape_apos(file)
+            ))?,
+            None => f.write_fmt(format_args!(
+                "  spec.license = {{ :type => '{}' }}\n",
+                escape_apos(&self.license)
+            ))?,
+        }
         f.write_fmt(format_args!(
             "  spec.homepage = '{}'\n",
             escape_apos(&self.homepage)
@@ -173,6 +500,13 @@ impl Display for Podspec {
             }
             f.write_str("  }\n")?;
         }
+        if let Some(prepare_command) = &self.prepare_command {
+            f.write_str("  spec.prepare_command = <<-CMD\n")?;
+            for line in prepare_command.lines() {
+                f.write_fmt(format_args!("    {}\n", line))?;
+            }
+            f.write_str("  CMD\n")?;
+        }
         if !self.preserve_paths.is_empty() {
             f.write_fmt(format_args!(
                 "  spec.preserve_paths = ['{}']\n",
@@ -182,11 +516,18 @@ impl Display for Podspec {
 
         if !self.vendored_frameworks.is_empty() {
             f.write_fmt(format_args!(
-                "  spec.macos.vendored_libraries = ['{}']\n",
+                "  spec.vendored_frameworks = ['{}']\n",
                 self.vendored_frameworks.join("', '")
             ))?;
         }
 
+        if !self.resources.is_empty() {
+            f.write_fmt(format_args!(
+                "  spec.resources = ['{}']\n",
+                self.resources.join("', '")
+            ))?;
+        }
+
         if !self.source_files.is_empty() {
             f.write_fmt(format_args!(
                 "  spec.source_files = ['{}']\n",
@@ -194,9 +535,490 @@ impl Display for Podspec {
             ))?;
         }
 
-        f.write_str("  spec.source = {\n")?;
-        f.write_fmt(format_args!("    :http => '{}',\n", self.source.http))?;
-        f.write_str("  }\n")?;
+        for (name, version) in self.dependencies.iter() {
+            f.write_fmt(format_args!(
+                "  spec.dependency '{}', '{}'\n",
+                escape_apos(name),
+                escape_apos(version)
+            ))?;
+        }
+
+        if !self.exclude_files.is_empty() {
+            f.write_fmt(format_args!(
+                "  spec.exclude_files = ['{}']\n",
+                self.exclude_files.join("', '")
+            ))?;
+        }
+
+        if let Some(source) = &self.source {
+            f.write_str("  spec.source = {\n")?;
+            f.write_fmt(format_args!("    :http => '{}',\n", source.http))?;
+            if let Some(sha256) = &source.sha256 {
+                f.write_fmt(format_args!("    :sha256 => '{}',\n", sha256))?;
+            }
+            f.write_str("  }\n")?;
+        }
         f.write_str("}\n")
     }
 }
+
+/// Generates the podspec for a crate, like `cargo pod podspec`.
+#[derive(Debug, Clone, Default)]
+pub struct PodspecBuilder {
+    pub(crate) manifest_path: Option<PathBuf>,
+    pub(crate) name: Option<String>,
+    pub(crate) source_build: bool,
+    pub(crate) local: bool,
+}
+
+impl PodspecBuilder {
+    pub fn new() -> PodspecBuilder {
+        Default::default()
+    }
+
+    /// The crate's manifest; found from the current directory if not given.
+    pub fn manifest_path(mut self, path: impl Into<PathBuf>) -> PodspecBuilder {
+        self.manifest_path = Some(path.into());
+        self
+    }
+
+    /// Overrides the pod name derived from the crate.
+    pub fn name(mut self, name: impl Into<String>) -> PodspecBuilder {
+        self.name = Some(name.into());
+        self
+    }
+
+    /// Emits a spec that builds the crate during `pod install`.
+    pub fn source_build(mut self, source_build: bool) -> PodspecBuilder {
+        self.source_build = source_build;
+        self
+    }
+
+    /// Emits a spec for local development with `:path`, which uses the
+    /// xcframeworks in `dist` and is written there.
+    pub fn local(mut self, local: bool) -> PodspecBuilder {
+        self.local = local;
+        self
+    }
+
+    /// The podspec's contents.
+    pub fn build(&self) -> Result<String, Error> {
+        Ok(self.generate()?.1.to_string())
+    }
+
+    /// Fails with a diff if the podspec on disk differs from the generated
+    /// one.
+    pub fn check(&self) -> Result<(), Error> {
+        let (path, podspec) = self.generate()?;
+        check_podspec(&path, &podspec.to_string())
+    }
+
+    /// Writes the podspec, returning its path.
+    pub fn write(&self) -> Result<PathBuf, Error> {
+        let (path, podspec) = self.generate()?;
+        log::info!("Writing {}", path.display());
+        crate::fs::write(&path, podspec.to_string())?;
+        crate::message::artifact("podspec", &path);
+        Ok(path)
+    }
+
+    /// The podspec and where it belongs: the pod root, or `dist` for a
+    /// local spec.
+    fn generate(&self) -> Result<(PathBuf, Podspec), Error> {
+        let has_subtree = std::fs::read_dir("./crate").is_ok();
+        let (metadata, package, targets) =
+            derive_manifest(pod_manifest(self.manifest_path.as_deref(), has_subtree))?;
+
+        let mut podspec = generate_podspec(
+            &metadata,
+            &package,
+            &targets,
+            self.name.clone(),
+            self.source_build,
+        )?;
+        if !self.local {
+            let path = PathBuf::from(format!("{}.podspec", podspec.name));
+            return Ok((path, podspec));
+        }
+
+        // The local spec lives in the dist dir so that everything it references
+        // is inside the pod root, as CocoaPods requires for `:path` pods.
+        let dist_dir = resolve_dist_dir(&metadata, &package, has_subtree);
+        crate::fs::create_dir_all(&dist_dir)?;
+
+        // The license file was found relative to the pod root, but CocoaPods
+        // reads paths relative to the spec.
+        let pod_root = std::fs::canonicalize(".").map_err(Error::io("."))?;
+        let spec_dir = std::fs::canonicalize(&dist_dir).map_err(Error::io(&dist_dir))?;
+        podspec.license_file = podspec.license_file.map(|x| {
+            relative_path(&spec_dir, &pod_root.join(x))
+                .to_string_lossy()
+                .to_string()
+        });
+        podspec.source = None;
+        podspec.source_files = vec![];
+        podspec.vendored_frameworks = targets
+            .iter()
+            .flat_map(|target| {
+                let (mod_name, ffi_mod_name) = framework_names(target);
+                [
+                    format!("{mod_name}.xcframework"),
+                    format!("{ffi_mod_name}.xcframework"),
+                ]
+            })
+            .collect();
+
+        let path = dist_dir.join(format!("{}.podspec", podspec.name));
+        Ok((path, podspec))
+    }
+}
+
+/// Finds the other pods in the workspace that `package` depends on, keyed by
+/// pod name, with their pod versions.
+pub(crate) fn workspace_pod_dependencies(
+    metadata: &Metadata,
+    package: &Package,
+) -> Result<Vec<(String, String)>, Error> {
+    package
+        .dependencies
+        .iter()
+        .filter(|dep| dep.kind == DependencyKind::Normal)
+        .filter_map(|dep| {
+            metadata.packages.iter().find(|p| {
+                p.name == dep.name
+                    && p.id != package.id
+                    && metadata.workspace_members.contains(&p.id)
+                    && p.targets
+                        .iter()
+                        .any(|t| t.kind.contains(&"staticlib".into()))
+            })
+        })
+        .map(|p| Ok((pod_name(p), pod_version(p)?)))
+        .collect()
+}
+
+pub(crate) fn generate_podspec(
+    metadata: &Metadata,
+    package: &Package,
+    targets: &[Target],
+    name: Option<String>,
+    source_build: bool,
+) -> Result<Podspec, Error> {
+    let mut config = crate::meta::config(package);
+
+    if let Some(name) = name {
+        config.name = Some(name);
+    }
+
+    let mut podspec = Podspec::from(package.clone());
+    podspec.version = pod_version(package)?;
+    podspec.set_deployment_targets(&config.min_versions());
+    podspec.disable_bitcode();
+    for target in targets {
+        podspec.add_target(target);
+    }
+    podspec.merge_xcconfig(&config.xcconfig);
+    podspec.description = config
+        .description
+        .clone()
+        .or_else(|| readme_excerpt(package));
+    podspec.license_file = find_license_file(package);
+    podspec.preserve_paths = config.preserve_paths;
+    podspec.exclude_files = config.exclude_files;
+    let format = config.bundle.format.unwrap_or_default();
+    let compression = config.bundle.compression.unwrap_or_default();
+    let output = config
+        .bundle
+        .output
+        .clone()
+        .unwrap_or_else(|| format.default_output(compression));
+    let asset = crate::bundle::render_output(
+        &output,
+        &pod_name(package),
+        "#{spec.version}",
+        crate::bundle::UNIVERSAL_PLATFORM,
+    );
+    if let Some(asset_name) = Path::new(&asset).file_name() {
+        podspec.set_source_asset(&asset_name.to_string_lossy());
+    }
+    if config.acknowledgements && config.acknowledgements_resource {
+        podspec
+            .resources
+            .push(format!("dist/{}", crate::acknowledgements::PLIST_FILE_NAME));
+    }
+    podspec.dependencies = workspace_pod_dependencies(metadata, package)?
+        .into_iter()
+        .collect();
+
+    if source_build || config.build_from_source {
+        let cur = crate::fs::canonicalize(".")?;
+        let package_dir = crate::fs::canonicalize(package.manifest_path.parent().unwrap())?;
+        match package_dir.strip_prefix(&cur) {
+            Ok(rel) => podspec.set_source_build(&rel.to_string_lossy()),
+            Err(_) => {
+                return Err(Error::Usage(format!(
+                    "The crate at {} must be inside the pod directory to build from source.",
+                    package_dir.display()
+                )));
+            }
+        }
+    }
+
+    podspec.name = config.name.unwrap_or_else(|| package.name.to_camel_case());
+    Ok(podspec)
+}
+
+/// Takes the first prose paragraph of the crate's README, skipping headings
+/// and badges.
+pub(crate) fn readme_excerpt(package: &Package) -> Option<String> {
+    let package_dir = package.manifest_path.parent().unwrap();
+    let readme_path = match &package.readme {
+        Some(readme) => package_dir.join(readme),
+        None => package_dir.join("README.md"),
+    };
+    let readme = std::fs::read_to_string(readme_path).ok()?;
+
+    let paragraph = readme.split("\n\n").map(str::trim).find(|block| {
+        !block.is_empty()
+            && !block.starts_with('#')
+            && !block.starts_with("![")
+            && !block.starts_with("[![")
+            && !block.starts_with("```")
+            && !block.starts_with('<')
+    })?;
+
+    Some(paragraph.to_string())
+}
+
+/// Finds the license file to reference from the podspec, relative to the pod
+/// root. Prefers the crate's `license-file`, falling back to any `LICENSE*`
+/// file in the pod root (which is what `bundle` ships).
+pub(crate) fn find_license_file(package: &Package) -> Option<String> {
+    let cur = std::fs::canonicalize(".").ok()?;
+
+    if let Some(path) = package.license_file() {
+        match std::fs::canonicalize(&path) {
+            Ok(path) => match path.strip_prefix(&cur) {
+                Ok(rel) => return Some(rel.to_string_lossy().to_string()),
+                Err(_) => log::warn!(
+                    "License file {} is outside the pod directory, ignoring.",
+                    path.display()
+                ),
+            },
+            Err(e) => log::warn!("Could not find license file {}: {}", path.display(), e),
+        }
+    }
+
+    let mut candidates = glob("LICENSE*")
+        .unwrap()
+        .filter_map(Result::ok)
+        .filter(|x| x.is_file())
+        .collect::<Vec<_>>();
+    candidates.sort();
+    candidates
+        .into_iter()
+        .next()
+        .map(|x| x.to_string_lossy().to_string())
+}
+
+/// Brings the podspec version in line with the crate version, or fails if
+/// `strict` is set and they differ.
+pub(crate) fn sync_podspec_version(
+    pod_root: &Path,
+    package: &Package,
+    strict: bool,
+) -> Result<(), Error> {
+    let config = crate::meta::config(package);
+    set_podspec_version(pod_root, &pod_version(package)?, strict, |current| {
+        crate::version::is_same_dev_build(current, &package.version, &config)
+    })
+}
+
+/// Sets the podspec's version to `expected`, unless it is already that or
+/// `is_current` accepts it.
+pub(crate) fn set_podspec_version(
+    pod_root: &Path,
+    expected: &str,
+    strict: bool,
+    is_current: impl Fn(&str) -> bool,
+) -> Result<(), Error> {
+    let podspec_path = match find_podspec(pod_root) {
+        Some(v) => v,
+        None => return Ok(()),
+    };
+
+    let spec = crate::fs::read_to_string(&podspec_path)?;
+    let current = match crate::podspec::spec_version(&spec) {
+        Some(v) => v,
+        None => {
+            log::warn!("No version found in {}.", podspec_path.display());
+            return Ok(());
+        }
+    };
+
+    if current == expected || is_current(current) {
+        return Ok(());
+    }
+
+    if strict {
+        return Err(Error::Check(format!(
+            "{} has version {} but the crate is at {}.",
+            podspec_path.display(),
+            current,
+            expected
+        )));
+    }
+
+    log::info!(
+        "Updating {} version from {} to {}",
+        podspec_path.display(),
+        current,
+        expected
+    );
+    let new_spec = crate::podspec::set_spec_version(&spec, expected).unwrap();
+    crate::fs::write(&podspec_path, new_spec)?;
+    Ok(())
+}
+
+pub(crate) fn update_podspec_sha256(pod_root: &Path, archive_path: &Path) -> Result<(), Error> {
+    match find_podspec(pod_root) {
+        Some(podspec_path) => update_podspec_sha256_at(&podspec_path, archive_path),
+        None => {
+            log::warn!("No podspec found; not updating source checksum.");
+            Ok(())
+        }
+    }
+}
+
+pub(crate) fn update_podspec_source_url(pod_root: &Path, url: &str) -> Result<(), Error> {
+    let podspec_path = match find_podspec(pod_root) {
+        Some(v) => v,
+        None => {
+            log::warn!("No podspec found; not updating source URL.");
+            return Ok(());
+        }
+    };
+    let spec = crate::fs::read_to_string(&podspec_path)?;
+    match crate::podspec::set_source_http(&spec, url) {
+        Some(new_spec) => {
+            log::info!(
+                "Setting source URL in {} to {}",
+                podspec_path.display(),
+                url
+            );
+            crate::fs::write(&podspec_path, new_spec)?;
+        }
+        None => log::warn!(
+            "Could not find the :http source in {}; not updating source URL.",
+            podspec_path.display()
+        ),
+    }
+    Ok(())
+}
+
+pub(crate) fn update_podspec_sha256_at(
+    podspec_path: &Path,
+    archive_path: &Path,
+) -> Result<(), Error> {
+    if crate::cmd::is_dry_run() {
+        log::info!(
+            "Would set the source sha256 in {} from {}",
+            podspec_path.display(),
+            archive_path.display()
+        );
+        return Ok(());
+    }
+    let sha256 = crate::checksum::sha256_file(archive_path).map_err(Error::io(archive_path))?;
+    let spec = crate::fs::read_to_string(podspec_path)?;
+
+    match crate::podspec::set_source_sha256(&spec, &sha256) {
+        Some(new_spec) => {
+            log::info!(
+                "Setting source sha256 in {} to {}",
+                podspec_path.display(),
+                sha256
+            );
+            crate::fs::write(podspec_path, new_spec)?;
+        }
+        None => {
+            log::warn!(
+                "Could not find the :http source in {}; not updating source checksum.",
+                podspec_path.display()
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Prints how the podspec at `path` differs from `generated`, returning
+/// whether it does.
+pub(crate) fn show_podspec_diff(path: &Path, existing: &str, generated: &str) -> bool {
+    let diff = crate::diff::unified(
+        &path.display().to_string(),
+        "generated",
+        existing,
+        generated,
+        console::colors_enabled(),
+    );
+    match diff {
+        Some(diff) => {
+            if !crate::message::is_json() {
+                print!("{}", diff);
+            }
+            true
+        }
+        None => false,
+    }
+}
+
+/// Fails if the podspec at `path` is missing or differs from `generated`.
+pub(crate) fn check_podspec(path: &Path, generated: &str) -> Result<(), Error> {
+    let existing = match std::fs::read_to_string(path) {
+        Ok(v) => v,
+        Err(_) => {
+            return Err(Error::Check(format!(
+                "{} not found; run `cargo pod init` to generate it.",
+                path.display()
+            )));
+        }
+    };
+    if show_podspec_diff(path, &existing, generated) {
+        return Err(Error::Check(format!(
+            "{} differs from the generated podspec.",
+            path.display()
+        )));
+    }
+    log::info!("{} is up to date.", path.display());
+    Ok(())
+}
+
+/// Logs the podspec changes a publish would make.
+pub(crate) fn log_dry_run_podspec(pod_root: &Path, archive_path: &Path, url: Option<&str>) {
+    let podspec_path = match find_podspec(pod_root) {
+        Some(v) => v,
+        None => {
+            log::info!("No podspec found; would not update it.");
+            return;
+        }
+    };
+    let sha256 = crate::checksum::sha256_file(archive_path).unwrap();
+    log::info!(
+        "Would set the source sha256 in {} to {}",
+        podspec_path.display(),
+        sha256
+    );
+    if let Some(url) = url {
+        log::info!("Would set the source URL to {}", url);
+    }
+    if pod_root.join(crate::package_swift::FILE_NAME).exists() {
+        let output_dir = archive_path.parent().unwrap_or_else(|| Path::new(""));
+        for zip in xcframework_zips(output_dir) {
+            log::info!(
+                "Would set the {} binary target checksum in {} to {}",
+                zip.display(),
+                crate::package_swift::FILE_NAME,
+                crate::checksum::sha256_file(&zip).unwrap()
+            );
+        }
+    }
+}
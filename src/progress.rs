@@ -0,0 +1,102 @@
+//! Spinners for the long phases of a build, so that a slow cargo or swiftc
+//! run does not look like a hang. When stderr is not a terminal, or with
+//! `--message-format json`, each step is logged instead.
+
+use std::time::{Duration, Instant};
+
+use indicatif::{HumanDuration, ProgressBar, ProgressStyle};
+
+/// Whether progress should be drawn rather than logged.
+pub(crate) fn is_interactive() -> bool {
+    atty::is(atty::Stream::Stderr) && !crate::message::is_json()
+}
+
+/// A phase made of `len` steps, such as building every triple.
+pub(crate) struct Phase {
+    name: &'static str,
+    bar: Option<ProgressBar>,
+    started: Instant,
+}
+
+impl Phase {
+    pub fn new(name: &'static str, len: u64) -> Phase {
+        let bar = if is_interactive() {
+            let bar = ProgressBar::new(len);
+            bar.set_style(
+                ProgressStyle::with_template(
+                    "{spinner} {prefix} [{pos}/{len}] {wide_msg} {elapsed:>4}",
+                )
+                .unwrap(),
+            );
+            bar.set_prefix(name);
+            bar.enable_steady_tick(Duration::from_millis(100));
+            Some(bar)
+        } else {
+            None
+        };
+        Phase {
+            name,
+            bar,
+            started: Instant::now(),
+        }
+    }
+
+    /// Runs one step, described by `message`, under the spinner.
+    pub fn step<T>(&self, message: &str, f: impl FnOnce() -> T) -> T {
+        self.run(message, false, f)
+    }
+
+    /// Like [`Phase::step`], for a step whose child process writes to the
+    /// terminal itself, such as cargo. The spinner is hidden while it runs.
+    pub fn step_with_output<T>(&self, message: &str, f: impl FnOnce() -> T) -> T {
+        self.run(message, true, f)
+    }
+
+    fn run<T>(&self, message: &str, has_output: bool, f: impl FnOnce() -> T) -> T {
+        let started = Instant::now();
+        let result = match &self.bar {
+            Some(bar) => {
+                bar.set_message(message.to_string());
+                let result = if has_output { bar.suspend(f) } else { f() };
+                bar.inc(1);
+                result
+            }
+            None => {
+                log::info!("{}...", message);
+                f()
+            }
+        };
+        let elapsed = started.elapsed();
+        crate::timings::step(self.name, message, elapsed);
+        log::debug!("{} took {}", message, HumanDuration(elapsed));
+        result
+    }
+
+    /// Times work that is part of the phase but not one of its steps, such
+    /// as copying files between them.
+    pub fn time<T>(&self, message: &str, f: impl FnOnce() -> T) -> T {
+        let started = Instant::now();
+        let result = f();
+        crate::timings::step(self.name, message, started.elapsed());
+        result
+    }
+
+    /// Clears the spinner and logs how long the phase took.
+    pub fn finish(mut self) {
+        if let Some(bar) = self.bar.take() {
+            bar.finish_and_clear();
+        }
+        let elapsed = self.started.elapsed();
+        crate::timings::phase(self.name, elapsed);
+        log::info!("{} finished in {}", self.name, HumanDuration(elapsed));
+    }
+}
+
+impl Drop for Phase {
+    fn drop(&mut self) {
+        // Clears the line if a step failed and the phase never finished.
+        if let Some(bar) = &self.bar {
+            bar.finish_and_clear();
+        }
+    }
+}
@@ -0,0 +1,137 @@
+use std::{collections::BTreeMap, path::Path, time::Instant};
+
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use serde::Deserialize;
+
+use super::http::{self, Auth, Error};
+
+/// Characters left unescaped in property keys and values, which are sent as
+/// `;key=value` matrix parameters.
+const PROPERTY: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'_')
+    .remove(b'.')
+    .remove(b'+');
+
+#[derive(Debug, Deserialize)]
+struct StorageInfo {
+    /// Artifactory reports sizes as strings.
+    size: String,
+    checksums: Checksums,
+}
+
+#[derive(Debug, Deserialize)]
+struct Checksums {
+    sha256: Option<String>,
+}
+
+/// Deploys artifacts to a repository on a JFrog Artifactory instance.
+pub struct Client {
+    http: reqwest::Client,
+    auth: Auth,
+    base_url: String,
+    repository: String,
+}
+
+impl Client {
+    /// `base_url` is the Artifactory root, e.g.
+    /// `https://example.jfrog.io/artifactory`.
+    pub fn new(base_url: &str, repository: String, auth: Auth) -> Client {
+        let http = reqwest::Client::builder()
+            .user_agent("cargo-cocoapods")
+            .build()
+            .unwrap();
+        Client {
+            http,
+            auth,
+            base_url: base_url.trim_end_matches('/').to_string(),
+            repository,
+        }
+    }
+
+    /// Where the artifact at `path` in the repository can be downloaded.
+    pub fn download_url(&self, path: &str) -> String {
+        format!("{}/{}/{}", self.base_url, self.repository, path)
+    }
+
+    /// Deploys `file` to `path`, attaching `properties` to the artifact.
+    /// Artifactory rejects the upload if it doesn't match the sha256 sent
+    /// along with it.
+    pub async fn deploy(
+        &self,
+        path: &str,
+        file: &Path,
+        content_type: &str,
+        properties: &BTreeMap<String, String>,
+    ) -> Result<(), Error> {
+        let len = std::fs::metadata(file)
+            .map_err(|e| Error::Io(file.to_path_buf(), e))?
+            .len();
+        let sha256 =
+            crate::checksum::sha256_file(file).map_err(|e| Error::Io(file.to_path_buf(), e))?;
+        let mut url = self.download_url(path);
+        for (key, value) in properties {
+            url.push_str(&format!(
+                ";{}={}",
+                utf8_percent_encode(key, PROPERTY),
+                utf8_percent_encode(value, PROPERTY)
+            ));
+        }
+
+        log::info!(
+            "Deploying {} ({}) to {}/{}...",
+            file.display(),
+            crate::bundle::format_size(len),
+            self.repository,
+            path
+        );
+        let progress = http::upload_progress_bar(len);
+        let started = Instant::now();
+        let result = http::send(|| {
+            self.auth
+                .apply(self.http.put(&url))
+                .header("content-type", content_type)
+                .header("content-length", len)
+                .header("x-checksum-sha256", &sha256)
+                .body(http::file_body(file, &progress).unwrap())
+        })
+        .await;
+        progress.finish_and_clear();
+        result?;
+
+        let elapsed = started.elapsed();
+        log::info!(
+            "Deployed {} in {:.1}s ({}/s)",
+            path,
+            elapsed.as_secs_f64(),
+            crate::bundle::format_size((len as f64 / elapsed.as_secs_f64().max(0.001)) as u64)
+        );
+        Ok(())
+    }
+
+    /// Checks the stored artifact's size and sha256 against `file` using the
+    /// storage API.
+    pub async fn verify(&self, path: &str, file: &Path) -> Result<(), String> {
+        let len = std::fs::metadata(file).map_err(|e| e.to_string())?.len();
+        let url = format!("{}/api/storage/{}/{}", self.base_url, self.repository, path);
+        let info: StorageInfo = http::send_json(|| self.auth.apply(self.http.get(&url)))
+            .await
+            .map_err(|e| format!("failed to fetch artifact info: {}", e))?;
+        if info.size.parse::<u64>().ok() != Some(len) {
+            return Err(format!(
+                "artifact is {} bytes but {} is {} bytes",
+                info.size,
+                file.display(),
+                len
+            ));
+        }
+        if let Some(actual) = info.checksums.sha256 {
+            let expected = crate::checksum::sha256_file(file).map_err(|e| e.to_string())?;
+            if actual != expected {
+                return Err(format!("sha256 is {} but expected {}", actual, expected));
+            }
+        }
+        log::debug!("Verified {}", path);
+        Ok(())
+    }
+}
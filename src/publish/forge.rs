@@ -0,0 +1,368 @@
+use std::{path::Path, time::Instant};
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    http::{self, Error},
+    Provider,
+};
+
+pub const GITHUB_HOST: &str = "github.com";
+
+static SCP_RE: Lazy<Regex> = regex_static::lazy_regex!(r"^(?:[^@/]+@)?([^:/]+):(.+)$");
+static URL_RE: Lazy<Regex> =
+    regex_static::lazy_regex!(r"^(?:https?|ssh|git)://(?:[^@/]+@)?([^/:]+)(?::\d+)?/(.+)$");
+
+/// A repository on a GitHub or Gitea/Forgejo host.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Repo {
+    pub host: String,
+    pub owner: String,
+    pub name: String,
+}
+
+impl Repo {
+    /// Parses `https://`, `ssh://` and scp-style (`git@host:owner/repo.git`)
+    /// remote URLs.
+    pub fn from_url(url: &str) -> Option<Repo> {
+        let captures = URL_RE.captures(url).or_else(|| SCP_RE.captures(url))?;
+        let host = captures[1].to_string();
+        let path = captures[2].trim_end_matches('/');
+        let path = path.strip_suffix(".git").unwrap_or(path);
+        let (owner, name) = path.split_once('/')?;
+        if owner.is_empty() || name.is_empty() || name.contains('/') {
+            return None;
+        }
+        Some(Repo {
+            host,
+            owner: owner.to_string(),
+            name: name.to_string(),
+        })
+    }
+
+    /// `owner/name`, as used in API paths.
+    pub fn tail(&self) -> String {
+        format!("{}/{}", self.owner, self.name)
+    }
+
+    /// The REST API root for the repo's host, with a trailing slash.
+    pub fn api_url(&self, provider: Provider) -> String {
+        match provider {
+            Provider::Gitea => format!("https://{}/api/v1/", self.host),
+            _ if self.host == GITHUB_HOST => "https://api.github.com/".into(),
+            _ => format!("https://{}/api/v3/", self.host),
+        }
+    }
+}
+
+/// Finds a token for `host` from the environment or, for GitHub, the `gh`
+/// CLI.
+pub fn token_fallback(provider: Provider, host: &str) -> Option<String> {
+    let vars: &[&str] = match provider {
+        Provider::Gitea => &["GITEA_TOKEN", "FORGEJO_TOKEN"],
+        _ if host == GITHUB_HOST => &["GITHUB_TOKEN", "GH_TOKEN"],
+        _ => &[
+            "GH_ENTERPRISE_TOKEN",
+            "GITHUB_ENTERPRISE_TOKEN",
+            "GITHUB_TOKEN",
+            "GH_TOKEN",
+        ],
+    };
+    for var in vars {
+        if let Ok(v) = std::env::var(var) {
+            if !v.trim().is_empty() {
+                log::debug!("Using token from {}", var);
+                return Some(v.trim().to_string());
+            }
+        }
+    }
+
+    if provider == Provider::Gitea {
+        return None;
+    }
+
+    let mut cmd = std::process::Command::new("gh");
+    cmd.args(["auth", "token", "--hostname", host]);
+    let output = crate::cmd::output(&mut cmd).ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let token = String::from_utf8(output.stdout).ok()?.trim().to_string();
+    if token.is_empty() {
+        return None;
+    }
+    log::debug!("Using GitHub token from `gh auth token`");
+    Some(token)
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Release {
+    pub id: u64,
+    pub tag_name: String,
+    pub html_url: String,
+    #[serde(default)]
+    pub upload_url: Option<String>,
+    #[serde(default)]
+    pub draft: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ReleaseAsset {
+    pub id: u64,
+    pub name: String,
+    pub size: u64,
+    /// Only reported by GitHub.
+    #[serde(default)]
+    pub state: Option<String>,
+    /// Only reported by GitHub, as `sha256:<hex>`.
+    #[serde(default)]
+    pub digest: Option<String>,
+    pub browser_download_url: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ReleaseRequest {
+    pub tag_name: String,
+    pub draft: bool,
+    pub prerelease: bool,
+    pub make_latest: String,
+}
+
+/// A client for the release APIs of GitHub and Gitea/Forgejo, which share
+/// their shapes but differ in endpoints and how assets are uploaded.
+pub struct Client {
+    http: reqwest::Client,
+    api_url: String,
+    repo: Repo,
+    provider: Provider,
+}
+
+impl Client {
+    pub fn new(provider: Provider, repo: Repo, api_url: String, token: &str) -> Client {
+        let mut header_map = reqwest::header::HeaderMap::new();
+        let mut auth_value =
+            reqwest::header::HeaderValue::from_str(format!("token {}", token).as_str()).unwrap();
+        auth_value.set_sensitive(true);
+        header_map.insert(reqwest::header::AUTHORIZATION, auth_value);
+        header_map.insert(
+            "user-agent",
+            reqwest::header::HeaderValue::from_static("cargo-cocoapods"),
+        );
+        let http = reqwest::Client::builder()
+            .default_headers(header_map)
+            .build()
+            .unwrap();
+
+        Client {
+            http,
+            api_url,
+            repo,
+            provider,
+        }
+    }
+
+    fn releases_url(&self) -> String {
+        format!("{}repos/{}/releases", self.api_url, self.repo.tail())
+    }
+
+    fn asset_url(&self, release: &Release, asset_id: u64) -> String {
+        match self.provider {
+            Provider::Gitea => {
+                format!("{}/{}/assets/{}", self.releases_url(), release.id, asset_id)
+            }
+            _ => format!("{}/assets/{}", self.releases_url(), asset_id),
+        }
+    }
+
+    /// Fetches every release of the repo, following `Link` header pagination.
+    pub async fn list_releases(&self) -> Result<Vec<Release>, Error> {
+        let page_size = match self.provider {
+            Provider::Gitea => "limit=50",
+            _ => "per_page=100",
+        };
+        let mut releases = vec![];
+        let mut next_url = Some(format!("{}?{}", self.releases_url(), page_size));
+        while let Some(url) = next_url {
+            let response = http::send(|| self.http.get(&url)).await?;
+            next_url = http::next_page_url(response.headers());
+            let page: Vec<Release> = response.json().await?;
+            releases.extend(page);
+        }
+        Ok(releases)
+    }
+
+    /// Fetches every asset of `release`, following pagination.
+    pub async fn list_assets(&self, release: &Release) -> Result<Vec<ReleaseAsset>, Error> {
+        let page_size = match self.provider {
+            Provider::Gitea => "limit=50",
+            _ => "per_page=100",
+        };
+        let mut assets = vec![];
+        let mut next_url = Some(format!(
+            "{}/{}/assets?{}",
+            self.releases_url(),
+            release.id,
+            page_size
+        ));
+        while let Some(url) = next_url {
+            let response = http::send(|| self.http.get(&url)).await?;
+            next_url = http::next_page_url(response.headers());
+            let page: Vec<ReleaseAsset> = response.json().await?;
+            assets.extend(page);
+        }
+        Ok(assets)
+    }
+
+    /// The sha256 of an uploaded asset, from its reported digest if there is
+    /// one, or by downloading it.
+    pub async fn asset_sha256(
+        &self,
+        release: &Release,
+        asset: &ReleaseAsset,
+    ) -> Result<String, String> {
+        if let Some(sha256) = asset
+            .digest
+            .as_deref()
+            .and_then(|x| x.strip_prefix("sha256:"))
+        {
+            return Ok(sha256.to_string());
+        }
+
+        log::info!("Downloading {} to compute its checksum...", asset.name);
+        let download_url = match self.provider {
+            Provider::Gitea => asset.browser_download_url.clone(),
+            _ => self.asset_url(release, asset.id),
+        };
+        let response = http::send(|| {
+            self.http
+                .get(&download_url)
+                .header(reqwest::header::ACCEPT, "application/octet-stream")
+        })
+        .await
+        .map_err(|e| format!("failed to download asset: {}", e))?;
+        crate::interrupt::until_interrupted(crate::checksum::sha256_stream(response.bytes_stream()))
+            .await
+            .map_err(|e| e.to_string())?
+            .map_err(|e| format!("failed to download asset: {}", e))
+    }
+
+    pub async fn delete_release(&self, release: &Release) -> Result<(), Error> {
+        let url = format!("{}/{}", self.releases_url(), release.id);
+        http::send(|| self.http.delete(&url)).await?;
+        Ok(())
+    }
+
+    pub async fn create_release(&self, request: &ReleaseRequest) -> Result<Release, Error> {
+        let url = self.releases_url();
+        http::send_json(|| self.http.post(&url).json(request)).await
+    }
+
+    /// Streams `path` to the release as an asset, showing progress.
+    pub async fn upload_asset(
+        &self,
+        release: &Release,
+        path: &Path,
+        content_type: &str,
+    ) -> Result<ReleaseAsset, Error> {
+        let asset_name = path.file_name().unwrap().to_string_lossy().to_string();
+        let len = std::fs::metadata(path)
+            .map_err(|e| Error::Io(path.to_path_buf(), e))?
+            .len();
+
+        // GitHub uploads go to a separate host, given as a URI template.
+        let upload_url = match (self.provider, release.upload_url.as_deref()) {
+            (Provider::GitHub, Some(url)) => url.split('{').next().unwrap().to_string(),
+            _ => format!("{}/{}/assets", self.releases_url(), release.id),
+        };
+
+        log::info!(
+            "Uploading {} ({})...",
+            asset_name,
+            crate::bundle::format_size(len)
+        );
+        let progress = http::upload_progress_bar(len);
+        let started = Instant::now();
+        let result = http::send(|| {
+            let request = self.http.post(&upload_url).query(&[("name", &asset_name)]);
+            let body = http::file_body(path, &progress).unwrap();
+            match self.provider {
+                Provider::Gitea => {
+                    let part = reqwest::multipart::Part::stream_with_length(body, len)
+                        .file_name(asset_name.clone())
+                        .mime_str(content_type)
+                        .unwrap();
+                    request.multipart(reqwest::multipart::Form::new().part("attachment", part))
+                }
+                _ => request
+                    .header("content-type", content_type)
+                    .header("content-length", len)
+                    .body(body),
+            }
+        })
+        .await;
+        progress.finish_and_clear();
+        let asset: ReleaseAsset = result?.json().await?;
+
+        let elapsed = started.elapsed();
+        log::info!(
+            "Uploaded {} in {:.1}s ({}/s)",
+            asset_name,
+            elapsed.as_secs_f64(),
+            crate::bundle::format_size((len as f64 / elapsed.as_secs_f64().max(0.001)) as u64)
+        );
+        Ok(asset)
+    }
+
+    pub async fn delete_asset(&self, release: &Release, asset: &ReleaseAsset) -> Result<(), Error> {
+        let url = self.asset_url(release, asset.id);
+        http::send(|| self.http.delete(&url)).await?;
+        Ok(())
+    }
+
+    /// Checks the uploaded asset's reported state and size against the local
+    /// file, and optionally its sha256 by downloading it again.
+    pub async fn verify_asset(
+        &self,
+        release: &Release,
+        uploaded: &ReleaseAsset,
+        path: &Path,
+        verify_hash: bool,
+    ) -> Result<(), String> {
+        let len = std::fs::metadata(path).map_err(|e| e.to_string())?.len();
+        let url = self.asset_url(release, uploaded.id);
+        let asset: ReleaseAsset = http::send_json(|| self.http.get(&url))
+            .await
+            .map_err(|e| format!("failed to fetch asset metadata: {}", e))?;
+        if let Some(state) = asset.state.as_deref() {
+            if state != "uploaded" {
+                return Err(format!("asset state is {:?}", state));
+            }
+        }
+        if asset.size != len {
+            return Err(format!(
+                "asset is {} bytes but {} is {} bytes",
+                asset.size,
+                path.display(),
+                len
+            ));
+        }
+
+        if verify_hash {
+            let expected = crate::checksum::sha256_file(path).map_err(|e| e.to_string())?;
+            // Always download, rather than trusting the server's digest.
+            let downloaded = ReleaseAsset {
+                digest: None,
+                ..asset
+            };
+            let actual = self.asset_sha256(release, &downloaded).await?;
+            if actual != expected {
+                return Err(format!("sha256 is {} but expected {}", actual, expected));
+            }
+        }
+
+        log::debug!("Verified {}", uploaded.name);
+        Ok(())
+    }
+}
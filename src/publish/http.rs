@@ -0,0 +1,207 @@
+use std::{
+    io,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use futures_util::TryStreamExt;
+use indicatif::{ProgressBar, ProgressStyle};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use tokio_util::io::ReaderStream;
+
+static NEXT_LINK_RE: Lazy<Regex> = regex_static::lazy_regex!(r#"<([^>]+)>;\s*rel="next""#);
+
+/// Extracts the `rel="next"` URL from a paginated response's `Link` header.
+pub fn next_page_url(headers: &reqwest::header::HeaderMap) -> Option<String> {
+    let link = headers.get(reqwest::header::LINK)?.to_str().ok()?;
+    NEXT_LINK_RE
+        .captures(link)
+        .map(|c| c.get(1).unwrap().as_str().to_string())
+}
+
+/// Credentials sent with every request.
+pub enum Auth {
+    None,
+    Basic(String, Option<String>),
+    Bearer(String),
+    /// A custom header, such as Artifactory's `X-JFrog-Art-Api`.
+    Header(&'static str, String),
+}
+
+impl Auth {
+    pub fn apply(&self, request: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        match self {
+            Auth::None => request,
+            Auth::Basic(username, password) => request.basic_auth(username, password.as_ref()),
+            Auth::Bearer(token) => request.bearer_auth(token),
+            Auth::Header(name, value) => {
+                let mut value = reqwest::header::HeaderValue::from_str(value).unwrap();
+                value.set_sensitive(true);
+                request.header(*name, value)
+            }
+        }
+    }
+}
+
+const MAX_ATTEMPTS: u32 = 5;
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+const MAX_RATE_LIMIT_WAIT: Duration = Duration::from_secs(15 * 60);
+
+#[derive(Debug)]
+pub enum Error {
+    Http(reqwest::Error),
+    Status(reqwest::StatusCode, String),
+    Io(PathBuf, io::Error),
+    Interrupted,
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Http(e) => write!(f, "{}", e),
+            Error::Status(status, body) => write!(f, "{}: {}", status, body),
+            Error::Io(path, e) => write!(f, "{}: {}", path.display(), e),
+            Error::Interrupted => f.write_str("interrupted"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<reqwest::Error> for Error {
+    fn from(e: reqwest::Error) -> Self {
+        Error::Http(e)
+    }
+}
+
+/// How long to wait before retrying a rate limited response, if it was one.
+fn rate_limit_wait(response: &reqwest::Response) -> Option<Duration> {
+    let status = response.status();
+    if status != reqwest::StatusCode::FORBIDDEN && status != reqwest::StatusCode::TOO_MANY_REQUESTS
+    {
+        return None;
+    }
+    let header = |name: &str| {
+        response
+            .headers()
+            .get(name)
+            .and_then(|x| x.to_str().ok())
+            .and_then(|x| x.parse::<u64>().ok())
+    };
+
+    // Secondary rate limits send `retry-after`; primary ones exhaust the
+    // quota and give the reset time as a unix timestamp.
+    if let Some(seconds) = header("retry-after") {
+        return Some(Duration::from_secs(seconds));
+    }
+    if header("x-ratelimit-remaining") == Some(0) {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        let reset = header("x-ratelimit-reset").unwrap_or(now + 60);
+        return Some(Duration::from_secs(reset.saturating_sub(now) + 1));
+    }
+    if status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+        return Some(Duration::from_secs(60));
+    }
+    None
+}
+
+/// Sends the request built by `make_request`, retrying transient failures
+/// (connection errors, 5xx and rate limits) with exponential backoff.
+///
+/// Non-success responses that aren't worth retrying are returned as
+/// `Error::Status`.
+pub async fn send<F>(make_request: F) -> Result<reqwest::Response, Error>
+where
+    F: Fn() -> reqwest::RequestBuilder,
+{
+    let mut backoff = Duration::from_secs(1);
+    let mut attempt = 1;
+    loop {
+        let result = crate::interrupt::until_interrupted(make_request().send())
+            .await
+            .map_err(|_| Error::Interrupted)?;
+        let wait = match result {
+            Ok(response) if response.status().is_success() => return Ok(response),
+            Ok(response) => {
+                let status = response.status();
+                let wait = match rate_limit_wait(&response) {
+                    Some(wait) if wait <= MAX_RATE_LIMIT_WAIT => {
+                        log::warn!("Rate limited; waiting {}s", wait.as_secs());
+                        Some(wait)
+                    }
+                    Some(_) => None,
+                    None if status.is_server_error() => {
+                        log::warn!("Server responded with {}", status);
+                        Some(backoff)
+                    }
+                    None => None,
+                };
+                match wait {
+                    Some(wait) if attempt < MAX_ATTEMPTS => wait,
+                    _ => {
+                        let body = response.text().await.unwrap_or_default();
+                        return Err(Error::Status(status, body));
+                    }
+                }
+            }
+            Err(e) if attempt < MAX_ATTEMPTS && (e.is_connect() || e.is_timeout()) => {
+                log::warn!("Request failed: {}", e);
+                backoff
+            }
+            Err(e) => return Err(e.into()),
+        };
+
+        log::info!(
+            "Retrying in {}s (attempt {} of {})",
+            wait.as_secs(),
+            attempt + 1,
+            MAX_ATTEMPTS
+        );
+        crate::interrupt::until_interrupted(tokio::time::sleep(wait))
+            .await
+            .map_err(|_| Error::Interrupted)?;
+        backoff = (backoff * 2).min(MAX_BACKOFF);
+        attempt += 1;
+    }
+}
+
+/// Like `send`, but decodes a JSON response body.
+pub async fn send_json<T, F>(make_request: F) -> Result<T, Error>
+where
+    T: serde::de::DeserializeOwned,
+    F: Fn() -> reqwest::RequestBuilder,
+{
+    Ok(send(make_request).await?.json().await?)
+}
+
+/// Streams the file at `path` as a request body, advancing `progress` as
+/// chunks are sent.
+pub fn file_body(path: &Path, progress: &ProgressBar) -> io::Result<reqwest::Body> {
+    let file = tokio::fs::File::from_std(std::fs::File::open(path)?);
+    progress.set_position(0);
+    let progress = progress.clone();
+    let stream = ReaderStream::new(file).inspect_ok(move |chunk| {
+        progress.inc(chunk.len() as u64);
+    });
+    Ok(reqwest::Body::wrap_stream(stream))
+}
+
+/// A bar for uploading `len` bytes, hidden when the progress would not be
+/// drawn on a terminal.
+pub fn upload_progress_bar(len: u64) -> ProgressBar {
+    if !crate::progress::is_interactive() {
+        return ProgressBar::hidden();
+    }
+    let progress = ProgressBar::new(len);
+    progress.set_style(
+        ProgressStyle::with_template(
+            "{bar:40} {bytes}/{total_bytes} ({bytes_per_sec}, {elapsed} elapsed, {eta} remaining)",
+        )
+        .unwrap(),
+    );
+    progress
+}
@@ -0,0 +1,24 @@
+use std::{
+    io,
+    path::{Path, PathBuf},
+};
+
+/// Copies `file` to `key` under `root`, creating directories as needed, and
+/// returns the destination.
+pub fn copy(root: &Path, key: &str, file: &Path) -> io::Result<PathBuf> {
+    let dest = root.join(key);
+    if let Some(parent) = dest.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    log::info!("Copying {} to {}", file.display(), dest.display());
+    std::fs::copy(file, &dest)?;
+    Ok(dest)
+}
+
+/// A `file://` URL for `path`, which is made absolute first.
+pub fn file_url(path: &Path) -> String {
+    let path = std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    reqwest::Url::from_file_path(&path)
+        .map(|x| x.to_string())
+        .unwrap_or_else(|_| format!("file://{}", path.display()))
+}
@@ -0,0 +1,1175 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use cargo_metadata::Package;
+use serde::Deserialize;
+
+use crate::{
+    error::Error,
+    meta::PublishConfig,
+    pod::{
+        bundle_output_path, derive_manifest, find_podspec, git_commit_paths, pod_manifest,
+        pod_name, pod_version, resolve_dist_dir, resolve_pod_root, run_hook, xcframework_zips,
+        SPLIT_PLATFORMS,
+    },
+    podspec::{log_dry_run_podspec, update_podspec_sha256, update_podspec_source_url},
+};
+use forge::{Client as ForgeClient, Release, ReleaseAsset, ReleaseRequest, Repo};
+use http::Auth;
+
+pub(crate) mod artifactory;
+pub(crate) mod forge;
+pub(crate) mod http;
+pub(crate) mod local;
+pub(crate) mod put;
+pub(crate) mod s3;
+pub(crate) mod ssh;
+
+/// Where `cargo pod publish` sends releases.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Provider {
+    GitHub,
+    #[serde(alias = "forgejo")]
+    Gitea,
+    S3,
+    /// A plain `PUT` to a URL, e.g. a Nexus raw repository or WebDAV share.
+    #[serde(alias = "webdav")]
+    Http,
+    Artifactory,
+    /// Copies to a host with rsync or scp.
+    Ssh,
+    /// Copies into a local directory, for testing the release flow.
+    Local,
+}
+
+impl Provider {
+    /// Guesses the provider from a git remote's host.
+    pub fn infer(host: &str) -> Provider {
+        let host = host.to_ascii_lowercase();
+        if host == "codeberg.org" || host.contains("gitea") || host.contains("forgejo") {
+            Provider::Gitea
+        } else {
+            Provider::GitHub
+        }
+    }
+}
+
+impl FromStr for Provider {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "github" => Ok(Provider::GitHub),
+            "gitea" | "forgejo" => Ok(Provider::Gitea),
+            "s3" => Ok(Provider::S3),
+            "http" | "webdav" => Ok(Provider::Http),
+            "artifactory" => Ok(Provider::Artifactory),
+            "ssh" => Ok(Provider::Ssh),
+            "local" => Ok(Provider::Local),
+            other => Err(format!("unsupported provider: {}", other)),
+        }
+    }
+}
+
+/// Fills in `{name}`, `{version}` and `{file}` in an object key template.
+pub fn render_key(template: &str, name: &str, version: &str, file: &str) -> String {
+    template
+        .replace("{name}", name)
+        .replace("{version}", version)
+        .replace("{file}", file)
+        .trim_start_matches('/')
+        .to_string()
+}
+
+/// Publishes the bundled pod, like `cargo pod publish`.
+#[derive(Debug, Clone, Default)]
+pub struct Publisher {
+    pub(crate) manifest_path: Option<PathBuf>,
+    pub(crate) token: Option<String>,
+    pub(crate) url: Option<String>,
+    pub(crate) provider: Option<Provider>,
+    pub(crate) api_url: Option<String>,
+    pub(crate) tag: Option<String>,
+    pub(crate) force: bool,
+    pub(crate) draft: bool,
+    pub(crate) sign_tag: bool,
+    pub(crate) verify_tag: bool,
+    pub(crate) prerelease: bool,
+    pub(crate) channel: Option<String>,
+    pub(crate) file: Option<PathBuf>,
+    pub(crate) verify_hash: bool,
+    pub(crate) path: Option<PathBuf>,
+    pub(crate) dest: Option<String>,
+    pub(crate) dry_run: bool,
+    pub(crate) commit: bool,
+}
+
+impl Publisher {
+    pub fn new() -> Publisher {
+        Default::default()
+    }
+
+    /// The crate's manifest; found from the current directory if not given.
+    pub fn manifest_path(mut self, path: impl Into<PathBuf>) -> Publisher {
+        self.manifest_path = Some(path.into());
+        self
+    }
+
+    /// The access token; read from the environment if not given.
+    pub fn token(mut self, token: impl Into<String>) -> Publisher {
+        self.token = Some(token.into());
+        self
+    }
+
+    /// The repository URL; the `origin` remote if not given.
+    pub fn url(mut self, url: impl Into<String>) -> Publisher {
+        self.url = Some(url.into());
+        self
+    }
+
+    /// Where to publish; inferred from the repository host if not given.
+    pub fn provider(mut self, provider: Provider) -> Publisher {
+        self.provider = Some(provider);
+        self
+    }
+
+    /// The API URL; derived from the repository host if not given.
+    pub fn api_url(mut self, url: impl Into<String>) -> Publisher {
+        self.api_url = Some(url.into());
+        self
+    }
+
+    /// The release tag; `v{version}` if not given.
+    pub fn tag(mut self, tag: impl Into<String>) -> Publisher {
+        self.tag = Some(tag.into());
+        self
+    }
+
+    /// Replaces a release that already exists for the tag.
+    pub fn force(mut self, force: bool) -> Publisher {
+        self.force = force;
+        self
+    }
+
+    /// Creates and pushes a signed tag if it does not exist yet, and
+    /// verifies it.
+    pub fn sign_tag(mut self, sign_tag: bool) -> Publisher {
+        self.sign_tag = sign_tag;
+        self
+    }
+
+    /// Requires a valid signature on the release tag.
+    pub fn verify_tag(mut self, verify_tag: bool) -> Publisher {
+        self.verify_tag = verify_tag;
+        self
+    }
+
+    /// The release channel, such as `beta`, which the tag must carry as a
+    /// prerelease suffix.
+    pub fn channel(mut self, channel: impl Into<String>) -> Publisher {
+        self.channel = Some(channel.into());
+        self
+    }
+
+    /// The archive to upload; the bundle's output if not given.
+    pub fn file(mut self, file: impl Into<PathBuf>) -> Publisher {
+        self.file = Some(file.into());
+        self
+    }
+
+    /// Re-downloads each asset after upload and compares its sha256.
+    pub fn verify_hash(mut self, verify_hash: bool) -> Publisher {
+        self.verify_hash = verify_hash;
+        self
+    }
+
+    /// The directory to publish into with [`Provider::Local`].
+    pub fn path(mut self, path: impl Into<PathBuf>) -> Publisher {
+        self.path = Some(path.into());
+        self
+    }
+
+    /// The destination for [`Provider::Ssh`], like `user@host:/var/www/pods/`.
+    pub fn dest(mut self, dest: impl Into<String>) -> Publisher {
+        self.dest = Some(dest.into());
+        self
+    }
+
+    pub fn draft(mut self, draft: bool) -> Publisher {
+        self.draft = draft;
+        self
+    }
+
+    pub fn prerelease(mut self, prerelease: bool) -> Publisher {
+        self.prerelease = prerelease;
+        self
+    }
+
+    /// Logs what would be published without uploading or changing anything.
+    pub fn dry_run(mut self, dry_run: bool) -> Publisher {
+        self.dry_run = dry_run;
+        self
+    }
+
+    /// Commits the podspec once it points at the published archive.
+    pub fn commit(mut self, commit: bool) -> Publisher {
+        self.commit = commit;
+        self
+    }
+
+    pub async fn publish(self) -> Result<(), Error> {
+        // The flag is process-wide, so it is only set for this call.
+        let was_dry_run = crate::cmd::is_dry_run();
+        crate::cmd::set_dry_run(was_dry_run || self.dry_run);
+        let result = publish(&self).await;
+        crate::cmd::set_dry_run(was_dry_run);
+        result
+    }
+}
+
+/// Creates a git tag, signed and annotated if `sign` is set. Whether gpg or
+/// ssh is used follows git's `gpg.format` configuration.
+pub(crate) fn create_tag(tag: &str, sign: bool) -> Result<(), Error> {
+    let created = if sign {
+        let mut cmd = std::process::Command::new("git");
+        cmd.args(["tag", "-s", "-m", tag, tag]);
+        crate::cmd::skip(&cmd)
+            || crate::cmd::status(&mut cmd)
+                .map_err(|e| Error::tool("git", e.to_string()))?
+                .success()
+    } else {
+        crate::git::open()
+            .and_then(|repo| crate::git::create_tag(&repo, tag))
+            .is_ok()
+    };
+    if !created {
+        return Err(Error::Git(format!("Failed to create tag {}", tag)));
+    }
+    Ok(())
+}
+
+pub(crate) fn tag_exists(tag: &str) -> bool {
+    crate::git::open()
+        .map(|repo| crate::git::tag_exists(&repo, tag))
+        .unwrap_or(false)
+}
+
+/// Checks the tag's signature with `git verify-tag`.
+pub(crate) fn verify_tag(tag: &str) -> bool {
+    let mut cmd = std::process::Command::new("git");
+    cmd.args(["verify-tag", tag]);
+    crate::cmd::skip(&cmd)
+        || crate::cmd::status(&mut cmd)
+            .map(|x| x.success())
+            .unwrap_or(false)
+}
+
+pub(crate) const DEFAULT_PUBLISH_KEY: &str = "{name}/{version}/{file}";
+
+/// `v{version}`, taking the version from the podspec, or from the crate if
+/// there is no podspec.
+pub(crate) fn default_tag(pod_root: &Path, package: &Package) -> Result<String, Error> {
+    let version = find_podspec(pod_root)
+        .and_then(|x| std::fs::read_to_string(x).ok())
+        .and_then(|x| crate::podspec::spec_version(&x).map(str::to_string));
+    let version = match version {
+        Some(v) => v,
+        None => pod_version(package)?,
+    };
+    let tag = format!("v{}", version);
+    log::info!("Using tag {} (pass --tag to override)", tag);
+    Ok(tag)
+}
+
+/// The prerelease channel of a tag such as `v1.2.0-beta.3`, i.e. `beta`.
+/// Tags with a purely numeric prerelease, like `v1.2.0-1`, are on the
+/// `pre` channel.
+pub(crate) fn tag_channel(tag: &str) -> Option<String> {
+    let version = semver::Version::parse(tag.trim_start_matches('v')).ok()?;
+    version.pre.first().map(|x| {
+        let channel = x
+            .to_string()
+            .trim_end_matches(|c: char| c.is_ascii_digit())
+            .to_string();
+        if channel.is_empty() {
+            "pre".into()
+        } else {
+            channel
+        }
+    })
+}
+
+pub(crate) async fn publish(options: &Publisher) -> Result<(), Error> {
+    let has_subtree = std::fs::read_dir("./crate").is_ok();
+    let (metadata, package, _targets) =
+        derive_manifest(pod_manifest(options.manifest_path.as_deref(), has_subtree))?;
+    let pod_root = resolve_pod_root(&resolve_dist_dir(&metadata, &package, has_subtree));
+    let config = crate::meta::config(&package);
+    let archive_path_for = |platform| {
+        bundle_output_path(
+            &pod_root,
+            &package,
+            options.file.as_deref(),
+            config.bundle.format.unwrap_or_default(),
+            config.bundle.compression.unwrap_or_default(),
+            platform,
+        )
+    };
+    let archive_path = archive_path_for(None)?;
+    // Written by `bundle --split-platforms`.
+    let mut platform_archive_paths = vec![];
+    for (platform, _, _) in SPLIT_PLATFORMS {
+        let path = archive_path_for(Some(platform))?;
+        if path.exists() {
+            platform_archive_paths.push((*platform, path));
+        }
+    }
+
+    let mut assets = vec![archive_path.clone()];
+    assets.extend(platform_archive_paths.iter().map(|(_, x)| x.clone()));
+
+    // Upload the checksum manifest (and its signature) written by `bundle`.
+    let output_dir = archive_path
+        .parent()
+        .map(Path::to_path_buf)
+        .unwrap_or_default();
+    let sums_path = output_dir.join(crate::checksum::SUMS_FILE_NAME);
+    let signature_path = output_dir.join(format!("{}.asc", crate::checksum::SUMS_FILE_NAME));
+    assets.extend(
+        [sums_path, signature_path]
+            .into_iter()
+            .filter(|x| x.exists()),
+    );
+    assets.extend(xcframework_zips(&output_dir));
+
+    if !archive_path.exists() {
+        return Err(Error::Usage(format!(
+            "No bundle found at {}; run `cargo pod bundle` first.",
+            archive_path.display()
+        )));
+    }
+    if options.dry_run {
+        log::info!("Dry run; nothing will be uploaded or changed.");
+    } else {
+        run_hook("pre_publish", config.hooks.pre_publish.as_deref(), &package)?;
+    }
+
+    let provider = options.provider.or(config.publish.provider);
+    let result = match provider {
+        Some(Provider::S3) => {
+            Some(publish_s3(options, &pod_root, &package, &config.publish, &assets).await)
+        }
+        Some(Provider::Http) => {
+            Some(publish_http(options, &pod_root, &package, &config.publish, &assets).await)
+        }
+        Some(Provider::Local) => Some(publish_local(
+            options,
+            &pod_root,
+            &package,
+            &config.publish,
+            &assets,
+        )),
+        Some(Provider::Ssh) => Some(publish_ssh(
+            options,
+            &pod_root,
+            &package,
+            &config.publish,
+            &assets,
+        )),
+        Some(Provider::Artifactory) => Some(
+            publish_artifactory(
+                options,
+                &pod_root,
+                &package,
+                &config.publish,
+                &assets,
+                &platform_archive_paths,
+            )
+            .await,
+        ),
+        _ => None,
+    };
+    if let Some(result) = result {
+        result?;
+        if options.dry_run {
+            return Ok(());
+        }
+        return run_hook(
+            "post_publish",
+            config.hooks.post_publish.as_deref(),
+            &package,
+        );
+    }
+
+    let tag = match options.tag.clone() {
+        Some(v) => v,
+        None => default_tag(&pod_root, &package)?,
+    };
+
+    let repo_url: String = if let Some(u) = options.url.clone() {
+        u
+    } else {
+        crate::git::open()
+            .ok()
+            .and_then(|repo| crate::git::remote_url(&repo, "origin"))
+            .unwrap_or_default()
+    };
+    log::trace!("Derived repo URL {:?}", repo_url);
+
+    let repo = match Repo::from_url(&repo_url) {
+        Some(v) => v,
+        None => {
+            return Err(Error::Usage(format!(
+                "Could not parse the repo url {:?}",
+                repo_url
+            )));
+        }
+    };
+    log::trace!("Derived repo {:?}", repo);
+    if options.dry_run {
+        log::info!("Repository: {}/{} on {}", repo.owner, repo.name, repo.host);
+        log::info!("Tag: {}", tag);
+    }
+
+    let provider = provider.unwrap_or_else(|| Provider::infer(&repo.host));
+    let api_url = options
+        .api_url
+        .clone()
+        .or_else(|| match provider {
+            Provider::GitHub => std::env::var("GITHUB_API_URL").ok(),
+            _ => None,
+        })
+        .map(|x| format!("{}/", x.trim_end_matches('/')))
+        .unwrap_or_else(|| repo.api_url(provider));
+    log::trace!("Using {:?} API URL {:?}", provider, api_url);
+
+    let token = match options
+        .token
+        .clone()
+        .or_else(|| crate::publish::forge::token_fallback(provider, &repo.host))
+    {
+        Some(v) => v,
+        None => {
+            return Err(Error::Usage(format!(
+                "You must provide an access token. {}",
+                match provider {
+                    Provider::GitHub => {
+                        "Pass --token, set GITHUB_TOKEN or GH_TOKEN, or log in with `gh auth login`."
+                    }
+                    _ => "Pass --token, or set GITEA_TOKEN or FORGEJO_TOKEN.",
+                }
+            )));
+        }
+    };
+
+    let client = ForgeClient::new(provider, repo, api_url, &token);
+
+    log::info!("Getting current releases...");
+
+    let current_releases = match client.list_releases().await {
+        Ok(v) => v,
+        Err(e) => {
+            return Err(Error::Network(format!("Failed to list releases: {}", e)));
+        }
+    };
+
+    let mut existing_release = None;
+    if let Some(release) = current_releases.into_iter().find(|r| r.tag_name == tag) {
+        if options.force && options.dry_run {
+            log::info!("Would delete the existing release {}", release.html_url);
+        } else if options.force {
+            log::info!("Deleting release...");
+            if let Err(e) = client.delete_release(&release).await {
+                return Err(Error::Network(format!(
+                    "Failed to delete release {}: {}",
+                    release.id, e
+                )));
+            }
+        } else {
+            existing_release = Some(release);
+        }
+    }
+    let would_sign_tag = options.sign_tag && options.dry_run && !tag_exists(&tag);
+    if would_sign_tag {
+        log::info!("Would create and push signed tag {}", tag);
+    } else if options.sign_tag && !tag_exists(&tag) {
+        log::info!("Creating signed tag {}...", tag);
+        create_tag(&tag, true)?;
+        let status = crate::cmd::status(
+            std::process::Command::new("git")
+                .args(["push", "origin"])
+                .arg(format!("refs/tags/{}", tag)),
+        );
+        if !status.map(|x| x.success()).unwrap_or(false) {
+            return Err(Error::Git(format!("Failed to push tag {}", tag)));
+        }
+    }
+    if (options.sign_tag || options.verify_tag) && !would_sign_tag {
+        if !tag_exists(&tag) {
+            return Err(Error::Git(format!(
+                "Tag {} does not exist locally, so its signature cannot be verified.",
+                tag
+            )));
+        }
+        if !verify_tag(&tag) {
+            return Err(Error::Check(format!(
+                "Tag {} does not have a valid signature.",
+                tag
+            )));
+        }
+        log::info!("Verified signature of tag {}", tag);
+    }
+
+    let channel = tag_channel(&tag);
+    if let Some(expected) = options.channel.as_deref() {
+        if channel.as_deref() != Some(expected) {
+            return Err(Error::Usage(format!(
+                "Tag {} is not on the {} channel; expected a tag like v1.0.0-{}.1",
+                tag, expected, expected
+            )));
+        }
+    }
+    let prerelease = options.prerelease || channel.is_some();
+    if let Some(channel) = channel.as_deref() {
+        log::info!("Publishing to the {} channel as a prerelease", channel);
+    }
+
+    if let Some(release) = existing_release {
+        return resume_release(options, &client, &release, &pod_root, &package, &assets).await;
+    }
+
+    let request = ReleaseRequest {
+        tag_name: tag,
+        draft: options.draft,
+        prerelease,
+        make_latest: (!prerelease).to_string(),
+    };
+    if options.dry_run {
+        log::info!(
+            "Would create a new {}release for {}",
+            match (request.draft, request.prerelease) {
+                (true, true) => "draft pre",
+                (true, false) => "draft ",
+                (false, true) => "pre",
+                (false, false) => "",
+            },
+            request.tag_name
+        );
+        log_dry_run_podspec(&pod_root, &archive_path, None);
+        for path in &assets {
+            log_dry_run_upload(path, "the release");
+        }
+        return Ok(());
+    }
+    if request.draft {
+        log::info!("Creating new draft release...");
+    } else {
+        log::info!("Creating new release...");
+    }
+    let release = match client.create_release(&request).await {
+        Ok(v) => v,
+        Err(e) => {
+            return Err(Error::Network(format!("Failed to create release: {}", e)));
+        }
+    };
+
+    let mut uploaded = vec![];
+    for path in &assets {
+        uploaded.push(upload_release_asset(&client, &release, path, options.verify_hash).await?);
+    }
+
+    // Draft assets are served from a temporary URL until the release is
+    // published, so only the checksum can be recorded now.
+    if request.draft {
+        update_published_podspec(options, &pod_root, &package, &archive_path, None)?;
+    } else {
+        update_published_podspec(
+            options,
+            &pod_root,
+            &package,
+            &archive_path,
+            Some(&uploaded[0].browser_download_url),
+        )?;
+    }
+
+    if request.draft {
+        log::info!(
+            "Draft release created; publish it when ready: {}",
+            release.html_url
+        );
+    } else {
+        log::info!("Published {}", release.html_url);
+        crate::message::published(&release.html_url);
+    }
+    run_hook(
+        "post_publish",
+        config.hooks.post_publish.as_deref(),
+        &package,
+    )
+}
+
+/// Uploads the bundle and its companions to an S3-compatible bucket and
+/// points the podspec at the archive's public URL.
+pub(crate) async fn publish_s3(
+    options: &Publisher,
+    pod_root: &Path,
+    package: &Package,
+    config: &PublishConfig,
+    assets: &[PathBuf],
+) -> Result<(), Error> {
+    let bucket = match &config.bucket {
+        Some(v) => v.clone(),
+        None => {
+            return Err(Error::Usage(
+                "Publishing to S3 requires `bucket` in [package.metadata.pod.publish].".into(),
+            ));
+        }
+    };
+    let key_template = config.key.as_deref().unwrap_or(DEFAULT_PUBLISH_KEY);
+    let name = pod_name(package);
+    let version = pod_version(package)?;
+    let key_for = |path: &Path| {
+        let file = path.file_name().unwrap().to_string_lossy();
+        crate::publish::render_key(key_template, &name, &version, &file)
+    };
+
+    let client = crate::publish::s3::Client::new(
+        bucket.clone(),
+        config.region.clone(),
+        config.endpoint.clone(),
+    )
+    .await;
+
+    let url = crate::publish::s3::public_url(
+        config.public_url.as_deref(),
+        &bucket,
+        config.region.as_deref(),
+        config.endpoint.as_deref(),
+        &key_for(&assets[0]),
+    );
+    if options.dry_run {
+        log_dry_run_podspec(pod_root, &assets[0], Some(&url));
+        for path in assets {
+            log_dry_run_upload(path, &format!("s3://{}/{}", bucket, key_for(path)));
+        }
+        return Ok(());
+    }
+
+    for path in assets {
+        let key = key_for(path);
+        if let Err(e) = client
+            .upload(&key, path, crate::bundle::content_type(path))
+            .await
+        {
+            return Err(Error::Network(format!(
+                "Failed to upload {}: {}",
+                path.display(),
+                e
+            )));
+        }
+    }
+
+    update_published_podspec(options, pod_root, package, &assets[0], Some(&url))?;
+    log::info!("Published {}", url);
+    crate::message::published(&url);
+    Ok(())
+}
+
+/// Uploads the bundle and its companions with `PUT` requests under the
+/// configured base URL and points the podspec at the archive.
+pub(crate) async fn publish_http(
+    options: &Publisher,
+    pod_root: &Path,
+    package: &Package,
+    config: &PublishConfig,
+    assets: &[PathBuf],
+) -> Result<(), Error> {
+    let base_url = match &config.url {
+        Some(v) => v.trim_end_matches('/').to_string(),
+        None => {
+            return Err(Error::Usage(
+                "Publishing over HTTP requires `url` in [package.metadata.pod.publish].".into(),
+            ));
+        }
+    };
+    let env = |name: &str| std::env::var(name).ok().filter(|x| !x.trim().is_empty());
+    let auth = match (
+        options
+            .token
+            .clone()
+            .or_else(|| env("CARGO_POD_PUBLISH_TOKEN")),
+        env("CARGO_POD_PUBLISH_USERNAME").or_else(|| config.username.clone()),
+    ) {
+        (Some(token), _) => Auth::Bearer(token),
+        (None, Some(username)) => Auth::Basic(username, env("CARGO_POD_PUBLISH_PASSWORD")),
+        (None, None) => Auth::None,
+    };
+    let key_template = config.key.as_deref().unwrap_or(DEFAULT_PUBLISH_KEY);
+    let name = pod_name(package);
+    let version = pod_version(package)?;
+    let key_for = |path: &Path| {
+        let file = path.file_name().unwrap().to_string_lossy();
+        crate::publish::render_key(key_template, &name, &version, &file)
+    };
+
+    let client = crate::publish::put::Client::new(auth);
+
+    let key = key_for(&assets[0]);
+    let url = match config.public_url.as_deref() {
+        Some(template) => template.replace("{key}", &key),
+        None => format!("{}/{}", base_url, key),
+    };
+    if options.dry_run {
+        log_dry_run_podspec(pod_root, &assets[0], Some(&url));
+        for path in assets {
+            log_dry_run_upload(path, &format!("{}/{}", base_url, key_for(path)));
+        }
+        return Ok(());
+    }
+
+    for path in assets {
+        let url = format!("{}/{}", base_url, key_for(path));
+        if let Err(e) = client
+            .upload(&url, path, crate::bundle::content_type(path))
+            .await
+        {
+            return Err(Error::Network(format!(
+                "Failed to upload {}: {}",
+                path.display(),
+                e
+            )));
+        }
+        if let Err(message) = client.verify(&url, path).await {
+            return Err(Error::Network(format!(
+                "Upload of {} is incomplete: {}",
+                path.display(),
+                message
+            )));
+        }
+    }
+
+    update_published_podspec(options, pod_root, package, &assets[0], Some(&url))?;
+    log::info!("Published {}", url);
+    crate::message::published(&url);
+    Ok(())
+}
+
+/// Deploys the bundle and its companions to an Artifactory repository,
+/// tagged with the pod's name, version and platform, and points the podspec
+/// at the archive.
+pub(crate) async fn publish_artifactory(
+    options: &Publisher,
+    pod_root: &Path,
+    package: &Package,
+    config: &PublishConfig,
+    assets: &[PathBuf],
+    platform_archives: &[(&str, PathBuf)],
+) -> Result<(), Error> {
+    let (base_url, repository) = match (&config.url, &config.repository) {
+        (Some(url), Some(repository)) => (url, repository.clone()),
+        _ => {
+            return Err(Error::Usage("Publishing to Artifactory requires `url` and `repository` in [package.metadata.pod.publish].".into()));
+        }
+    };
+    let env = |name: &str| std::env::var(name).ok().filter(|x| !x.trim().is_empty());
+    let auth = if let Some(api_key) = options.token.clone().or_else(|| env("ARTIFACTORY_API_KEY")) {
+        Auth::Header("X-JFrog-Art-Api", api_key)
+    } else if let Some(access_token) = env("ARTIFACTORY_ACCESS_TOKEN") {
+        Auth::Bearer(access_token)
+    } else if let Some(username) = env("ARTIFACTORY_USER").or_else(|| config.username.clone()) {
+        Auth::Basic(username, env("ARTIFACTORY_PASSWORD"))
+    } else {
+        return Err(Error::Usage("No Artifactory credentials found; pass --token or set ARTIFACTORY_API_KEY, ARTIFACTORY_ACCESS_TOKEN or ARTIFACTORY_USER and ARTIFACTORY_PASSWORD.".into()));
+    };
+    let key_template = config.key.as_deref().unwrap_or(DEFAULT_PUBLISH_KEY);
+    let name = pod_name(package);
+    let version = pod_version(package)?;
+    let key_for = |path: &Path| {
+        let file = path.file_name().unwrap().to_string_lossy();
+        crate::publish::render_key(key_template, &name, &version, &file)
+    };
+
+    let client = crate::publish::artifactory::Client::new(base_url, repository, auth);
+
+    let key = key_for(&assets[0]);
+    let url = match config.public_url.as_deref() {
+        Some(template) => template.replace("{key}", &key),
+        None => client.download_url(&key),
+    };
+    if options.dry_run {
+        log_dry_run_podspec(pod_root, &assets[0], Some(&url));
+        for path in assets {
+            log_dry_run_upload(path, &client.download_url(&key_for(path)));
+        }
+        return Ok(());
+    }
+
+    for path in assets {
+        let platform = platform_archives
+            .iter()
+            .find(|(_, x)| x == path)
+            .map(|(platform, _)| *platform)
+            .unwrap_or(crate::bundle::UNIVERSAL_PLATFORM);
+        let mut properties = BTreeMap::new();
+        properties.insert("pod.name".to_string(), name.clone());
+        properties.insert("pod.version".to_string(), version.clone());
+        properties.insert("pod.platform".to_string(), platform.to_string());
+        properties.extend(config.properties.clone());
+
+        let key = key_for(path);
+        if let Err(e) = client
+            .deploy(&key, path, crate::bundle::content_type(path), &properties)
+            .await
+        {
+            return Err(Error::Network(format!(
+                "Failed to deploy {}: {}",
+                path.display(),
+                e
+            )));
+        }
+        if let Err(message) = client.verify(&key, path).await {
+            return Err(Error::Network(format!(
+                "Deploy of {} is incomplete: {}",
+                path.display(),
+                message
+            )));
+        }
+    }
+
+    update_published_podspec(options, pod_root, package, &assets[0], Some(&url))?;
+    log::info!("Published {}", url);
+    crate::message::published(&url);
+    Ok(())
+}
+
+/// Copies the bundle and its companions into a local directory, laid out
+/// like a remote publish, and points the podspec at the archive with a
+/// `file://` URL.
+pub(crate) fn publish_local(
+    options: &Publisher,
+    pod_root: &Path,
+    package: &Package,
+    config: &PublishConfig,
+    assets: &[PathBuf],
+) -> Result<(), Error> {
+    let root = match &options.path {
+        Some(v) => v.clone(),
+        None => {
+            return Err(Error::Usage("Publishing locally requires --path.".into()));
+        }
+    };
+    let key_template = config.key.as_deref().unwrap_or(DEFAULT_PUBLISH_KEY);
+    let name = pod_name(package);
+    let version = pod_version(package)?;
+    let key_for = |path: &Path| {
+        let file = path.file_name().unwrap().to_string_lossy();
+        crate::publish::render_key(key_template, &name, &version, &file)
+    };
+
+    if options.dry_run {
+        let url = crate::publish::local::file_url(&root.join(key_for(&assets[0])));
+        log_dry_run_podspec(pod_root, &assets[0], Some(&url));
+        for path in assets {
+            log_dry_run_upload(path, &root.join(key_for(path)).to_string_lossy());
+        }
+        return Ok(());
+    }
+
+    let mut dests = vec![];
+    for path in assets {
+        let key = key_for(path);
+        dests.push(crate::publish::local::copy(&root, &key, path).map_err(Error::io(path))?);
+    }
+
+    let url = crate::publish::local::file_url(&dests[0]);
+    update_published_podspec(options, pod_root, package, &assets[0], Some(&url))?;
+    log::info!("Published {}", url);
+    crate::message::published(&url);
+    Ok(())
+}
+
+/// Copies the bundle and its companions to a host over SSH, for pods served
+/// from a static web server, and points the podspec at `public_url`.
+pub(crate) fn publish_ssh(
+    options: &Publisher,
+    pod_root: &Path,
+    package: &Package,
+    config: &PublishConfig,
+    assets: &[PathBuf],
+) -> Result<(), Error> {
+    let dest = match options.dest.as_deref().or(config.dest.as_deref()) {
+        Some(v) => match crate::publish::ssh::Dest::parse(v) {
+            Some(v) => v,
+            None => {
+                return Err(Error::Usage(format!(
+                    "Could not parse the destination {:?}; expected user@host:/path/",
+                    v
+                )));
+            }
+        },
+        None => {
+            return Err(Error::Usage(
+                "Publishing over SSH requires --dest or `dest` in [package.metadata.pod.publish]."
+                    .into(),
+            ));
+        }
+    };
+    let key_template = config.key.as_deref().unwrap_or(DEFAULT_PUBLISH_KEY);
+    let name = pod_name(package);
+    let version = pod_version(package)?;
+    let key_for = |path: &Path| {
+        let file = path.file_name().unwrap().to_string_lossy();
+        crate::publish::render_key(key_template, &name, &version, &file)
+    };
+    let url = config
+        .public_url
+        .as_deref()
+        .map(|x| x.replace("{key}", &key_for(&assets[0])));
+    if url.is_none() {
+        log::warn!("No `public_url` configured; only the podspec checksum will be updated.");
+    }
+
+    if options.dry_run {
+        log_dry_run_podspec(pod_root, &assets[0], url.as_deref());
+        for path in assets {
+            log_dry_run_upload(
+                path,
+                &format!("{}:{}", dest.host, dest.path_of(&key_for(path))),
+            );
+        }
+        return Ok(());
+    }
+
+    for path in assets {
+        if let Err(e) = crate::publish::ssh::copy(&dest, &key_for(path), path) {
+            return Err(Error::tool(
+                "scp",
+                format!("could not copy {} to {}: {}", path.display(), dest, e),
+            ));
+        }
+    }
+
+    update_published_podspec(options, pod_root, package, &assets[0], url.as_deref())?;
+    log::info!("Published to {}", dest);
+    crate::message::published(&url.unwrap_or_else(|| dest.to_string()));
+    Ok(())
+}
+
+pub(crate) fn log_dry_run_upload(path: &Path, dest: &str) {
+    let len = std::fs::metadata(path).map(|x| x.len()).unwrap_or_default();
+    log::info!(
+        "Would upload {} ({}) to {}",
+        path.file_name().unwrap().to_string_lossy(),
+        crate::bundle::format_size(len),
+        dest
+    );
+}
+
+/// Records the published archive's checksum and, if known, URL in the
+/// podspec, committing it if asked to.
+pub(crate) fn update_published_podspec(
+    options: &Publisher,
+    pod_root: &Path,
+    package: &Package,
+    archive_path: &Path,
+    url: Option<&str>,
+) -> Result<(), Error> {
+    update_podspec_sha256(pod_root, archive_path)?;
+    if let Some(url) = url {
+        update_podspec_source_url(pod_root, url)?;
+    }
+    update_package_swift(
+        pod_root,
+        archive_path.parent().unwrap_or_else(|| Path::new("")),
+        url,
+    )?;
+    if !options.commit {
+        return Ok(());
+    }
+
+    let podspec_path = match find_podspec(pod_root) {
+        Some(v) => v,
+        None => return Ok(()),
+    };
+    let mut paths = vec![podspec_path];
+    let package_swift_path = pod_root.join(crate::package_swift::FILE_NAME);
+    if package_swift_path.exists() {
+        paths.push(package_swift_path);
+    }
+    let message = format!("Update podspec source for {}", pod_version(package)?);
+    git_commit_paths(&message, &paths)
+}
+
+/// Points the binary targets of `Package.swift`, if there is one, at the
+/// xcframework zips in `output_dir`, published next to the archive at `url`.
+pub(crate) fn update_package_swift(
+    pod_root: &Path,
+    output_dir: &Path,
+    url: Option<&str>,
+) -> Result<(), Error> {
+    let path = pod_root.join(crate::package_swift::FILE_NAME);
+    if !path.exists() {
+        return Ok(());
+    }
+    let mut manifest = crate::fs::read_to_string(&path)?;
+    for zip in xcframework_zips(output_dir) {
+        let file_name = zip.file_name().unwrap().to_string_lossy().to_string();
+        let name = file_name.trim_end_matches(crate::package_swift::ZIP_SUFFIX);
+        let zip_url = url.map(|x| crate::package_swift::sibling_url(x, &file_name));
+        let checksum = crate::checksum::sha256_file(&zip).map_err(Error::io(&zip))?;
+        match crate::package_swift::set_binary_target(
+            &manifest,
+            name,
+            zip_url.as_deref(),
+            &checksum,
+        ) {
+            Some(v) => {
+                log::info!(
+                    "Setting {} checksum in {} to {}",
+                    name,
+                    path.display(),
+                    checksum
+                );
+                manifest = v;
+            }
+            None => log::warn!(
+                "No binary target named {} in {}; not updating it.",
+                name,
+                path.display()
+            ),
+        }
+    }
+    crate::fs::write(&path, manifest)
+}
+
+/// Completes a release that already exists, such as one left behind by a
+/// failed run: assets already uploaded with a matching checksum are kept,
+/// missing or broken ones are uploaded, and any that differ from the local
+/// files are an error unless `--force` is used.
+pub(crate) async fn resume_release(
+    options: &Publisher,
+    client: &ForgeClient,
+    release: &Release,
+    pod_root: &Path,
+    package: &Package,
+    assets: &[PathBuf],
+) -> Result<(), Error> {
+    log::info!(
+        "Release {} already exists; checking its assets...",
+        release.tag_name
+    );
+    let existing_assets = match client.list_assets(release).await {
+        Ok(v) => v,
+        Err(e) => {
+            return Err(Error::Network(format!(
+                "Failed to list assets of release {}: {}",
+                release.id, e
+            )));
+        }
+    };
+
+    let mut uploaded = vec![];
+    let mut up_to_date = true;
+    for path in assets {
+        let name = path.file_name().unwrap().to_string_lossy();
+        let existing = existing_assets.iter().find(|x| x.name == name);
+
+        if let Some(asset) = existing {
+            if asset.state.as_deref().map_or(true, |x| x == "uploaded") {
+                let expected = crate::checksum::sha256_file(path).map_err(Error::io(path))?;
+                match client.asset_sha256(release, asset).await {
+                    Ok(actual) if actual == expected => {
+                        log::info!("{} is already uploaded", name);
+                        uploaded.push(asset.browser_download_url.clone());
+                        continue;
+                    }
+                    Ok(_) => {
+                        return Err(Error::Check(format!("{} in release {} differs from {}; pass --force to replace the release.",
+                            name,
+                            release.tag_name,
+                            path.display())));
+                    }
+                    Err(e) => {
+                        return Err(Error::Network(format!("Failed to check {}: {}", name, e)));
+                    }
+                }
+            }
+        }
+
+        up_to_date = false;
+        if options.dry_run {
+            log_dry_run_upload(path, "the release");
+            continue;
+        }
+        if let Some(asset) = existing {
+            log::info!("Deleting incomplete asset {}...", asset.name);
+            if let Err(e) = client.delete_asset(release, asset).await {
+                return Err(Error::Network(format!(
+                    "Failed to delete {}: {}",
+                    asset.name, e
+                )));
+            }
+        }
+        let asset = upload_release_asset(client, release, path, options.verify_hash).await?;
+        uploaded.push(asset.browser_download_url);
+    }
+
+    if options.dry_run {
+        return Ok(());
+    }
+    let url = if release.draft {
+        None
+    } else {
+        Some(uploaded[0].as_str())
+    };
+    update_published_podspec(options, pod_root, package, &assets[0], url)?;
+    if up_to_date {
+        log::info!("Already published {}", release.html_url);
+    } else {
+        log::info!("Published {}", release.html_url);
+        crate::message::published(&release.html_url);
+    }
+    Ok(())
+}
+
+/// Uploads and verifies a single asset, deleting it again if the upload
+/// turns out to be incomplete.
+pub(crate) async fn upload_release_asset(
+    client: &ForgeClient,
+    release: &Release,
+    path: &Path,
+    verify_hash: bool,
+) -> Result<ReleaseAsset, Error> {
+    let asset = match client
+        .upload_asset(release, path, crate::bundle::content_type(path))
+        .await
+    {
+        Ok(v) => v,
+        Err(e) => {
+            return Err(Error::Network(format!(
+                "Failed to upload {}: {}",
+                path.display(),
+                e
+            )));
+        }
+    };
+
+    if let Err(message) = client
+        .verify_asset(release, &asset, path, verify_hash)
+        .await
+    {
+        log::info!("Deleting broken asset {}...", asset.name);
+        if let Err(e) = client.delete_asset(release, &asset).await {
+            log::warn!("Failed to delete {}: {}", asset.name, e);
+        }
+        return Err(Error::Network(format!(
+            "Upload of {} is incomplete: {}",
+            asset.name, message
+        )));
+    }
+    Ok(asset)
+}
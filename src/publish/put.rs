@@ -0,0 +1,123 @@
+use std::{path::Path, time::Instant};
+
+use reqwest::{Method, StatusCode, Url};
+
+use super::http::{self, Auth, Error};
+
+/// Uploads files with plain `PUT` requests, which Nexus raw repositories,
+/// WebDAV shares and most artifact stores accept.
+pub struct Client {
+    http: reqwest::Client,
+    auth: Auth,
+}
+
+impl Client {
+    pub fn new(auth: Auth) -> Client {
+        let http = reqwest::Client::builder()
+            .user_agent("cargo-cocoapods")
+            .build()
+            .unwrap();
+        Client { http, auth }
+    }
+
+    fn request(&self, method: Method, url: &str) -> reqwest::RequestBuilder {
+        self.auth.apply(self.http.request(method, url))
+    }
+
+    /// Streams `path` to `url`, creating missing WebDAV collections if the
+    /// server reports a conflict.
+    pub async fn upload(&self, url: &str, path: &Path, content_type: &str) -> Result<(), Error> {
+        let len = std::fs::metadata(path)
+            .map_err(|e| Error::Io(path.to_path_buf(), e))?
+            .len();
+
+        log::info!(
+            "Uploading {} ({}) to {}...",
+            path.display(),
+            crate::bundle::format_size(len),
+            url
+        );
+        let progress = http::upload_progress_bar(len);
+        let started = Instant::now();
+        let put = || {
+            http::send(|| {
+                self.request(Method::PUT, url)
+                    .header("content-type", content_type)
+                    .header("content-length", len)
+                    .body(http::file_body(path, &progress).unwrap())
+            })
+        };
+        let result = match put().await {
+            Err(Error::Status(StatusCode::CONFLICT, _)) => {
+                self.create_collections(url).await;
+                put().await
+            }
+            result => result,
+        };
+        progress.finish_and_clear();
+        result?;
+
+        let elapsed = started.elapsed();
+        log::info!(
+            "Uploaded {} in {:.1}s ({}/s)",
+            url,
+            elapsed.as_secs_f64(),
+            crate::bundle::format_size((len as f64 / elapsed.as_secs_f64().max(0.001)) as u64)
+        );
+        Ok(())
+    }
+
+    /// Issues `MKCOL` for each parent of `url`. Failures are ignored, since
+    /// most of them already exist or sit above the share's root.
+    async fn create_collections(&self, url: &str) {
+        let mut collection = match Url::parse(url) {
+            Ok(v) => v,
+            Err(_) => return,
+        };
+        let segments = match collection.path_segments() {
+            Some(v) => v.map(str::to_string).collect::<Vec<_>>(),
+            None => return,
+        };
+        let mkcol = Method::from_bytes(b"MKCOL").unwrap();
+        let mut path = String::new();
+        for segment in &segments[..segments.len().saturating_sub(1)] {
+            path.push('/');
+            path.push_str(segment);
+            collection.set_path(&format!("{}/", path));
+            let request = self.request(mkcol.clone(), collection.as_str()).send();
+            let result = match crate::interrupt::until_interrupted(request).await {
+                Ok(v) => v,
+                Err(_) => return,
+            };
+            log::debug!(
+                "MKCOL {}: {:?}",
+                collection,
+                result.map(|x| x.status()).map_err(|e| e.to_string())
+            );
+        }
+    }
+
+    /// Checks the size the server reports for `url` against `path`. Servers
+    /// that don't answer `HEAD` with a length are trusted.
+    pub async fn verify(&self, url: &str, path: &Path) -> Result<(), String> {
+        let len = std::fs::metadata(path).map_err(|e| e.to_string())?.len();
+        let response = http::send(|| self.request(Method::HEAD, url))
+            .await
+            .map_err(|e| format!("failed to fetch {}: {}", url, e))?;
+        let size = response
+            .headers()
+            .get(reqwest::header::CONTENT_LENGTH)
+            .and_then(|x| x.to_str().ok())
+            .and_then(|x| x.parse::<u64>().ok());
+        match size {
+            Some(size) if size != len => Err(format!(
+                "{} is {} bytes but {} is {} bytes",
+                url,
+                size,
+                path.display(),
+                len
+            )),
+            _ => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,148 @@
+use std::{
+    path::{Path, PathBuf},
+    time::Instant,
+};
+
+use aws_sdk_s3::{
+    config::{Region, RequestChecksumCalculation, ResponseChecksumValidation},
+    primitives::ByteStream,
+};
+
+#[derive(Debug)]
+pub enum Error {
+    Io(PathBuf, std::io::Error),
+    Body(PathBuf, aws_sdk_s3::primitives::ByteStreamError),
+    S3(aws_sdk_s3::Error),
+    SizeMismatch(String, u64, Option<i64>),
+    Interrupted,
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Io(path, e) => write!(f, "{}: {}", path.display(), e),
+            Error::Body(path, e) => write!(f, "{}: {}", path.display(), e),
+            Error::S3(e) => write!(f, "{}", e),
+            Error::SizeMismatch(key, expected, actual) => write!(
+                f,
+                "{} is {} bytes after upload, expected {}",
+                key,
+                actual.map_or("an unknown number of".into(), |x| x.to_string()),
+                expected
+            ),
+            Error::Interrupted => f.write_str("interrupted"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl<E: Into<aws_sdk_s3::Error>> From<E> for Error {
+    fn from(e: E) -> Self {
+        Error::S3(e.into())
+    }
+}
+
+/// Uploads to a bucket on S3 or an S3-compatible store such as R2, GCS or
+/// MinIO when an `endpoint` is given.
+pub struct Client {
+    s3: aws_sdk_s3::Client,
+    bucket: String,
+}
+
+impl Client {
+    /// Credentials come from the standard AWS chain: environment, shared
+    /// config/credentials files, SSO, and container or instance metadata.
+    pub async fn new(bucket: String, region: Option<String>, endpoint: Option<String>) -> Client {
+        let mut loader = aws_config::defaults(aws_config::BehaviorVersion::latest());
+        if let Some(region) = region {
+            loader = loader.region(Region::new(region));
+        }
+        let shared = loader.load().await;
+
+        let mut builder = aws_sdk_s3::config::Builder::from(&shared);
+        if let Some(endpoint) = endpoint {
+            // Many S3-compatible stores reject the SDK's default aws-chunked
+            // checksum trailers.
+            builder = builder
+                .endpoint_url(endpoint)
+                .force_path_style(true)
+                .request_checksum_calculation(RequestChecksumCalculation::WhenRequired)
+                .response_checksum_validation(ResponseChecksumValidation::WhenRequired);
+        }
+        Client {
+            s3: aws_sdk_s3::Client::from_conf(builder.build()),
+            bucket,
+        }
+    }
+
+    /// Uploads `path` to `key`, then checks the stored object's size.
+    pub async fn upload(&self, key: &str, path: &Path, content_type: &str) -> Result<(), Error> {
+        let len = std::fs::metadata(path)
+            .map_err(|e| Error::Io(path.to_path_buf(), e))?
+            .len();
+        let body = ByteStream::from_path(path)
+            .await
+            .map_err(|e| Error::Body(path.to_path_buf(), e))?;
+
+        log::info!(
+            "Uploading {} ({}) to s3://{}/{}...",
+            path.display(),
+            crate::bundle::format_size(len),
+            self.bucket,
+            key
+        );
+        let started = Instant::now();
+        let upload = self
+            .s3
+            .put_object()
+            .bucket(&self.bucket)
+            .key(key)
+            .content_type(content_type)
+            .content_length(len as i64)
+            .body(body)
+            .send();
+        crate::interrupt::until_interrupted(upload)
+            .await
+            .map_err(|_| Error::Interrupted)??;
+        let elapsed = started.elapsed();
+        log::info!(
+            "Uploaded {} in {:.1}s ({}/s)",
+            key,
+            elapsed.as_secs_f64(),
+            crate::bundle::format_size((len as f64 / elapsed.as_secs_f64().max(0.001)) as u64)
+        );
+
+        let head = self.s3.head_object().bucket(&self.bucket).key(key).send();
+        let head = crate::interrupt::until_interrupted(head)
+            .await
+            .map_err(|_| Error::Interrupted)??;
+        if head.content_length() != Some(len as i64) {
+            return Err(Error::SizeMismatch(
+                key.to_string(),
+                len,
+                head.content_length(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// The public URL of `key`: `public_url` with `{key}` filled in, or the
+/// bucket's own URL.
+pub fn public_url(
+    public_url: Option<&str>,
+    bucket: &str,
+    region: Option<&str>,
+    endpoint: Option<&str>,
+    key: &str,
+) -> String {
+    match (public_url, endpoint) {
+        (Some(template), _) => template.replace("{key}", key),
+        (None, Some(endpoint)) => format!("{}/{}/{}", endpoint.trim_end_matches('/'), bucket, key),
+        (None, None) => match region {
+            Some(region) => format!("https://{}.s3.{}.amazonaws.com/{}", bucket, region, key),
+            None => format!("https://{}.s3.amazonaws.com/{}", bucket, key),
+        },
+    }
+}
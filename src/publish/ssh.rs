@@ -0,0 +1,90 @@
+use std::{io, path::Path, process::Command};
+
+/// An scp-style destination, `[user@]host:/path/`.
+#[derive(Debug, Clone)]
+pub struct Dest {
+    pub host: String,
+    pub path: String,
+}
+
+impl Dest {
+    pub fn parse(dest: &str) -> Option<Dest> {
+        let (host, path) = dest.split_once(':')?;
+        if host.is_empty() || host.contains('/') {
+            return None;
+        }
+        Some(Dest {
+            host: host.to_string(),
+            path: path.trim_end_matches('/').to_string(),
+        })
+    }
+
+    /// The remote path of `key` under the destination.
+    pub fn path_of(&self, key: &str) -> String {
+        if self.path.is_empty() {
+            key.to_string()
+        } else {
+            format!("{}/{}", self.path, key)
+        }
+    }
+}
+
+impl std::fmt::Display for Dest {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.host, self.path)
+    }
+}
+
+fn shell_quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', r"'\''"))
+}
+
+fn run(mut cmd: Command) -> io::Result<()> {
+    log::trace!("Calling: {:?}", &cmd);
+    let status = crate::cmd::status(&mut cmd)?;
+    if !status.success() {
+        return Err(io::Error::new(
+            io::ErrorKind::Other,
+            format!("{:?} exited with {}", cmd, status),
+        ));
+    }
+    Ok(())
+}
+
+fn has_rsync() -> bool {
+    crate::cmd::output(Command::new("rsync").arg("--version"))
+        .map(|x| x.status.success())
+        .unwrap_or(false)
+}
+
+/// Copies `file` to `key` under `dest`, creating remote directories first.
+/// Uses rsync when it's installed, and scp otherwise.
+pub fn copy(dest: &Dest, key: &str, file: &Path) -> io::Result<()> {
+    let remote_path = dest.path_of(key);
+    if let Some((dir, _)) = remote_path.rsplit_once('/') {
+        let mut mkdir = Command::new("ssh");
+        mkdir
+            .arg(&dest.host)
+            .arg(format!("mkdir -p {}", shell_quote(dir)));
+        run(mkdir)?;
+    }
+
+    log::info!(
+        "Copying {} to {}:{}",
+        file.display(),
+        dest.host,
+        remote_path
+    );
+    let target = format!("{}:{}", dest.host, remote_path);
+    let mut cmd = if has_rsync() {
+        let mut cmd = Command::new("rsync");
+        cmd.args(["--times", "--partial", "--protect-args", "--chmod=F644"]);
+        cmd
+    } else {
+        let mut cmd = Command::new("scp");
+        cmd.arg("-q");
+        cmd
+    };
+    cmd.arg(file).arg(target);
+    run(cmd)
+}
@@ -0,0 +1,315 @@
+use std::{fmt::Display, path::Path, process::Command};
+
+use crate::error::Error;
+
+pub const REMOTE_FILE: &str = ".crate-remote";
+pub const PREFIX: &str = "crate";
+
+/// What the embedded crate tracks.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Pin {
+    Branch(String),
+    Tag(String),
+    Rev(String),
+}
+
+impl Pin {
+    /// Picks the pin from the mutually exclusive branch, tag and revision
+    /// options, if any was given.
+    pub fn from_options(
+        branch: Option<&str>,
+        tag: Option<&str>,
+        rev: Option<&str>,
+    ) -> Result<Option<Pin>, Error> {
+        match (branch, tag, rev) {
+            (None, None, None) => Ok(None),
+            (Some(v), None, None) => Ok(Some(Pin::Branch(v.into()))),
+            (None, Some(v), None) => Ok(Some(Pin::Tag(v.into()))),
+            (None, None, Some(v)) => Ok(Some(Pin::Rev(v.into()))),
+            _ => Err(Error::Usage(
+                "Only one of a branch, tag or revision may be given".into(),
+            )),
+        }
+    }
+
+    fn git_ref(&self) -> &str {
+        match self {
+            Pin::Branch(v) | Pin::Tag(v) | Pin::Rev(v) => v,
+        }
+    }
+}
+
+/// The default branch of the repository at `url`, falling back to `main`
+/// if it cannot be determined.
+pub fn default_branch(url: &str) -> Pin {
+    let mut cmd = Command::new("git");
+    cmd.args(["ls-remote", "--symref", url, "HEAD"]);
+    let output = crate::cmd::output(&mut cmd);
+    let branch = output.ok().and_then(|x| {
+        String::from_utf8_lossy(&x.stdout).lines().find_map(|line| {
+            line.strip_prefix("ref: refs/heads/")
+                .and_then(|x| x.split('\t').next())
+                .map(str::to_string)
+        })
+    });
+    Pin::Branch(branch.unwrap_or_else(|| "main".into()))
+}
+
+impl Display for Pin {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Pin::Branch(v) => write!(f, "branch {}", v),
+            Pin::Tag(v) => write!(f, "tag {}", v),
+            Pin::Rev(v) => write!(f, "rev {}", v),
+        }
+    }
+}
+
+/// An entry of `.crate-remote`: the crate's repository URL on the first line,
+/// followed by the pin, the upstream commit last synced and, for crates not
+/// vendored at `crate/`, the prefix, such as `branch main`, `synced <sha>` and
+/// `prefix crates/foo`. Entries are separated by blank lines. Files from older
+/// versions only have the URL, which tracks the repository's default branch.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CrateRemote {
+    pub url: String,
+    pub pin: Pin,
+    pub synced: Option<String>,
+    pub prefix: String,
+}
+
+impl CrateRemote {
+    pub fn new(url: &str, pin: Pin, prefix: &str) -> CrateRemote {
+        CrateRemote {
+            url: url.into(),
+            pin,
+            synced: None,
+            prefix: prefix.into(),
+        }
+    }
+
+    /// Parses an entry, returning false for entries without a pin.
+    fn parse(input: &str) -> Result<(CrateRemote, bool), Error> {
+        let mut lines = input.lines().map(str::trim);
+        let url = match lines.next() {
+            Some(v) => v.to_string(),
+            None => return Err(Error::Usage(format!("{} is empty", REMOTE_FILE))),
+        };
+        let mut pin = None;
+        let mut synced = None;
+        let mut prefix = PREFIX.to_string();
+        for line in lines {
+            match line.split_once(' ').map(|(k, v)| (k, v.trim().to_string())) {
+                Some(("branch", v)) => pin = Some(Pin::Branch(v)),
+                Some(("tag", v)) => pin = Some(Pin::Tag(v)),
+                Some(("rev", v)) => pin = Some(Pin::Rev(v)),
+                Some(("synced", v)) => synced = Some(v),
+                Some(("prefix", v)) => prefix = v,
+                _ => {
+                    return Err(Error::Usage(format!(
+                        "Could not parse `{}` in {}",
+                        line, REMOTE_FILE
+                    )))
+                }
+            }
+        }
+        let pinned = pin.is_some();
+        let pin = pin.unwrap_or_else(|| default_branch(&url));
+        let remote = CrateRemote {
+            url,
+            pin,
+            synced,
+            prefix,
+        };
+        Ok((remote, pinned))
+    }
+
+    /// Reads every entry of `.crate-remote`. The second value is false for
+    /// entries without a pin, whose pin is resolved to the default branch.
+    pub fn read_all() -> Result<Vec<(CrateRemote, bool)>, Error> {
+        let input = std::fs::read_to_string(REMOTE_FILE).map_err(Error::io(REMOTE_FILE))?;
+        let mut entries = vec![];
+        let mut entry = vec![];
+        for line in input.lines().chain(std::iter::once("")) {
+            if !line.trim().is_empty() {
+                entry.push(line);
+            } else if !entry.is_empty() {
+                entries.push(CrateRemote::parse(&entry.join("\n"))?);
+                entry.clear();
+            }
+        }
+        if entries.is_empty() {
+            return Err(Error::Usage(format!("{} is empty", REMOTE_FILE)));
+        }
+        Ok(entries)
+    }
+
+    /// Reads the entry vendored at `prefix`.
+    pub fn read(prefix: &str) -> Result<(CrateRemote, bool), Error> {
+        CrateRemote::read_all()?
+            .into_iter()
+            .find(|(x, _)| x.prefix == prefix)
+            .ok_or_else(|| Error::Usage(format!("No crate at {}/ in {}", prefix, REMOTE_FILE)))
+    }
+
+    /// Writes `remotes` to `.crate-remote`.
+    pub fn write_all(remotes: &[CrateRemote]) -> Result<(), Error> {
+        let entries = remotes.iter().map(|x| x.to_string()).collect::<Vec<_>>();
+        if crate::cmd::is_dry_run() {
+            log::info!("Would write {}", REMOTE_FILE);
+            return Ok(());
+        }
+        std::fs::write(REMOTE_FILE, entries.join("\n")).map_err(Error::io(REMOTE_FILE))
+    }
+
+    /// Adds or replaces the entry for this remote's prefix in `.crate-remote`.
+    pub fn write(&self) -> Result<(), Error> {
+        let mut remotes = CrateRemote::read_all()
+            .map(|x| x.into_iter().map(|(x, _)| x).collect::<Vec<_>>())
+            .unwrap_or_default();
+        match remotes.iter_mut().find(|x| x.prefix == self.prefix) {
+            Some(v) => *v = self.clone(),
+            None => remotes.push(self.clone()),
+        }
+        CrateRemote::write_all(&remotes)
+    }
+}
+
+impl Display for CrateRemote {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{}", self.url)?;
+        writeln!(f, "{}", self.pin)?;
+        if self.prefix != PREFIX {
+            writeln!(f, "prefix {}", self.prefix)?;
+        }
+        if let Some(synced) = &self.synced {
+            writeln!(f, "synced {}", synced)?;
+        }
+        Ok(())
+    }
+}
+
+fn git(args: &[&str]) -> Result<(), Error> {
+    let mut cmd = Command::new("git");
+    cmd.args(args);
+    if crate::cmd::skip(&cmd) {
+        return Ok(());
+    }
+    let status = crate::cmd::status(&mut cmd).map_err(|e| Error::tool("git", e.to_string()))?;
+    if !status.success() {
+        return Err(Error::Git(format!("`git {}` failed", args.join(" "))));
+    }
+    Ok(())
+}
+
+/// The upstream commit the last `git subtree` or `git fetch` brought in.
+fn fetched_commit() -> Result<String, Error> {
+    if crate::cmd::is_dry_run() {
+        return Ok("FETCH_HEAD".into());
+    }
+    crate::git::open()
+        .and_then(|repo| crate::git::fetched_commit(&repo))
+        .map_err(|e| Error::Git(format!("Could not resolve the fetched commit: {}", e)))
+}
+
+/// Adds the crate as a squashed subtree at its prefix, returning the upstream
+/// commit it was added from. Revisions are fetched first, as `git subtree`
+/// only fetches named refs.
+pub fn add(remote: &CrateRemote) -> Result<String, Error> {
+    match &remote.pin {
+        Pin::Rev(rev) => {
+            git(&["fetch", &remote.url, rev])?;
+            git(&[
+                "subtree",
+                "add",
+                "--prefix",
+                &remote.prefix,
+                rev,
+                "--squash",
+            ])
+        }
+        pin => git(&[
+            "subtree",
+            "add",
+            "--prefix",
+            &remote.prefix,
+            &remote.url,
+            pin.git_ref(),
+            "--squash",
+        ]),
+    }?;
+    fetched_commit()
+}
+
+/// Updates the crate's subtree to what `remote` is pinned to, returning
+/// the upstream commit it now matches.
+pub fn pull(remote: &CrateRemote) -> Result<String, Error> {
+    if !Path::new(&remote.prefix).exists() {
+        return Err(Error::Usage(format!(
+            "No crate found at {}/.",
+            remote.prefix
+        )));
+    }
+    match &remote.pin {
+        Pin::Rev(rev) => {
+            git(&["fetch", &remote.url, rev])?;
+            git(&[
+                "subtree",
+                "merge",
+                "--prefix",
+                &remote.prefix,
+                rev,
+                "--squash",
+            ])
+        }
+        pin => git(&[
+            "subtree",
+            "pull",
+            "--prefix",
+            &remote.prefix,
+            &remote.url,
+            pin.git_ref(),
+            "--squash",
+        ]),
+    }?;
+    fetched_commit()
+}
+
+/// Pushes commits made under the crate's prefix to `branch` of its repository.
+pub fn push(remote: &CrateRemote, branch: &str) -> Result<(), Error> {
+    if !Path::new(&remote.prefix).exists() {
+        return Err(Error::Usage(format!(
+            "No crate found at {}/.",
+            remote.prefix
+        )));
+    }
+    git(&[
+        "subtree",
+        "push",
+        "--prefix",
+        &remote.prefix,
+        &remote.url,
+        branch,
+    ])
+}
+
+/// Splits the history of `prefix` out into a new repository at `dest`, with
+/// it checked out on `branch`.
+pub fn extract(prefix: &str, dest: &Path, branch: &str) -> Result<(), Error> {
+    if !Path::new(prefix).exists() {
+        return Err(Error::Usage(format!("No crate found at {}/.", prefix)));
+    }
+    let split_branch = "cargo-pod-extract";
+    git(&["subtree", "split", "--prefix", prefix, "-b", split_branch])?;
+    let result = crate::git::clone_branch(Path::new("."), split_branch, dest, branch)
+        .map_err(|e| Error::Git(format!("Could not create the repository: {}", e)));
+    crate::git::open()
+        .and_then(|repo| crate::git::delete_branch(&repo, split_branch))
+        .map_err(|e| {
+            Error::Git(format!(
+                "Could not delete the {} branch: {}",
+                split_branch, e
+            ))
+        })?;
+    result
+}
@@ -0,0 +1,235 @@
+use std::{
+    io,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use cargo_metadata::{Package, Target};
+use heck::CamelCase;
+
+/// Starting layouts for `cargo pod init --template`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Template {
+    /// A hand-written C header and Swift wrapper.
+    Plain,
+    /// A header generated by cbindgen, with a hand-written Swift wrapper.
+    Cbindgen,
+    /// Header and Swift bindings both generated by UniFFI.
+    Uniffi,
+}
+
+impl FromStr for Template {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "plain" => Ok(Template::Plain),
+            "cbindgen" => Ok(Template::Cbindgen),
+            "uniffi" => Ok(Template::Uniffi),
+            other => Err(format!(
+                "unknown template {:?}; expected plain, cbindgen or uniffi",
+                other
+            )),
+        }
+    }
+}
+
+/// Metadata written for templates that need more than the defaults. UniFFI's
+/// generated Swift needs iOS 13 and macOS 10.15 for its use of concurrency.
+const UNIFFI_METADATA: &str = r#"
+[package.metadata.pod]
+ios_deployment_target = "13.0"
+macos_deployment_target = "10.15"
+"#;
+
+fn header(sys_name: &str) -> String {
+    let guard = format!("{}_H", sys_name.to_uppercase());
+    format!(
+        r#"#ifndef {guard}
+#define {guard}
+
+#include <stdint.h>
+
+/* Declare the functions exported from Rust with `#[no_mangle] pub extern "C"` here. */
+int32_t {sys_name}_add(int32_t a, int32_t b);
+
+#endif /* {guard} */
+"#
+    )
+}
+
+fn swift_wrapper(sys_name: &str, mod_name: &str) -> String {
+    format!(
+        r#"import {mod_name}_Private
+
+/// Swift API over the C functions in `headers/{sys_name}.h`.
+public func add(_ a: Int32, _ b: Int32) -> Int32 {{
+    {sys_name}_add(a, b)
+}}
+"#
+    )
+}
+
+fn bridging_guide(sys_name: &str, mod_name: &str) -> String {
+    format!(
+        r#"# Bridging Rust to Swift
+
+A call from Swift passes through three layers:
+
+1. Rust exports a C ABI function from the staticlib:
+
+   ```rust
+   #[no_mangle]
+   pub extern "C" fn {sys_name}_add(a: i32, b: i32) -> i32 {{
+       a + b
+   }}
+   ```
+
+2. `headers/{sys_name}.h` declares it for the private `{mod_name}_Private` module:
+
+   ```c
+   int32_t {sys_name}_add(int32_t a, int32_t b);
+   ```
+
+3. `bindings/{mod_name}.swift` wraps it in the public `{mod_name}` API:
+
+   ```swift
+   public func add(_ a: Int32, _ b: Int32) -> Int32 {{
+       {sys_name}_add(a, b)
+   }}
+   ```
+
+Apps then `import {mod_name}` and call `add(1, 2)`. Keep the header in sync
+with the Rust exports; a missing or misspelled symbol only shows up as a link
+error in the app.
+"#
+    )
+}
+
+fn cbindgen_toml(sys_name: &str) -> String {
+    format!(
+        r#"language = "C"
+include_guard = "{}_H"
+sys_includes = ["stdint.h"]
+no_includes = true
+"#,
+        sys_name.to_uppercase()
+    )
+}
+
+fn uniffi_toml(sys_name: &str, mod_name: &str) -> String {
+    // Match the module layout `cargo pod build` creates, so the generated
+    // Swift imports the private FFI module.
+    format!(
+        r#"[bindings.swift]
+module_name = "{mod_name}"
+ffi_module_name = "{mod_name}_Private"
+ffi_module_filename = "{sys_name}"
+generate_module_map = false
+"#
+    )
+}
+
+/// The files `template` creates for `target`, relative to the crate.
+fn files(template: Template, target: &Target) -> Vec<(PathBuf, String)> {
+    let sys_name = target.name.replace('-', "_");
+    let mod_name = sys_name.to_camel_case();
+    let header_path = Path::new("headers").join(format!("{}.h", sys_name));
+    let wrapper_path = Path::new("bindings").join(format!("{}.swift", mod_name));
+
+    match template {
+        Template::Plain => vec![
+            (header_path, header(&sys_name)),
+            (wrapper_path, swift_wrapper(&sys_name, &mod_name)),
+            ("BRIDGING.md".into(), bridging_guide(&sys_name, &mod_name)),
+        ],
+        Template::Cbindgen => vec![
+            ("cbindgen.toml".into(), cbindgen_toml(&sys_name)),
+            (wrapper_path, swift_wrapper(&sys_name, &mod_name)),
+        ],
+        Template::Uniffi => vec![("uniffi.toml".into(), uniffi_toml(&sys_name, &mod_name))],
+    }
+}
+
+/// Whether the crate already has Swift sources, either in `bindings/` or
+/// under `src/`.
+pub fn has_swift_sources(package: &Package) -> bool {
+    let package_dir = package.manifest_path.parent().unwrap();
+    if package_dir.join("bindings").exists() {
+        return true;
+    }
+    let pattern = package_dir.join("src").join("**").join("*.swift");
+    glob::glob(&pattern.to_string_lossy())
+        .map(|mut paths| paths.next().is_some())
+        .unwrap_or(false)
+}
+
+/// Writes the template's files into the crate, leaving existing files
+/// untouched, and adds metadata defaults to `Cargo.toml` if it has none.
+pub fn scaffold(template: Template, package: &Package, targets: &[Target]) -> io::Result<()> {
+    let package_dir = package.manifest_path.parent().unwrap();
+    let dry_run = crate::cmd::is_dry_run();
+
+    for dir in ["headers", "bindings"] {
+        if dry_run {
+            break;
+        }
+        std::fs::create_dir_all(package_dir.join(dir))?;
+    }
+
+    for target in targets {
+        for (path, contents) in files(template, target) {
+            let path = package_dir.join(path);
+            if path.exists() {
+                log::info!("Keeping existing {}", path.display());
+                continue;
+            }
+            if dry_run {
+                log::info!("Would write {}", path.display());
+                continue;
+            }
+            log::info!("Writing {}", path.display());
+            std::fs::write(&path, contents)?;
+        }
+    }
+
+    if template == Template::Uniffi {
+        let manifest = std::fs::read_to_string(&package.manifest_path)?;
+        if !manifest.contains("[package.metadata.pod") && dry_run {
+            log::info!(
+                "Would add pod metadata to {}",
+                package.manifest_path.display()
+            );
+        } else if !manifest.contains("[package.metadata.pod") {
+            log::info!("Adding pod metadata to {}", package.manifest_path.display());
+            std::fs::write(
+                &package.manifest_path,
+                format!("{}{}", manifest, UNIFFI_METADATA),
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+/// What's left to do by hand after scaffolding.
+pub fn next_steps(template: Template, targets: &[Target]) -> Vec<String> {
+    targets
+        .iter()
+        .map(|target| {
+            let sys_name = target.name.replace('-', "_");
+            let mod_name = sys_name.to_camel_case();
+            match template {
+                Template::Plain => format!(
+                    "Export `{sys_name}_add` from Rust as shown in BRIDGING.md, then declare your own functions in headers/{sys_name}.h and wrap them in bindings/{mod_name}.swift."
+                ),
+                Template::Cbindgen => format!(
+                    "Generate the header with `cbindgen --config cbindgen.toml --output headers/{sys_name}.h`, then wrap its functions in bindings/{mod_name}.swift."
+                ),
+                Template::Uniffi => format!(
+                    "Generate bindings with `uniffi-bindgen generate --library <path to lib{sys_name}> --language swift --config uniffi.toml --out-dir bindings`, then move bindings/{sys_name}.h to headers/."
+                ),
+            }
+        })
+        .collect()
+}
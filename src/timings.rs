@@ -0,0 +1,180 @@
+//! How long each phase and step of a build took, for the summary at the end
+//! of `cargo pod build` and its `--timings` report.
+
+use std::{
+    cmp::Reverse,
+    fmt::Write,
+    str::FromStr,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use indicatif::HumanDuration;
+use once_cell::sync::Lazy;
+
+/// The format of the `--timings` report.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Html,
+    Json,
+}
+
+impl Format {
+    pub fn file_name(&self) -> &'static str {
+        match self {
+            Format::Html => "cargo-pod-timings.html",
+            Format::Json => "cargo-pod-timings.json",
+        }
+    }
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "html" => Ok(Format::Html),
+            "json" => Ok(Format::Json),
+            other => Err(format!("unsupported timings format: {}", other)),
+        }
+    }
+}
+
+struct Step {
+    phase: &'static str,
+    name: String,
+    duration: Duration,
+}
+
+#[derive(Default)]
+struct Timings {
+    phases: Vec<(&'static str, Duration)>,
+    steps: Vec<Step>,
+}
+
+static TIMINGS: Lazy<Mutex<Timings>> = Lazy::new(Default::default);
+
+/// Records that the `name` step of `phase` took `duration`.
+pub(crate) fn step(phase: &'static str, name: &str, duration: Duration) {
+    TIMINGS.lock().unwrap().steps.push(Step {
+        phase,
+        name: name.to_string(),
+        duration,
+    });
+}
+
+/// Records that the whole of `phase` took `duration`.
+pub(crate) fn phase(phase: &'static str, duration: Duration) {
+    TIMINGS.lock().unwrap().phases.push((phase, duration));
+}
+
+/// Logs how long each phase took out of the `started` total, and the
+/// slowest steps.
+pub(crate) fn log_summary(started: Instant) {
+    let timings = TIMINGS.lock().unwrap();
+    let total = started.elapsed();
+    log::info!("Build finished in {}", HumanDuration(total));
+    for (name, duration) in &timings.phases {
+        log::info!(
+            "  {:<16} {:>8} {:>5.1}%",
+            name,
+            HumanDuration(*duration).to_string(),
+            percent(*duration, total)
+        );
+    }
+    let mut steps = timings.steps.iter().collect::<Vec<_>>();
+    steps.sort_by_key(|x| Reverse(x.duration));
+    for step in steps.iter().take(3) {
+        log::info!(
+            "Slowest: {} took {}",
+            step.name,
+            HumanDuration(step.duration)
+        );
+    }
+}
+
+fn percent(duration: Duration, total: Duration) -> f64 {
+    if total.as_secs_f64() == 0.0 {
+        return 0.0;
+    }
+    duration.as_secs_f64() / total.as_secs_f64() * 100.0
+}
+
+/// The report for `--timings`, with `started` as the start of the build.
+pub(crate) fn report(format: Format, started: Instant) -> String {
+    let timings = TIMINGS.lock().unwrap();
+    let total = started.elapsed();
+    match format {
+        Format::Json => {
+            let value = serde_json::json!({
+                "total_secs": total.as_secs_f64(),
+                "phases": timings.phases.iter().map(|(name, duration)| serde_json::json!({
+                    "name": name,
+                    "secs": duration.as_secs_f64(),
+                })).collect::<Vec<_>>(),
+                "steps": timings.steps.iter().map(|step| serde_json::json!({
+                    "phase": step.phase,
+                    "name": step.name,
+                    "secs": step.duration.as_secs_f64(),
+                })).collect::<Vec<_>>(),
+            });
+            serde_json::to_string_pretty(&value).unwrap()
+        }
+        Format::Html => html(&timings, total),
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+fn html(timings: &Timings, total: Duration) -> String {
+    let mut out = String::new();
+    let row = |out: &mut String, name: &str, duration: Duration| {
+        let percent = percent(duration, total);
+        writeln!(
+            out,
+            "<tr><td>{}</td><td class=\"secs\">{:.1}s</td><td><div class=\"bar\" style=\"width: {:.1}%\"></div></td></tr>",
+            escape(name),
+            duration.as_secs_f64(),
+            percent
+        )
+        .unwrap();
+    };
+
+    out.push_str(
+        "<!DOCTYPE html>
+<html>
+<head>
+<meta charset=\"utf-8\">
+<title>cargo pod build timings</title>
+<style>
+body { font-family: -apple-system, sans-serif; margin: 2em; }
+table { border-collapse: collapse; width: 100%; }
+td, th { padding: 2px 8px; text-align: left; }
+td.secs { text-align: right; white-space: nowrap; }
+td:last-child { width: 60%; }
+.bar { background: #4a90d9; height: 1em; }
+</style>
+</head>
+<body>
+",
+    );
+    writeln!(out, "<h1>cargo pod build: {:.1}s</h1>", total.as_secs_f64()).unwrap();
+    out.push_str("<h2>Phases</h2>\n<table>\n");
+    for (name, duration) in &timings.phases {
+        row(&mut out, name, *duration);
+    }
+    out.push_str("</table>\n");
+    for (phase, _) in &timings.phases {
+        writeln!(out, "<h2>{}</h2>\n<table>", escape(phase)).unwrap();
+        for step in timings.steps.iter().filter(|x| x.phase == *phase) {
+            row(&mut out, &step.name, step.duration);
+        }
+        out.push_str("</table>\n");
+    }
+    out.push_str("</body>\n</html>\n");
+    out
+}
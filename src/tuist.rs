@@ -0,0 +1,44 @@
+use heck::MixedCase;
+
+/// A Tuist project description helper listing the pod's xcframeworks as
+/// target dependencies. Copied into `Tuist/ProjectDescriptionHelpers`, it is
+/// used as `dependencies: .skLib(in: "Vendor/SkLib")`.
+pub struct Helper {
+    pub pod_name: String,
+    /// The Swift and FFI module names of each crate target.
+    pub targets: Vec<(String, String)>,
+}
+
+impl Helper {
+    pub fn file_name(&self) -> String {
+        format!("TargetDependency+{}.swift", self.pod_name.replace('-', ""))
+    }
+}
+
+impl std::fmt::Display for Helper {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("import ProjectDescription\n\n")?;
+        f.write_str("public extension Array where Element == TargetDependency {\n")?;
+        f.write_fmt(format_args!(
+            "    /// The {} xcframeworks built by cargo-pod, in `dir` relative to\n",
+            self.pod_name
+        ))?;
+        f.write_str("    /// the root of the Tuist project.\n")?;
+        f.write_fmt(format_args!(
+            "    static func {}(in dir: String = \"dist\") -> [TargetDependency] {{\n",
+            self.pod_name.to_mixed_case()
+        ))?;
+        f.write_str("        [\n")?;
+        for (mod_name, ffi_mod_name) in &self.targets {
+            for name in [ffi_mod_name, mod_name] {
+                f.write_fmt(format_args!(
+                    "            .xcframework(path: .relativeToRoot(\"\\(dir)/{}.xcframework\")),\n",
+                    name
+                ))?;
+            }
+        }
+        f.write_str("        ]\n")?;
+        f.write_str("    }\n")?;
+        f.write_str("}\n")
+    }
+}
@@ -0,0 +1,155 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+use crate::meta::Config;
+
+// Mirrors `Pod::Version::VERSION_PATTERN`; notably, build metadata (`+...`)
+// is not accepted by CocoaPods.
+static POD_VERSION_RE: Lazy<Regex> =
+    regex_static::lazy_regex!(r"^[0-9]+(\.[0-9a-zA-Z]+)*(-[0-9A-Za-z-]+(\.[0-9A-Za-z-]+)*)?$");
+
+/// Translates a Cargo version into a version CocoaPods will accept.
+///
+/// Explicit entries in `version_map` win. Otherwise build metadata is dropped,
+/// and when `dev_timestamp` is set, versions whose pre-release starts with
+/// `dev` get a timestamp appended so successive dev builds sort correctly.
+pub fn pod_version(version: &semver::Version, config: &Config) -> Result<String, String> {
+    let cargo_version = version.to_string();
+
+    if let Some(mapped) = config.version_map.get(&cargo_version) {
+        return validate(mapped.clone());
+    }
+
+    let mut out = untimed_version(version);
+    if has_dev_timestamp(version, config) {
+        out.push_str(&format!(".{}", dev_timestamp()));
+    }
+
+    if !version.build.is_empty() {
+        log::warn!(
+            "Dropping build metadata from version {}; CocoaPods does not support it.",
+            cargo_version
+        );
+    }
+
+    validate(out)
+}
+
+/// The version without build metadata or a dev timestamp.
+fn untimed_version(version: &semver::Version) -> String {
+    let mut out = format!("{}.{}.{}", version.major, version.minor, version.patch);
+    if !version.pre.is_empty() {
+        let pre = version
+            .pre
+            .iter()
+            .map(|x| x.to_string())
+            .collect::<Vec<_>>()
+            .join(".");
+        out.push('-');
+        out.push_str(&pre);
+    }
+    out
+}
+
+fn has_dev_timestamp(version: &semver::Version, config: &Config) -> bool {
+    config.dev_timestamp
+        && !config.version_map.contains_key(&version.to_string())
+        && version
+            .pre
+            .first()
+            .map(|x| x.to_string().starts_with("dev"))
+            .unwrap_or(false)
+}
+
+/// The timestamp appended to dev versions: `SOURCE_DATE_EPOCH` if set,
+/// otherwise the time of the HEAD commit, so that it only changes with the
+/// source. Without a repository, the time of the first call.
+fn dev_timestamp() -> u64 {
+    static TIMESTAMP: Lazy<u64> = Lazy::new(|| {
+        if let Some(v) = std::env::var("SOURCE_DATE_EPOCH")
+            .ok()
+            .and_then(|x| x.parse().ok())
+        {
+            return v;
+        }
+        let head_time =
+            crate::git::open().and_then(|repo| Ok(repo.head()?.peel_to_commit()?.time().seconds()));
+        match head_time {
+            Ok(v) => v as u64,
+            Err(_) => SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap()
+                .as_secs(),
+        }
+    });
+    *TIMESTAMP
+}
+
+/// Whether the podspec version `current` is the pod version of `version`
+/// with a different dev timestamp. The timestamp moves with every commit, so
+/// such a podspec is not out of date.
+pub fn is_same_dev_build(current: &str, version: &semver::Version, config: &Config) -> bool {
+    if !has_dev_timestamp(version, config) {
+        return false;
+    }
+    current
+        .strip_prefix(&format!("{}.", untimed_version(version)))
+        .map(|x| !x.is_empty() && x.bytes().all(|b| b.is_ascii_digit()))
+        .unwrap_or(false)
+}
+
+fn validate(version: String) -> Result<String, String> {
+    if POD_VERSION_RE.is_match(&version) {
+        Ok(version)
+    } else {
+        Err(format!("'{}' is not a valid CocoaPods version", version))
+    }
+}
+
+static SECTION_RE: Lazy<Regex> = regex_static::lazy_regex!(r"(?m)^\s*\[\[?([^\]]*)\]\]?\s*$");
+static MANIFEST_VERSION_RE: Lazy<Regex> =
+    regex_static::lazy_regex!(r#"(?m)^(\s*version\s*=\s*)"(.*?)""#);
+
+/// Applies a `major`, `minor` or `patch` bump, or parses an explicit version.
+pub fn bump(version: &semver::Version, how: &str) -> Result<semver::Version, String> {
+    let mut version = version.clone();
+    match how {
+        "major" => version.increment_major(),
+        "minor" => version.increment_minor(),
+        "patch" => version.increment_patch(),
+        other => {
+            version = semver::Version::parse(other)
+                .map_err(|e| format!("'{}' is not a valid version: {}", other, e))?;
+        }
+    }
+    Ok(version)
+}
+
+/// Sets the `version` key of the `[package]` table in a Cargo.toml, leaving
+/// the rest of the file untouched.
+pub fn set_manifest_version(manifest: &str, version: &str) -> Option<String> {
+    let mut sections = SECTION_RE.captures_iter(manifest);
+    let start = sections
+        .by_ref()
+        .find(|c| c.get(1).unwrap().as_str().trim() == "package")?
+        .get(0)
+        .unwrap()
+        .end();
+    let end = sections
+        .next()
+        .map(|c| c.get(0).unwrap().start())
+        .unwrap_or(manifest.len());
+
+    let package = &manifest[start..end];
+    let m = MANIFEST_VERSION_RE.captures(package)?;
+    let whole = m.get(0).unwrap();
+    let prefix = m.get(1).unwrap().as_str();
+
+    let mut out = String::with_capacity(manifest.len());
+    out.push_str(&manifest[..start + whole.start()]);
+    out.push_str(&format!("{}\"{}\"", prefix, version));
+    out.push_str(&manifest[start + whole.end()..]);
+    Some(out)
+}
@@ -0,0 +1,177 @@
+use std::{
+    io,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use crate::{IOS_TRIPLES, MACOS_TRIPLES};
+
+/// Release automation `cargo pod init --workflow` can write.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Workflow {
+    /// A GitHub Actions workflow releasing on tag push.
+    GitHub,
+    /// fastlane lanes wrapping `cargo pod`.
+    Fastlane,
+}
+
+impl FromStr for Workflow {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "github" => Ok(Workflow::GitHub),
+            "fastlane" => Ok(Workflow::Fastlane),
+            other => Err(format!("unsupported workflow: {}", other)),
+        }
+    }
+}
+
+const GITHUB_RELEASE: &str = r#"name: Release
+
+on:
+  push:
+    tags:
+      - "v*"
+
+permissions:
+  contents: write
+
+jobs:
+  release:
+    runs-on: macos-latest
+%DEFAULTS%    steps:
+      - uses: actions/checkout@v4
+      - uses: dtolnay/rust-toolchain@stable
+        with:
+          targets: %TARGETS%
+      - uses: Swatinem/rust-cache@v2
+        with:
+          workspaces: %WORKSPACE%
+      - name: Install cargo-cocoapods
+        run: cargo install cargo-cocoapods --version %VERSION% --locked
+      - run: cargo pod build
+      - run: cargo pod bundle
+      - run: cargo pod publish --tag "${{ github.ref_name }}"
+        env:
+          GITHUB_TOKEN: ${{ secrets.GITHUB_TOKEN }}
+"#;
+
+// Lane code runs in the fastlane directory, so commands change to the crate
+// first. No `platform` block, so the lanes also work when imported.
+const FASTFILE: &str = r#"# Lanes for building and releasing the pod with cargo-pod.
+# Run `fastlane cargo_pod_release tag:v1.2.3` to publish a release.
+
+CARGO_POD_TARGETS = %w[%TARGETS%]
+
+def cargo_pod(*args)
+  Dir.chdir(File.expand_path("../%WORKING_DIR%", Dir.pwd)) do
+    sh("cargo", "pod", *args)
+  end
+end
+
+desc "Install cargo-cocoapods and the Rust targets it builds for"
+lane :cargo_pod_setup do
+  sh("rustup", "target", "add", *CARGO_POD_TARGETS)
+  sh("cargo", "install", "cargo-cocoapods", "--version", "%VERSION%", "--locked")
+end
+
+desc "Build the xcframeworks"
+lane :cargo_pod_build do
+  cargo_pod("build")
+end
+
+desc "Bundle the built pod into an archive"
+lane :cargo_pod_bundle do
+  cargo_pod("bundle")
+end
+
+desc "Publish the bundle. Options: tag, provider"
+lane :cargo_pod_publish do |options|
+  args = ["publish"]
+  args += ["--tag", options[:tag]] if options[:tag]
+  args += ["--provider", options[:provider]] if options[:provider]
+  UI.user_error!("Set GITHUB_TOKEN to publish to GitHub") if options[:provider].nil? && ENV["GITHUB_TOKEN"].to_s.empty?
+  cargo_pod(*args)
+end
+
+desc "Build, bundle and publish a release. Options: tag, provider"
+lane :cargo_pod_release do |options|
+  cargo_pod_build
+  cargo_pod_bundle
+  cargo_pod_publish(options)
+end
+"#;
+
+/// Written instead of the Fastfile when a project already has one.
+const FASTLANE_IMPORT_NAME: &str = "CargoPodFastfile";
+
+impl Workflow {
+    /// Where the workflow file goes, relative to the repository root.
+    pub fn path(&self) -> PathBuf {
+        match self {
+            Workflow::GitHub => Path::new(".github")
+                .join("workflows")
+                .join("cargo-pod-release.yml"),
+            Workflow::Fastlane => Path::new("fastlane").join("Fastfile"),
+        }
+    }
+
+    /// The workflow's contents. `crate_dir` is the crate's directory relative
+    /// to the repository root, such as `crate` for subtree layouts, and
+    /// `working_dir` is where `cargo pod` is run from.
+    pub fn render(&self, crate_dir: &str, working_dir: &str) -> String {
+        let defaults = if working_dir == "." {
+            String::new()
+        } else {
+            format!(
+                "    defaults:\n      run:\n        working-directory: {}\n",
+                working_dir
+            )
+        };
+        let targets = IOS_TRIPLES
+            .iter()
+            .chain(MACOS_TRIPLES.iter())
+            .copied()
+            .collect::<Vec<_>>()
+            .join(",");
+        match self {
+            Workflow::GitHub => GITHUB_RELEASE
+                .replace("%DEFAULTS%", &defaults)
+                .replace("%TARGETS%", &targets)
+                .replace("%WORKSPACE%", crate_dir)
+                .replace("%VERSION%", env!("CARGO_PKG_VERSION")),
+            Workflow::Fastlane => FASTFILE
+                .replace("%TARGETS%", &targets.replace(',', " "))
+                .replace("%WORKING_DIR%", working_dir)
+                .replace("%VERSION%", env!("CARGO_PKG_VERSION")),
+        }
+    }
+
+    /// Writes the workflow under `root`, unless one is already there.
+    pub fn write(&self, root: &Path, crate_dir: &str, working_dir: &str) -> io::Result<()> {
+        let mut path = root.join(self.path());
+        let has_lanes = std::fs::read_to_string(&path)
+            .map(|x| x.contains("lane :cargo_pod_release"))
+            .unwrap_or(false);
+        if path.exists() && *self == Workflow::Fastlane && !has_lanes {
+            // Leave the project's own lanes alone and have them import ours.
+            path = path.with_file_name(FASTLANE_IMPORT_NAME);
+            log::info!(
+                "Add `import \"{}\"` to your Fastfile to use the cargo-pod lanes.",
+                FASTLANE_IMPORT_NAME
+            );
+        }
+        if path.exists() {
+            log::info!("Keeping existing {}", path.display());
+            return Ok(());
+        }
+        if crate::cmd::is_dry_run() {
+            log::info!("Would write {}", path.display());
+            return Ok(());
+        }
+        std::fs::create_dir_all(path.parent().unwrap())?;
+        log::info!("Writing {}", path.display());
+        std::fs::write(&path, self.render(crate_dir, working_dir))
+    }
+}
@@ -0,0 +1,195 @@
+use std::{
+    io,
+    path::{Path, PathBuf},
+    process::{Command, ExitStatus},
+};
+
+use crate::cmd::{current_sdk, current_triple, MinVersions};
+
+/// Where `cargo pod test` looks for XCTest cases, next to the podspec.
+pub const TESTS_DIR: &str = "Tests";
+
+/// The oldest releases the XCTest shipped with current Xcode supports.
+const MIN_MACOS_VERSION: &str = "11.0";
+const MIN_IOS_VERSION: &str = "14.0";
+
+const INFO_PLIST: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+	<key>CFBundleExecutable</key>
+	<string>%NAME%</string>
+	<key>CFBundleIdentifier</key>
+	<string>dev.cargo-pod.%NAME%</string>
+	<key>CFBundleName</key>
+	<string>%NAME%</string>
+	<key>CFBundlePackageType</key>
+	<string>BNDL</string>
+</dict>
+</plist>
+"#;
+
+/// Where a test bundle runs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Platform {
+    MacOS,
+    IosSimulator,
+}
+
+impl Platform {
+    /// The `dist` subdirectory holding this platform's frameworks.
+    pub fn frameworks_dir(&self) -> &'static str {
+        match self {
+            Platform::MacOS => "macos-universal",
+            Platform::IosSimulator => "ios-simulator",
+        }
+    }
+
+    /// The triple matching the machine running the tests.
+    fn host_triple(&self) -> &'static str {
+        match (self, std::env::consts::ARCH) {
+            (Platform::MacOS, "aarch64") => "aarch64-apple-darwin",
+            (Platform::MacOS, _) => "x86_64-apple-darwin",
+            (Platform::IosSimulator, "aarch64") => "aarch64-apple-ios-sim",
+            (Platform::IosSimulator, _) => "x86_64-apple-ios",
+        }
+    }
+
+    fn sdk_name(&self) -> &'static str {
+        match self {
+            Platform::MacOS => "macosx",
+            Platform::IosSimulator => "iphonesimulator",
+        }
+    }
+}
+
+impl std::fmt::Display for Platform {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Platform::MacOS => f.write_str("macOS"),
+            Platform::IosSimulator => f.write_str("the iOS simulator"),
+        }
+    }
+}
+
+/// The platform directory holding XCTest and its runner.
+fn platform_path(platform: Platform) -> io::Result<PathBuf> {
+    let mut cmd = Command::new("xcrun");
+    cmd.args(["--sdk", platform.sdk_name(), "--show-sdk-platform-path"]);
+    let output = crate::cmd::output(&mut cmd)?;
+    if !output.status.success() {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("No {} platform found", platform.sdk_name()),
+        ));
+    }
+    Ok(PathBuf::from(
+        String::from_utf8_lossy(&output.stdout).trim().to_string(),
+    ))
+}
+
+/// Compiles `tests` into `<name>.xctest` inside `out_dir`, linked against the
+/// `modules` frameworks in `frameworks_dir`.
+pub fn build(
+    platform: Platform,
+    name: &str,
+    frameworks_dir: &Path,
+    modules: &[String],
+    tests: &[PathBuf],
+    out_dir: &Path,
+) -> io::Result<PathBuf> {
+    let bundle = out_dir.join(format!("{}.xctest", name));
+    let contents = match platform {
+        Platform::MacOS => bundle.join("Contents"),
+        Platform::IosSimulator => bundle.clone(),
+    };
+    let executable = match platform {
+        Platform::MacOS => contents.join("MacOS").join(name),
+        Platform::IosSimulator => contents.join(name),
+    };
+    std::fs::create_dir_all(executable.parent().unwrap())?;
+    std::fs::write(
+        contents.join("Info.plist"),
+        INFO_PLIST.replace("%NAME%", name),
+    )?;
+
+    let developer = platform_path(platform)?.join("Developer");
+    let xctest_frameworks = developer.join("Library").join("Frameworks");
+    let xctest_libs = developer.join("usr").join("lib");
+    let min_versions = MinVersions {
+        ios: MIN_IOS_VERSION.into(),
+        macos: MIN_MACOS_VERSION.into(),
+    };
+    let triple = platform.host_triple();
+
+    let mut cmd = Command::new("swiftc");
+    cmd.args(["-emit-library", "-Xlinker", "-bundle", "-module-name", name])
+        .arg("-sdk")
+        .arg(current_sdk(triple)?)
+        .arg("-target")
+        .arg(current_triple(triple, &min_versions)?)
+        .arg("-F")
+        .arg(frameworks_dir)
+        .arg("-F")
+        .arg(&xctest_frameworks)
+        .arg("-I")
+        .arg(&xctest_libs)
+        .arg("-L")
+        .arg(&xctest_libs);
+    for path in [&xctest_frameworks, &xctest_libs] {
+        cmd.args(["-Xlinker", "-rpath", "-Xlinker"]).arg(path);
+    }
+    cmd.args(["-framework", "XCTest"]);
+    for module in modules {
+        cmd.args(["-framework", module]);
+    }
+    cmd.args(tests).arg("-o").arg(&executable);
+
+    if crate::cmd::skip(&cmd) {
+        return Ok(bundle);
+    }
+    log::trace!("Calling: {:?}", &cmd);
+    let status = crate::cmd::status(&mut cmd)?;
+    if !status.success() {
+        return Err(io::Error::new(
+            io::ErrorKind::Other,
+            format!("Failed to compile the tests for {}", platform),
+        ));
+    }
+    Ok(bundle)
+}
+
+/// Runs the test bundle, on `simulator` for the iOS simulator.
+pub fn run(platform: Platform, bundle: &Path, simulator: &str) -> io::Result<ExitStatus> {
+    let mut cmd = Command::new("xcrun");
+    match platform {
+        Platform::MacOS => {
+            cmd.arg("xctest").arg(bundle);
+        }
+        Platform::IosSimulator => {
+            let developer = platform_path(platform)?.join("Developer");
+            cmd.args(["simctl", "spawn", simulator])
+                .arg(
+                    developer
+                        .join("Library")
+                        .join("Xcode")
+                        .join("Agents")
+                        .join("xctest"),
+                )
+                .arg(bundle)
+                .env(
+                    "SIMCTL_CHILD_DYLD_FRAMEWORK_PATH",
+                    developer.join("Library").join("Frameworks"),
+                )
+                .env(
+                    "SIMCTL_CHILD_DYLD_LIBRARY_PATH",
+                    developer.join("usr").join("lib"),
+                );
+        }
+    }
+    if crate::cmd::skip(&cmd) {
+        return Ok(ExitStatus::default());
+    }
+    log::trace!("Calling: {:?}", &cmd);
+    crate::cmd::status(&mut cmd)
+}